@@ -7,8 +7,8 @@ use std::time::{Instant};
 use std::io::Write;
 use crdts::crdts::crdt::CRDT;
 use crdts::crdts::rga::{RGAOp, RGA};
-use crdts::bft_crdts::bft_crdt::BFTCRDTHandler;
-use crdts::bft_crdts::bft_rga::{BFTRGAOp, BFTRGA};
+use crdts::bft_crdts::replica::Replica;
+use crdts::bft_crdts::bft_rga::{BFTRGAOp, BFTRGA, LamportId};
 use crdts::bft_crdts::hash_graph::Node;
 
 #[derive(Clone)]
@@ -21,7 +21,7 @@ pub struct Message {
 #[derive(Clone)]
 pub struct BFTMessage {
     pub deps: HashSet<usize>,
-    pub ops: Vec<Node<BFTRGAOp<i32, char>>>,
+    pub ops: Vec<Node<BFTRGAOp<LamportId, char>>>,
     pub txnid: i32,
 }
 
@@ -232,8 +232,8 @@ impl BFTPeer {
         return thread::spawn(move || {
             let mut graph = HashSet::new();
             let mut heads = HashSet::new();
-            let bft_rga = BFTRGA::new();
-            let mut handler = BFTCRDTHandler::new(bft_rga);
+            let bft_rga = BFTRGA::new(id as u64);
+            let mut replica = Replica::new(bft_rga);
             let mut txnid = -1;
             let mut buffer = Vec::new();
             let mut wait_for_parents = HashSet::new();
@@ -257,17 +257,15 @@ impl BFTPeer {
                             let mut operations = Vec::new();
                             for patch in txn.patches {
                                 for _ in 0..patch.1 {
-                                    let delete_op = handler.crdt.delete(patch.0).unwrap();
-                                    let node = handler.handle_local_op(delete_op.clone());
+                                    let delete_op = replica.crdt.delete(patch.0).unwrap();
+                                    let node = replica.handle_local_op(delete_op.clone());
                                     operations.push(node);
                                 }
-                                // it would be enough to guarantee the uniqueness of id using increment integer
-                                // for each character in the patch, we insert a new node with the id i
                                 let chars_count = patch.2.chars().count();
                                 for j in 0..chars_count {
                                     if let Some(s) = patch.2.chars().nth(j) {
-                                        let insert_op = handler.crdt.insert(patch.0 + j, s, txnid).unwrap();
-                                        let node = handler.handle_local_op(insert_op.clone());
+                                        let insert_op = replica.crdt.insert_auto(patch.0 + j, s).unwrap();
+                                        let node = replica.handle_local_op(insert_op.clone());
                                         operations.push(node);
                                     }
                                 }
@@ -288,7 +286,7 @@ impl BFTPeer {
                             
                             // check if we have applied the final txn
                             if number_of_txns_applied == num_of_txns as i32 {
-                                let char_list = handler.crdt.get_list();
+                                let char_list = replica.crdt.get_list();
                                 let result = char_list.iter().map(|c| c.to_string()).collect::<Vec<String>>().join("");
                                 assert_eq!(result.len(), end_content.len());
                                 assert_eq!(result, end_content);
@@ -337,7 +335,7 @@ impl BFTPeer {
 
                             let ops = m.ops.clone();
                             for op in ops {
-                                handler.handle_remote_node(op);
+                                replica.handle_remote_node(op);
                             }
                             graph.insert(m.txnid);
                             heads.insert(m.txnid as usize);
@@ -346,9 +344,9 @@ impl BFTPeer {
                             }
                             
                             if number_of_txns_applied == num_of_txns as i32 {
-                                let char_list = handler.crdt.get_list();
+                                let char_list = replica.crdt.get_list();
                                 let result = char_list.iter().map(|c| c.to_string()).collect::<Vec<String>>().join("");
-                                println!("{:?}", handler.pending_nodes);
+                                println!("{:?}", replica.pending_nodes);
                                 assert_eq!(result.len(), end_content.len());
                                 assert_eq!(result, end_content);
                                 done = true;
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use std::time::{Duration, Instant};
 use std::io::{Write};
-use crdts::bft_crdts::bft_crdt::{BFTCRDTHandler};
+use crdts::bft_crdts::replica::Replica;
 use crdts::bft_crdts::bft_orset::{BFTORSet};
 use crdts::crdts::orset::{ORSet};
 use crate::common::get_output_file;
@@ -50,14 +50,14 @@ pub fn orset_add1() {
     // BFTORSet
     for run in 0..num_runs {
         let bft_orset = BFTORSet::new();
-        let mut handler = BFTCRDTHandler::new(bft_orset);
+        let mut replica = Replica::new(bft_orset);
         let start = Instant::now();
         for i in 0..num_ops {
             let random_content: String = (0..10)
                 .map(|_| rand::random::<u8>() as char)
                 .collect();
-            let addop = handler.crdt.add(random_content);
-            handler.handle_local_op(addop);
+            let addop = replica.crdt.add(random_content);
+            replica.handle_local_op(addop);
         }
         let elapsed = start.elapsed();
         bft_orset_times.push(elapsed / num_ops as u32);
@@ -122,20 +122,20 @@ pub fn orset_remove1() {
     // BFTOrSet
     for run in 0..num_runs {
         let mut bft_orset = BFTORSet::new();
-        let mut handler = BFTCRDTHandler::new(bft_orset);
+        let mut replica = Replica::new(bft_orset);
         let mut id2content = HashMap::new();
         // Pre-populate
         for i in 0..num_ops {
             let random_content: String = (0..10)
                 .map(|_| rand::random::<u8>() as char)
                 .collect();
-            let addop = handler.crdt.add(random_content.clone());
-            handler.handle_local_op(addop);
+            let addop = replica.crdt.add(random_content.clone());
+            replica.handle_local_op(addop);
             id2content.insert(i, random_content);
         }
         let mut ids = vec![];
         for i in 0..num_ops {
-            let orset_id = handler.crdt.get_ids(id2content[&i].clone());
+            let orset_id = replica.crdt.get_ids(id2content[&i].clone());
             // convert from hashset to vec
             let mut ids_vec = vec![];
             for id in orset_id {
@@ -145,8 +145,8 @@ pub fn orset_remove1() {
         }
         let start = Instant::now();
         for i in 0..num_ops {
-            let removeop = handler.crdt.remove(id2content[&i].clone(), ids[i].clone());
-            handler.handle_local_op(removeop);
+            let removeop = replica.crdt.remove(id2content[&i].clone(), ids[i].clone());
+            replica.handle_local_op(removeop);
         }
         let elapsed = start.elapsed();
         bft_orset_times.push(elapsed / num_ops as u32);
@@ -1,6 +1,6 @@
 use std::time::{Duration, Instant};
 use std::io::{Write};
-use crdts::bft_crdts::bft_crdt::{BFTCRDTHandler};
+use crdts::bft_crdts::replica::Replica;
 use crdts::bft_crdts::bft_rga::{BFTRGA};
 use crdts::crdts::crdt::CRDT;
 use crdts::crdts::rga::{RGA};
@@ -51,15 +51,15 @@ pub fn rga_insert1() {
 
     // BFTRGA
     for run in 0..num_runs {
-        let mut bft_rga = BFTRGA::new();
-        let mut handler = BFTCRDTHandler::new(bft_rga);
+        let mut bft_rga = BFTRGA::default();
+        let mut replica = Replica::new(bft_rga);
         let start = Instant::now();
         for i in 0..num_ops {
             let random_content: String = (0..10)
                 .map(|_| rand::random::<u8>() as char)
                 .collect();
-            let insert_op = handler.crdt.insert(i, random_content, format!("id_{}", i)).unwrap();
-            handler.handle_local_op(insert_op);
+            let insert_op = replica.crdt.insert(i, random_content, format!("id_{}", i)).unwrap();
+            replica.handle_local_op(insert_op);
         }
         let elapsed = start.elapsed();
         bft_rga_times.push(elapsed / num_ops as u32);
@@ -123,20 +123,20 @@ pub fn rga_delete1() {
 
     // BFTRGA
     for run in 0..num_runs {
-        let mut bft_rga = BFTRGA::new();
-        let mut handler = BFTCRDTHandler::new(bft_rga);
+        let mut bft_rga = BFTRGA::default();
+        let mut replica = Replica::new(bft_rga);
         // Pre-populate
         for i in 0..num_ops {
             let random_content: String = (0..10)
                 .map(|_| rand::random::<u8>() as char)
                 .collect();
-            let insert_op = handler.crdt.insert(i, random_content, format!("id_{}", i)).unwrap();
-            handler.handle_local_op(insert_op);
+            let insert_op = replica.crdt.insert(i, random_content, format!("id_{}", i)).unwrap();
+            replica.handle_local_op(insert_op);
         }
         let start = Instant::now();
         for i in 0..num_ops {
-            let delete_op = handler.crdt.delete(0).unwrap();
-            handler.handle_local_op(delete_op);
+            let delete_op = replica.crdt.delete(0).unwrap();
+            replica.handle_local_op(delete_op);
         }
         let elapsed = start.elapsed();
         bft_rga_times.push(elapsed / num_ops as u32);
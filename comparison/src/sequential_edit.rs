@@ -1,6 +1,6 @@
 use std::io::Write;
 use std::time::Instant;
-use crdts::bft_crdts::bft_crdt::BFTCRDTHandler;
+use crdts::bft_crdts::replica::Replica;
 use crdts::bft_crdts::bft_rga::BFTRGA;
 use crdts::crdts::crdt::CRDT;
 use crdts::crdts::rga::RGA;
@@ -47,25 +47,23 @@ pub fn sequential_editing(dataset_name: &str) {
     assert_eq!(result.len(), data.end_content.len());
     assert_eq!(result, expected);
 
-    let bft_rga = BFTRGA::new();
-    let mut handler = BFTCRDTHandler::new(bft_rga);
+    let bft_rga = BFTRGA::new(0);
+    let mut replica = Replica::new(bft_rga);
     let start_time = Instant::now();
     count = 0;
-    let mut i = 0;
     for patch in data.patches() {
         println!("bft-rga: {}/{}", count, len);
         count = count + 1;
         for _ in 0..patch.1 {
-            let delete_op = handler.crdt.delete(patch.0);
-            handler.handle_local_op(delete_op.unwrap());
+            let delete_op = replica.crdt.delete(patch.0);
+            replica.handle_local_op(delete_op.unwrap());
         }
         
         let chars_count = patch.2.chars().clone().count();
         for j in 0..chars_count {
             let s = patch.2.chars().nth(j).unwrap();
-            let insert_op = handler.crdt.insert(patch.0 + j, s, i);
-            i = i + 1;
-            handler.handle_local_op(insert_op.unwrap());
+            let insert_op = replica.crdt.insert_auto(patch.0 + j, s);
+            replica.handle_local_op(insert_op.unwrap());
         }
     }
     let elapsed = start_time.elapsed();
@@ -73,7 +71,7 @@ pub fn sequential_editing(dataset_name: &str) {
 
     writeln!(out, "the total number of operation is {:?}", total_op_count).unwrap();
 
-    let char_list = handler.crdt.get_list();
+    let char_list = replica.crdt.get_list();
     let result = char_list.iter().map(|c| c.to_string()).collect::<Vec<String>>().join("");
     
     assert_eq!(result.len(), data.end_content.len());
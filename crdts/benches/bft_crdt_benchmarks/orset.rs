@@ -2,19 +2,19 @@ use criterion::BenchmarkGroup;
 use criterion::measurement::WallTime;
 
 use crdts::bft_crdts::bft_orset::BFTORSet;
-use crdts::bft_crdts::bft_crdt::BFTCRDTHandler;
+use crdts::bft_crdts::replica::Replica;
 
 pub fn bench_bft_orset_add(group: &mut BenchmarkGroup<WallTime>) {
     group.bench_function("BFT-ORSet-Add", |b| {
         let orset = BFTORSet::new();
-        let mut handler = BFTCRDTHandler::new(orset);
+        let mut replica = Replica::new(orset);
         
         let mut i = 0;
         b.iter_with_setup(
             || { i += 1; i },
             |iteration| {
-                let add_op = handler.crdt.add(format!("test_element_{}", iteration));
-                handler.handle_local_op(add_op);
+                let add_op = replica.crdt.add(format!("test_element_{}", iteration));
+                replica.handle_local_op(add_op);
             }
         );
     });
@@ -23,15 +23,15 @@ pub fn bench_bft_orset_add(group: &mut BenchmarkGroup<WallTime>) {
 pub fn bench_bft_orset_remove(group: &mut BenchmarkGroup<WallTime>) {
     group.bench_function("BFT-ORSet-Remove", |b| {
         let orset = BFTORSet::new();
-        let mut handler = BFTCRDTHandler::new(orset);
+        let mut replica = Replica::new(orset);
         
         // Setup: Add elements first
-        let add_op = handler.crdt.add("test_element");
-        handler.handle_local_op(add_op);
+        let add_op = replica.crdt.add("test_element");
+        replica.handle_local_op(add_op);
         
         b.iter(|| {
-            let remove_op = handler.crdt.remove_elem("test_element");
-            handler.handle_local_op(remove_op);
+            let remove_op = replica.crdt.remove_elem("test_element");
+            replica.handle_local_op(remove_op);
         });
     });
 } 
\ No newline at end of file
@@ -1,19 +1,19 @@
 use criterion::BenchmarkGroup;
 use criterion::measurement::WallTime;
 use crdts::bft_crdts::bft_rga::BFTRGA;
-use crdts::bft_crdts::bft_crdt::BFTCRDTHandler;
+use crdts::bft_crdts::replica::Replica;
 
 pub fn bench_bft_rga_insert(group: &mut BenchmarkGroup<WallTime>) {
     group.bench_function("BFT-RGA-Insert", |b| {
-        let rga = BFTRGA::new();
-        let mut handler = BFTCRDTHandler::new(rga);
+        let rga = BFTRGA::default();
+        let mut replica = Replica::new(rga);
         
         let mut i = 0;
         b.iter_with_setup(
             || { i += 1; i },
             |iteration| {
-                let insert_op = handler.crdt.insert(iteration - 1, format!("test_element_{}", iteration), "test").unwrap();
-                handler.handle_local_op(insert_op);
+                let insert_op = replica.crdt.insert(iteration - 1, format!("test_element_{}", iteration), "test").unwrap();
+                replica.handle_local_op(insert_op);
             }
         )
     });
@@ -21,16 +21,16 @@ pub fn bench_bft_rga_insert(group: &mut BenchmarkGroup<WallTime>) {
 
 pub fn bench_bft_rga_delete(group: &mut BenchmarkGroup<WallTime>) {
     group.bench_function("BFT-RGA-Delete", |b| {
-        let rga = BFTRGA::new();
-        let mut handler = BFTCRDTHandler::new(rga);
+        let rga = BFTRGA::default();
+        let mut replica = Replica::new(rga);
 
         // Setup: Insert 1000 elements first
         for i in 0..1000 {
-            let insert_op = handler.crdt.insert(i, format!("test_element_{}", i), "test").unwrap();
-            handler.handle_local_op(insert_op);
+            let insert_op = replica.crdt.insert(i, format!("test_element_{}", i), "test").unwrap();
+            replica.handle_local_op(insert_op);
         }
         
-        let current_list = handler.crdt.get_list();
+        let current_list = replica.crdt.get_list();
         assert_eq!(current_list.len(), 1000);
 
 
@@ -38,8 +38,8 @@ pub fn bench_bft_rga_delete(group: &mut BenchmarkGroup<WallTime>) {
         b.iter_with_setup(
             || { i += 1; i },
             |iteration| {
-                let delete_op = handler.crdt.raw_delete(iteration % 1000).unwrap();
-                handler.handle_local_op(delete_op);
+                let delete_op = replica.crdt.raw_delete(iteration % 1000).unwrap();
+                replica.handle_local_op(delete_op);
             }
         )
     });
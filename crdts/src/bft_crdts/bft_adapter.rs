@@ -0,0 +1,210 @@
+use std::collections::HashSet;
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+use std::marker::PhantomData;
+use crate::bft_crdts::bft_crdt::BFTCRDT;
+use crate::bft_crdts::hash_graph::{GraphView, HashType, Node};
+use crate::crdts::crdt::{CausalOp, CRDT};
+use crate::serialize::Serialize;
+
+//  The BFT adapter turns an op-based CRDT into a BFT CRDT, using only what its operations
+//   declare through `CausalOp`: the IDs an operation introduces and the IDs it depends on.
+//   Each operation is sent with the hashes of the nodes that introduced its dependencies, and
+//   it is valid if these nodes are in its causal past, introduce these IDs, and are accepted
+//   by the operation. The wrapped CRDT therefore only sees operations whose dependencies it
+//   has applied before.
+//
+//   The IDs are chosen by the peers, so a faulty peer could introduce an ID that a concurrent
+//   operation introduces as well. As in the BFT RGA, the wrapped CRDT therefore gets every ID
+//   paired with the hash of the node that introduced it, which makes the IDs unique; an
+//   operation is qualified that way before it is applied, and the hashes of its dependencies
+//   are sent along with it.
+
+/// An operation of the CRDT wrapped by a `BFTAdapter<C, O>`, whose IDs are those of `O`
+/// paired with the hashes of the nodes that introduced them.
+pub type QualifiedOp<O> = <O as CausalOp>::WithIds<(<O as CausalOp>::Id, HashType)>;
+
+// op, hashes of the nodes introducing its dependencies, in the order of `CausalOp::deps`
+#[derive(Debug, Clone)]
+pub struct BFTAdapterOp<O>(pub O, pub Vec<HashType>);
+
+impl<O: Debug> Display for BFTAdapterOp<O> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} after {:?}", self.0, self.1)
+    }
+}
+
+impl<O: Serialize> Serialize for BFTAdapterOp<O> {
+    fn to_bytes(&self) -> Vec<u8> {
+        // the operation is length-prefixed so that it stays apart from the hashes
+        let op_bytes = self.0.to_bytes();
+        let mut bytes = [(op_bytes.len() as u64).to_bytes(), op_bytes].concat();
+        for hash in self.1.iter() {
+            bytes.extend_from_slice(hash.as_bytes());
+        }
+        bytes
+    }
+}
+
+pub struct BFTAdapter<C, O> {
+    crdt: C,
+    op: PhantomData<O>,
+}
+
+impl<C, O> BFTAdapter<C, O>
+where
+    O: CausalOp,
+    O::Id: Eq + Hash + Clone,
+    C: CRDT<QualifiedOp<O>>,
+    QualifiedOp<O>: CausalOp<Id = (O::Id, HashType), WithIds<O::Id> = O>,
+{
+    pub fn new(crdt: C) -> Self {
+        BFTAdapter {
+            crdt,
+            op: PhantomData,
+        }
+    }
+
+    pub fn get(&self) -> &C {
+        &self.crdt
+    }
+
+    /// Generates an operation with the wrapped CRDT, e.g.
+    /// `adapter.generate(|rga| rga.insert(0, "a", (1, HashType::new())))`, and replaces its IDs
+    /// with those of `O` and the hashes of its dependencies. The hashes of the IDs it introduces
+    /// are ignored, since they are the hash of the node the operation ends up in.
+    pub fn generate(&mut self, generate: impl FnOnce(&mut C) -> Option<QualifiedOp<O>>) -> Option<BFTAdapterOp<O>> {
+        let op = generate(&mut self.crdt)?;
+        let mut dep_hashes = vec![];
+        let op = op.map_ids(|(id, _)| id.clone(), |(dep, hash)| {
+            dep_hashes.push(hash.clone());
+            dep.clone()
+        });
+        Some(BFTAdapterOp(op, dep_hashes))
+    }
+}
+
+impl<C, O> Default for BFTAdapter<C, O>
+where
+    O: CausalOp,
+    O::Id: Eq + Hash + Clone,
+    C: CRDT<QualifiedOp<O>> + Default,
+    QualifiedOp<O>: CausalOp<Id = (O::Id, HashType), WithIds<O::Id> = O>,
+{
+    fn default() -> Self {
+        Self::new(C::default())
+    }
+}
+
+impl<C, O> BFTCRDT<BFTAdapterOp<O>> for BFTAdapter<C, O>
+where
+    O: CausalOp + Serialize + Clone,
+    O::Id: Eq + Hash + Clone,
+    C: CRDT<QualifiedOp<O>>,
+    QualifiedOp<O>: Clone,
+{
+    /// The wrapped CRDT does not report changes, so the event is the applied operation.
+    type Event = QualifiedOp<O>;
+
+    fn interpret_node(&mut self, node: &Node<BFTAdapterOp<O>>) -> Vec<QualifiedOp<O>> {
+        let BFTAdapterOp(op, dep_hashes) = &node.value;
+        let hash = node.get_hash();
+        let mut dep_hashes = dep_hashes.iter();
+        let op = op.map_ids(|id| (id.clone(), hash.clone()), |dep| {
+            (dep.clone(), dep_hashes.next().cloned().unwrap_or_default())
+        });
+        self.crdt.interpret_op(&op);
+        vec![op]
+    }
+
+    fn is_sem_valid(&self, node: &Node<BFTAdapterOp<O>>, hash_graph: &dyn GraphView<BFTAdapterOp<O>>) -> bool {
+        let BFTAdapterOp(op, dep_hashes) = &node.value;
+        let deps = op.deps();
+        if deps.len() != dep_hashes.len() {
+            return false;
+        }
+        // each dependency must be introduced by the node named for it, which must be in the
+        // causal past and be accepted by the operation
+        let deps_valid = deps.iter().zip(dep_hashes).all(|(dep, hash)| match hash_graph.get_node(hash) {
+            Some(m) => m.value.0.ids().contains(dep) && op.accepts_dep(&m.value.0) && hash_graph.is_ancestor(hash, node),
+            None => false,
+        });
+        // the IDs of the operation are qualified with the same hash, so they must differ
+        let ids = op.ids();
+        let ids_distinct = ids.iter().collect::<HashSet<_>>().len() == ids.len();
+        deps_valid && ids_distinct
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use crate::bft_crdts::replica::Replica;
+    use crate::crdts::crdt::SetQuery;
+    use crate::crdts::orset::{ORSet, ORSetOp};
+    use crate::crdts::rga::{RGA, RGAOp};
+    use super::*;
+
+    #[test]
+    fn test_bft_adapter_rga() {
+        let mut local = Replica::new(BFTAdapter::new(RGA::new()));
+        let mut nodes = vec![];
+        for (i, v) in ["a", "b", "c"].iter().enumerate() {
+            let op = local.crdt.generate(|rga| rga.insert(i, *v, (i as u64, HashType::new()))).unwrap();
+            nodes.push(local.handle_local_op(op));
+        }
+        let op = local.crdt.generate(|rga| rga.delete(1)).unwrap();
+        assert!(matches!(&op.0, RGAOp::Delete(1)) && op.1 == vec![nodes[1].get_hash()]);
+        nodes.push(local.handle_local_op(op));
+        assert_eq!(local.crdt.get().get_list(), vec!["a", "c"]);
+
+        let mut remote = Replica::new(BFTAdapter::new(RGA::new()));
+        for node in nodes.iter().rev() {
+            remote.handle_remote_node(node.clone());
+        }
+        assert!(remote.pending_nodes.is_empty());
+        assert_eq!(remote.crdt.get().get_list(), vec!["a", "c"]);
+
+        let heads = remote.hash_graph.get_heads().to_vec();
+        let (a, c) = (nodes[0].get_hash(), nodes[2].get_hash());
+        let valid = Node::new(heads.clone(), BFTAdapterOp(RGAOp::Insert(3, "d", Some(2)), vec![c.clone()]));
+        assert!(remote.crdt.is_sem_valid(&valid, &remote.hash_graph));
+        // the dependency must be introduced by the named node, which must be in the causal past
+        let wrong_node = Node::new(heads.clone(), BFTAdapterOp(RGAOp::Insert(3, "d", Some(2)), vec![a.clone()]));
+        assert!(!remote.crdt.is_sem_valid(&wrong_node, &remote.hash_graph));
+        let concurrent = Node::new(vec![a.clone()], BFTAdapterOp(RGAOp::Insert(3, "d", Some(2)), vec![c]));
+        assert!(!remote.crdt.is_sem_valid(&concurrent, &remote.hash_graph));
+        let missing_hash = Node::new(heads.clone(), BFTAdapterOp(RGAOp::Delete(0), vec![]));
+        assert!(!remote.crdt.is_sem_valid(&missing_hash, &remote.hash_graph));
+
+        // IDs are qualified with the hashes of their nodes, so a faulty peer that introduces
+        // an ID twice cannot make replicas that apply the nodes in different orders diverge
+        let x = Node::new(heads.clone(), BFTAdapterOp(RGAOp::Insert(0, "x", None), vec![]));
+        let y = Node::new(heads, BFTAdapterOp(RGAOp::Insert(0, "y", None), vec![]));
+        assert!(remote.crdt.is_sem_valid(&x, &remote.hash_graph) && remote.crdt.is_sem_valid(&y, &remote.hash_graph));
+        local.handle_remote_node(x.clone());
+        local.handle_remote_node(y.clone());
+        remote.handle_remote_node(y);
+        remote.handle_remote_node(x);
+        assert_eq!(local.crdt.get().get_list().len(), 4);
+        assert_eq!(local.crdt.get().get_list(), remote.crdt.get().get_list());
+    }
+
+    #[test]
+    fn test_bft_adapter_orset() {
+        let mut handler = Replica::new(BFTAdapter::new(ORSet::new()));
+        let op = handler.crdt.generate(|set| Some(set.add("a", (1u64, HashType::new())))).unwrap();
+        let add = handler.handle_local_op(op);
+        let op = handler.crdt.generate(|set| Some(set.add("b", (2u64, HashType::new())))).unwrap();
+        handler.handle_local_op(op);
+        assert_eq!(handler.crdt.get().elements(), HashSet::from(["a", "b"]));
+
+        // a Remove may only cancel Adds of the same element
+        let heads = handler.hash_graph.get_heads().to_vec();
+        let other_elem = Node::new(heads, BFTAdapterOp(ORSetOp::Remove("b", vec![1]), vec![add.get_hash()]));
+        assert!(!handler.crdt.is_sem_valid(&other_elem, &handler.hash_graph));
+        let op = handler.crdt.generate(|set| Some(set.remove("a", vec![(1, add.get_hash())]))).unwrap();
+        handler.handle_local_op(op);
+        assert_eq!(handler.crdt.get().elements(), HashSet::from(["b"]));
+    }
+}
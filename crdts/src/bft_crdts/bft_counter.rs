@@ -0,0 +1,401 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+use crate::bft_crdts::bft_crdt::BFTCRDT;
+use crate::bft_crdts::hash_graph::{GraphView, Node};
+use crate::serialize::Serialize;
+
+//  Every counter operation names its author and carries a non-negative amount. The amounts of
+//   each author are summed up separately, and with a per-author cap, an author's contribution
+//   to the value is its sum clamped to the cap. Since sums do not depend on the order in which
+//   nodes are interpreted, neither does the clamped value, even if concurrent increments of an
+//   author exceed its cap together.
+//
+//   The author of an operation is filled in by the peer that issues it, so a cap on its own
+//   limits nothing: a Byzantine peer would just name a new author for every node. A capped
+//   counter is therefore given an authenticator that tells who issued a node, e.g. from a
+//   signature checked against the keys of the peers, and a node is only valid if it names the
+//   author that issued it. A Byzantine peer can then move the counter by no more than its cap.
+
+#[derive(Debug, Clone)]
+pub enum BFTGCounterOp<A> {
+    // author, amount
+    Increment(A, i64),
+}
+
+#[derive(Debug, Clone)]
+pub enum BFTPNCounterOp<A> {
+    // author, amount
+    Increment(A, i64),
+    // author, amount
+    Decrement(A, i64),
+}
+
+impl<A> BFTGCounterOp<A> {
+    pub fn author(&self) -> &A {
+        match self {
+            BFTGCounterOp::Increment(a, _n) => a,
+        }
+    }
+}
+
+impl<A> BFTPNCounterOp<A> {
+    pub fn author(&self) -> &A {
+        match self {
+            BFTPNCounterOp::Increment(a, _n) | BFTPNCounterOp::Decrement(a, _n) => a,
+        }
+    }
+}
+
+impl <A> Display for BFTGCounterOp<A>
+where
+    A: Eq + Hash + Clone + Serialize + Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BFTGCounterOp::Increment(a, n) => {
+                write!(f, "Increment({:?}, {})", a, n)
+            }
+        }
+    }
+}
+
+impl <A> Display for BFTPNCounterOp<A>
+where
+    A: Eq + Hash + Clone + Serialize + Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BFTPNCounterOp::Increment(a, n) => {
+                write!(f, "Increment({:?}, {})", a, n)
+            }
+            BFTPNCounterOp::Decrement(a, n) => {
+                write!(f, "Decrement({:?}, {})", a, n)
+            }
+        }
+    }
+}
+
+impl<A> Serialize for BFTGCounterOp<A>
+where
+    A: Eq + Hash + Clone + Serialize,
+{
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            BFTGCounterOp::Increment(a, n) => {
+                let mut bytes = vec![];
+                bytes.extend_from_slice(&a.to_bytes());
+                bytes.extend_from_slice(&n.to_bytes());
+                bytes
+            }
+        }
+    }
+}
+
+impl<A> Serialize for BFTPNCounterOp<A>
+where
+    A: Eq + Hash + Clone + Serialize,
+{
+    fn to_bytes(&self) -> Vec<u8> {
+        // the tag keeps an increment and a decrement with the same arguments apart
+        let (tag, a, n) = match self {
+            BFTPNCounterOp::Increment(a, n) => (0u8, a, n),
+            BFTPNCounterOp::Decrement(a, n) => (1u8, a, n),
+        };
+        let mut bytes = vec![tag];
+        bytes.extend_from_slice(&a.to_bytes());
+        bytes.extend_from_slice(&n.to_bytes());
+        bytes
+    }
+}
+
+/// A change to the value of a counter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BFTCounterEvent {
+    Changed(i64),
+}
+
+/// Tells who issued a node, or `None` if the node cannot be authenticated.
+pub type Authenticator<O, A> = Box<dyn Fn(&Node<O>) -> Option<A>>;
+
+// The sums of the amounts of each author.
+struct Tally<A> {
+    sums: HashMap<A, i64>,
+    cap: Option<i64>,
+}
+
+impl<A: Eq + Hash + Clone> Tally<A> {
+    fn new(cap: Option<i64>) -> Self {
+        Tally {
+            sums: HashMap::new(),
+            cap,
+        }
+    }
+
+    // amounts are never negative, nor above the cap if there is one
+    fn is_amount_valid(&self, n: i64) -> bool {
+        n >= 0 && self.cap.is_none_or(|cap| n <= cap)
+    }
+
+    fn add(&mut self, author: &A, n: i64) {
+        let sum = self.sums.entry(author.clone()).or_default();
+        *sum = sum.saturating_add(n);
+    }
+
+    fn contribution(&self, author: &A) -> i64 {
+        let sum = self.sums.get(author).copied().unwrap_or_default();
+        self.cap.map_or(sum, |cap| sum.min(cap))
+    }
+
+    fn value(&self) -> i64 {
+        self.sums.keys().fold(0i64, |acc, a| acc.saturating_add(self.contribution(a)))
+    }
+}
+
+// without an authenticator, the author of a node is taken as it is named
+fn is_issued_by<O: Serialize + Clone, A: Eq>(authenticate: &Option<Authenticator<O, A>>, node: &Node<O>, author: &A) -> bool {
+    authenticate.as_ref().is_none_or(|authenticate| authenticate(node).as_ref() == Some(author))
+}
+
+pub struct BFTGCounter<A>
+where
+    A: Eq + Hash + Clone + Serialize,
+{
+    tally: Tally<A>,
+    authenticate: Option<Authenticator<BFTGCounterOp<A>, A>>,
+}
+
+impl<A> BFTGCounter<A>
+where
+    A: Eq + Hash + Clone + Serialize,
+{
+    pub fn new() -> Self {
+        BFTGCounter {
+            tally: Tally::new(None),
+            authenticate: None,
+        }
+    }
+
+    /// Creates a counter to which each author contributes at most `cap`, and which only
+    /// accepts nodes that name the author `authenticate` tells for them.
+    pub fn with_author_cap(cap: i64, authenticate: impl Fn(&Node<BFTGCounterOp<A>>) -> Option<A> + 'static) -> Self {
+        BFTGCounter {
+            tally: Tally::new(Some(cap)),
+            authenticate: Some(Box::new(authenticate)),
+        }
+    }
+
+    pub fn increment(&mut self, author: A, n: i64) -> BFTGCounterOp<A> {
+        BFTGCounterOp::Increment(author, n)
+    }
+
+    pub fn get_value(&self) -> i64 {
+        self.tally.value()
+    }
+
+    pub fn get_contribution(&self, author: &A) -> i64 {
+        self.tally.contribution(author)
+    }
+}
+
+impl<A> Default for BFTGCounter<A>
+where
+    A: Eq + Hash + Clone + Serialize,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A> BFTCRDT<BFTGCounterOp<A>> for BFTGCounter<A>
+where
+    A: Eq + Hash + Clone + Serialize,
+{
+    type Event = BFTCounterEvent;
+
+    fn interpret_node(&mut self, node: &Node<BFTGCounterOp<A>>) -> Vec<BFTCounterEvent> {
+        let before = self.get_value();
+        match &node.value {
+            BFTGCounterOp::Increment(a, n) => self.tally.add(a, *n),
+        }
+        let after = self.get_value();
+        if after == before { vec![] } else { vec![BFTCounterEvent::Changed(after)] }
+    }
+
+    fn is_sem_valid(&self, node: &Node<BFTGCounterOp<A>>, _hash_graph: &dyn GraphView<BFTGCounterOp<A>>) -> bool {
+        match &node.value {
+            BFTGCounterOp::Increment(a, n) => self.tally.is_amount_valid(*n) && is_issued_by(&self.authenticate, node, a),
+        }
+    }
+}
+
+pub struct BFTPNCounter<A>
+where
+    A: Eq + Hash + Clone + Serialize,
+{
+    increments: Tally<A>,
+    decrements: Tally<A>,
+    authenticate: Option<Authenticator<BFTPNCounterOp<A>, A>>,
+}
+
+impl<A> BFTPNCounter<A>
+where
+    A: Eq + Hash + Clone + Serialize,
+{
+    pub fn new() -> Self {
+        BFTPNCounter {
+            increments: Tally::new(None),
+            decrements: Tally::new(None),
+            authenticate: None,
+        }
+    }
+
+    /// Creates a counter to which each author contributes at most `cap` in either direction,
+    /// and which only accepts nodes that name the author `authenticate` tells for them.
+    pub fn with_author_cap(cap: i64, authenticate: impl Fn(&Node<BFTPNCounterOp<A>>) -> Option<A> + 'static) -> Self {
+        BFTPNCounter {
+            increments: Tally::new(Some(cap)),
+            decrements: Tally::new(Some(cap)),
+            authenticate: Some(Box::new(authenticate)),
+        }
+    }
+
+    pub fn increment(&mut self, author: A, n: i64) -> BFTPNCounterOp<A> {
+        BFTPNCounterOp::Increment(author, n)
+    }
+
+    pub fn decrement(&mut self, author: A, n: i64) -> BFTPNCounterOp<A> {
+        BFTPNCounterOp::Decrement(author, n)
+    }
+
+    pub fn get_value(&self) -> i64 {
+        self.increments.value().saturating_sub(self.decrements.value())
+    }
+
+    pub fn get_contribution(&self, author: &A) -> i64 {
+        self.increments.contribution(author).saturating_sub(self.decrements.contribution(author))
+    }
+}
+
+impl<A> Default for BFTPNCounter<A>
+where
+    A: Eq + Hash + Clone + Serialize,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A> BFTCRDT<BFTPNCounterOp<A>> for BFTPNCounter<A>
+where
+    A: Eq + Hash + Clone + Serialize,
+{
+    type Event = BFTCounterEvent;
+
+    fn interpret_node(&mut self, node: &Node<BFTPNCounterOp<A>>) -> Vec<BFTCounterEvent> {
+        let before = self.get_value();
+        match &node.value {
+            BFTPNCounterOp::Increment(a, n) => self.increments.add(a, *n),
+            BFTPNCounterOp::Decrement(a, n) => self.decrements.add(a, *n),
+        }
+        let after = self.get_value();
+        if after == before { vec![] } else { vec![BFTCounterEvent::Changed(after)] }
+    }
+
+    fn is_sem_valid(&self, node: &Node<BFTPNCounterOp<A>>, _hash_graph: &dyn GraphView<BFTPNCounterOp<A>>) -> bool {
+        match &node.value {
+            BFTPNCounterOp::Increment(a, n) => self.increments.is_amount_valid(*n) && is_issued_by(&self.authenticate, node, a),
+            BFTPNCounterOp::Decrement(a, n) => self.decrements.is_amount_valid(*n) && is_issued_by(&self.authenticate, node, a),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bft_crdts::hash_graph::HashType;
+    use crate::bft_crdts::replica::Replica;
+    use rand::SeedableRng;
+    use rand::seq::SliceRandom;
+    use rand_pcg::Pcg32;
+    use super::*;
+
+    #[test]
+    fn test_bft_counters_under_reordering() {
+        // local operations are not validated, so the local counter needs no authenticator
+        let mut local = Replica::new(BFTPNCounter::with_author_cap(10, |_| None));
+        // the issuer of every node, e.g. as known from its signature
+        let mut issuers: HashMap<HashType, &str> = HashMap::new();
+        let mut nodes = vec![];
+        for (author, n) in [("a", 4), ("b", 3), ("a", 5), ("a", 6), ("b", 2)] {
+            let op = local.crdt.increment(author, n);
+            nodes.push(local.handle_local_op(op));
+            issuers.insert(nodes.last().unwrap().get_hash(), author);
+        }
+        let op = local.crdt.decrement("b", 7);
+        nodes.push(local.handle_local_op(op));
+        issuers.insert(nodes.last().unwrap().get_hash(), "b");
+        // "a" is clamped to 10, "b" contributes 5 - 7
+        assert_eq!(local.crdt.get_contribution(&"a"), 10);
+        assert_eq!(local.crdt.get_value(), 8);
+
+        let authenticated = |issuers: &HashMap<HashType, &'static str>| {
+            let issuers = issuers.clone();
+            BFTPNCounter::with_author_cap(10, move |node: &Node<BFTPNCounterOp<&str>>| issuers.get(&node.get_hash()).copied())
+        };
+        let mut rng = Pcg32::seed_from_u64(7);
+        for _ in 0..10 {
+            nodes.shuffle(&mut rng);
+            let mut remote = Replica::new(authenticated(&issuers));
+            for node in nodes.iter() {
+                remote.handle_remote_node(node.clone());
+            }
+            assert!(remote.pending_nodes.is_empty());
+            assert_eq!(remote.crdt.get_value(), 8);
+        }
+
+        // negative amounts and amounts above the cap are never valid, and neither are nodes
+        // that name another author than their issuer or cannot be authenticated
+        let ops = [
+            (BFTPNCounterOp::Increment("c", -1), Some("c")),
+            (BFTPNCounterOp::Decrement("c", 11), Some("c")),
+            (BFTPNCounterOp::Increment("d", 10), Some("c")),
+            (BFTPNCounterOp::Increment("d", 10), None),
+            (BFTPNCounterOp::Increment("c", 1), Some("c")),
+        ];
+        let nodes: Vec<_> = ops.into_iter().map(|(op, issuer)| {
+            let node = Node::new(vec![], op);
+            if let Some(issuer) = issuer {
+                issuers.insert(node.get_hash(), issuer);
+            }
+            node
+        }).collect();
+        let mut remote = Replica::new(authenticated(&issuers));
+        let events = remote.record_events();
+        for node in nodes {
+            remote.handle_remote_node(node);
+        }
+        assert_eq!(remote.crdt.get_value(), 1);
+        assert_eq!(*events.borrow(), vec![BFTCounterEvent::Changed(1)]);
+    }
+
+    #[test]
+    fn test_bft_gcounter_handler() {
+        let mut handler = Replica::new(BFTGCounter::new());
+        for (author, n) in [("a", 1), ("b", 2), ("a", 3)] {
+            let op = handler.crdt.increment(author, n);
+            handler.handle_local_op(op);
+        }
+        assert_eq!(handler.crdt.get_value(), 6);
+        assert_eq!(handler.crdt.get_contribution(&"a"), 4);
+        assert_eq!(handler.crdt.get_contribution(&"c"), 0);
+
+        // a capped counter only counts the nodes of authenticated authors
+        let authenticate = |node: &Node<BFTGCounterOp<&'static str>>| Some(*node.value.author()).filter(|a| *a != "mallory");
+        let mut remote = Replica::new(BFTGCounter::with_author_cap(2, authenticate));
+        for op in [BFTGCounterOp::Increment("a", 2), BFTGCounterOp::Increment("mallory", 2), BFTGCounterOp::Increment("a", 1)] {
+            remote.handle_remote_node(Node::new(vec![], op));
+        }
+        assert_eq!(remote.crdt.get_value(), 2);
+        assert_eq!(remote.crdt.get_contribution(&"mallory"), 0);
+    }
+}
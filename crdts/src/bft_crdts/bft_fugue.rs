@@ -0,0 +1,367 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+use crate::bft_crdts::hash_graph::{GraphView, HashType, Node};
+use crate::bft_crdts::bft_crdt::BFTCRDT;
+use crate::crdts::order_statistic_tree::{NodeId, OrderStatisticTree, Weighted};
+use crate::serialize::Serialize;
+
+//  Fugue (Weidner and Kleppmann, "The Art of the Fugue") arranges the elements of a list in a
+//   tree: every element is either a left or a right child of its parent element, or a right
+//   child of the virtual root, and the list is the in-order traversal of the tree, where the
+//   children on each side are visited in the order of their IDs. A peer inserting between two
+//   elements makes the new element a right child of the left one, unless the left one already
+//   has right children, in which case the element becomes a left child of the right one.
+//   Elements typed one after another thus form a chain of right children, and a concurrent run
+//   typed at the same position ends up in a different subtree, so the runs never interleave.
+//   Since the position of an element only depends on its parent and side, we can use the hash
+//   of the node containing the Insert operation as the element ID.
+
+type FugueID = HashType; // in BFT Fugue, ID is the hash value of the element's Insert operation
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone)]
+pub enum BFTFugueOp<V> {
+    // v, parent (None for the root), side
+    Insert(V, Option<FugueID>, Side),
+    // ei
+    Delete(FugueID),
+}
+
+impl <V> Display for BFTFugueOp<V>
+where
+    V: Eq + Hash + Clone + Serialize + Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BFTFugueOp::Insert(v, parent, side) => {
+                write!(f, "Insert({:?}, {:?}, {:?})", v, parent, side)
+            }
+            BFTFugueOp::Delete(id) => {
+                write!(f, "Delete({:?})", id)
+            }
+        }
+    }
+}
+
+impl<V> Serialize for BFTFugueOp<V>
+where
+    V: Eq + Hash + Clone + Serialize,
+{
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            BFTFugueOp::Insert(v, parent, side) => {
+                let mut bytes = vec![];
+                bytes.extend_from_slice(&v.to_bytes());
+                if let Some(parent) = parent {
+                    bytes.extend_from_slice(parent.as_bytes());
+                }
+                bytes.push(*side as u8);
+                bytes
+            }
+            BFTFugueOp::Delete(id) => {
+                let mut bytes = vec![];
+                bytes.extend_from_slice(id.as_bytes());
+                bytes
+            }
+        }
+    }
+}
+
+/// A change to the visible contents of a `BFTFugue`, by index into the list as it was right
+/// before the change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BFTFugueEvent<V> {
+    Insert(usize, V),
+    Delete(usize, V),
+}
+
+// An element in traversal order, as stored in the order-statistic tree.
+struct Element<V> {
+    id: FugueID,
+    value: V,
+    deleted: bool,
+}
+
+impl<V> Weighted for Element<V> {
+    fn len(&self) -> usize {
+        1
+    }
+
+    fn visible_len(&self) -> usize {
+        !self.deleted as usize
+    }
+}
+
+// The position of an element in the Fugue tree; children are sorted by ID.
+struct TreeEntry {
+    slot: NodeId,
+    left_children: Vec<FugueID>,
+    right_children: Vec<FugueID>,
+}
+
+pub struct BFTFugue<V>
+where
+    V: Eq + Hash + Clone + Serialize,
+{
+    elements: OrderStatisticTree<Element<V>>,
+    tree: HashMap<FugueID, TreeEntry>,
+    // the right children of the virtual root
+    root_children: Vec<FugueID>,
+}
+
+impl <V> BFTCRDT<BFTFugueOp<V>> for BFTFugue<V>
+where
+    V: Eq + Hash + Clone + Serialize,
+{
+    type Event = BFTFugueEvent<V>;
+
+    fn interpret_node(&mut self, node: &Node<BFTFugueOp<V>>) -> Vec<BFTFugueEvent<V>> {
+        let op = &node.value;
+        match op {
+            BFTFugueOp::Insert(value, parent, side) => {
+                let id = node.get_hash();
+                if self.tree.contains_key(&id) {
+                    return vec![];
+                }
+                let slot = match self.insert_into_tree(id, parent.as_ref(), *side, value.clone()) {
+                    Some(slot) => slot,
+                    None => return vec![],
+                };
+                let (_, idx) = self.elements.rank(slot);
+                vec![BFTFugueEvent::Insert(idx, value.clone())]
+            }
+            BFTFugueOp::Delete(id) => {
+                let slot = match self.tree.get(id) {
+                    Some(entry) => entry.slot,
+                    None => return vec![],
+                };
+                if self.elements.get(slot).deleted {
+                    return vec![];
+                }
+                let (_, idx) = self.elements.rank(slot);
+                let value = self.elements.update(slot, |e| {
+                    e.deleted = true;
+                    e.value.clone()
+                });
+                vec![BFTFugueEvent::Delete(idx, value)]
+            }
+        }
+    }
+
+    fn is_sem_valid(&self, node: &Node<BFTFugueOp<V>>, hash_graph: &dyn GraphView<BFTFugueOp<V>>) -> bool {
+        let op = &node.value;
+        match op {
+            // An Insert without a parent is a right child of the root; otherwise its parent
+            // must be an Insert in the causal past of the node.
+            BFTFugueOp::Insert(_v, parent, side) => {
+                match parent {
+                    Some(parent) => self.is_inserted_ancestor(parent, node, hash_graph),
+                    None => *side == Side::Right,
+                }
+            }
+            // A Delete must refer to an Insert in the causal past of the node.
+            BFTFugueOp::Delete(id) => self.is_inserted_ancestor(id, node, hash_graph),
+        }
+    }
+}
+
+impl<V> Default for BFTFugue<V>
+where
+    V: Eq + Hash + Clone + Serialize,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> BFTFugue<V>
+where
+    V: Eq + Hash + Clone + Serialize,
+{
+    pub fn new() -> Self {
+        BFTFugue {
+            elements: OrderStatisticTree::new(),
+            tree: HashMap::new(),
+            root_children: vec![],
+        }
+    }
+
+    // whether `id` is the hash of an Insert node in the causal past of `node`
+    fn is_inserted_ancestor(&self, id: &FugueID, node: &Node<BFTFugueOp<V>>, hash_graph: &dyn GraphView<BFTFugueOp<V>>) -> bool {
+        match hash_graph.get_node(id) {
+            Some(ref_node) => {
+                matches!(ref_node.value, BFTFugueOp::Insert(..)) && hash_graph.is_ancestor(id, node)
+            }
+            None => false,
+        }
+    }
+
+    fn children(&self, parent: Option<&FugueID>, side: Side) -> Option<&Vec<FugueID>> {
+        match (parent, side) {
+            (None, Side::Left) => None,
+            (None, Side::Right) => Some(&self.root_children),
+            (Some(p), Side::Left) => self.tree.get(p).map(|e| &e.left_children),
+            (Some(p), Side::Right) => self.tree.get(p).map(|e| &e.right_children),
+        }
+    }
+
+    // the first element of the subtree rooted at `id` in traversal order
+    fn leftmost(&self, id: &FugueID) -> NodeId {
+        let mut entry = &self.tree[id];
+        while let Some(first) = entry.left_children.first() {
+            entry = &self.tree[first];
+        }
+        entry.slot
+    }
+
+    // the last element of the subtree rooted at `id` (or the virtual root) in traversal order
+    fn rightmost(&self, id: Option<&FugueID>) -> Option<NodeId> {
+        let mut entry = match id {
+            Some(id) => &self.tree[id],
+            None => &self.tree[self.root_children.last()?],
+        };
+        while let Some(last) = entry.right_children.last() {
+            entry = &self.tree[last];
+        }
+        Some(entry.slot)
+    }
+
+    fn insert_into_tree(&mut self, id: FugueID, parent: Option<&FugueID>, side: Side, value: V) -> Option<NodeId> {
+        let siblings = self.children(parent, side)?;
+        let pos = siblings.binary_search(&id).unwrap_or_else(|pos| pos);
+        // the element goes right before the subtree of its next sibling, or else right before
+        // its parent (left child) or right after the subtree of its parent (right child)
+        let anchor = match (siblings.get(pos), side) {
+            (Some(next), _) => Some(self.leftmost(next)),
+            (None, Side::Left) => Some(self.tree[parent?].slot),
+            (None, Side::Right) => match self.rightmost(parent) {
+                Some(last) => self.elements.next(last),
+                None => None,
+            },
+        };
+        let slot = self.elements.insert_before(anchor, Element { id: id.clone(), value, deleted: false });
+        let siblings = match (parent, side) {
+            (None, _) => &mut self.root_children,
+            (Some(p), Side::Left) => &mut self.tree.get_mut(p)?.left_children,
+            (Some(p), Side::Right) => &mut self.tree.get_mut(p)?.right_children,
+        };
+        siblings.insert(pos, id.clone());
+        self.tree.insert(id, TreeEntry { slot, left_children: vec![], right_children: vec![] });
+        Some(slot)
+    }
+
+    pub fn get(&self, idx: usize) -> Option<V> {
+        let (slot, _) = self.elements.find_visible(idx)?;
+        Some(self.elements.get(slot).value.clone())
+    }
+
+    pub fn get_list(&self) -> Vec<V> {
+        self.elements.iter().filter(|e| !e.deleted).map(|e| e.value.clone()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.elements.visible_len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn insert(&mut self, idx: usize, value: V) -> Option<BFTFugueOp<V>> {
+        if idx > self.len() {
+            return None;
+        }
+        // the left origin is the visible element before the insertion point, or the root
+        let left = match idx.checked_sub(1) {
+            Some(i) => Some(self.elements.find_visible(i)?.0),
+            None => None,
+        };
+        let left_id = left.map(|slot| self.elements.get(slot).id.clone());
+        let has_right_children = self.children(left_id.as_ref(), Side::Right).is_some_and(|c| !c.is_empty());
+        if !has_right_children {
+            return Some(BFTFugueOp::Insert(value, left_id, Side::Right));
+        }
+        // the next element (possibly deleted) is the leftmost descendant of the first right
+        // child, so it has no left children yet
+        let right = match left {
+            Some(slot) => self.elements.next(slot)?,
+            None => self.elements.first()?,
+        };
+        Some(BFTFugueOp::Insert(value, Some(self.elements.get(right).id.clone()), Side::Left))
+    }
+
+    pub fn delete(&mut self, idx: usize) -> Option<BFTFugueOp<V>> {
+        let (slot, _) = self.elements.find_visible(idx)?;
+        Some(BFTFugueOp::Delete(self.elements.get(slot).id.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bft_crdts::replica::Replica;
+    use super::*;
+
+    #[test]
+    fn test_bft_fugue_handler() {
+        let mut handler = Replica::new(BFTFugue::new());
+        for (i, v) in ["a", "c", "d"].iter().enumerate() {
+            let insert_op = handler.crdt.insert(i, *v).unwrap();
+            handler.handle_local_op(insert_op);
+        }
+        let insert_op = handler.crdt.insert(1, "b").unwrap();
+        assert!(matches!(insert_op, BFTFugueOp::Insert(_, Some(_), Side::Left)));
+        handler.handle_local_op(insert_op);
+        let insert_op = handler.crdt.insert(0, "_").unwrap();
+        handler.handle_local_op(insert_op);
+        assert_eq!(handler.crdt.get_list(), vec!["_", "a", "b", "c", "d"]);
+
+        let delete_op = handler.crdt.delete(3).unwrap();
+        handler.handle_local_op(delete_op);
+        assert_eq!(handler.crdt.get_list(), vec!["_", "a", "b", "d"]);
+        assert!(handler.crdt.insert(5, "e").is_none());
+
+        // only the root may have no parent, and it has no left children
+        let root_left = Node::new(vec![], BFTFugueOp::Insert("x", None, Side::Left));
+        assert!(!handler.crdt.is_sem_valid(&root_left, &handler.hash_graph));
+    }
+
+    #[test]
+    fn test_bft_fugue_does_not_interleave() {
+        let mut a = Replica::new(BFTFugue::new());
+        let mut b = Replica::new(BFTFugue::new());
+        for (i, v) in ["[", "]"].iter().enumerate() {
+            let insert_op = a.crdt.insert(i, *v).unwrap();
+            let node = a.handle_local_op(insert_op);
+            b.handle_remote_node(node);
+        }
+
+        // both replicas type a word between the brackets concurrently
+        let mut a_nodes = vec![];
+        for (i, v) in ["a", "b", "c"].iter().enumerate() {
+            let insert_op = a.crdt.insert(1 + i, *v).unwrap();
+            a_nodes.push(a.handle_local_op(insert_op));
+        }
+        let mut b_nodes = vec![];
+        for (i, v) in ["x", "y", "z"].iter().enumerate() {
+            let insert_op = b.crdt.insert(1 + i, *v).unwrap();
+            b_nodes.push(b.handle_local_op(insert_op));
+        }
+        // deliver in reverse so that the nodes have to wait for their parents
+        for node in a_nodes.into_iter().rev() {
+            b.handle_remote_node(node);
+        }
+        for node in b_nodes.into_iter().rev() {
+            a.handle_remote_node(node);
+        }
+
+        assert_eq!(a.crdt.get_list(), b.crdt.get_list());
+        let text = a.crdt.get_list().concat();
+        assert!(text == "[abcxyz]" || text == "[xyzabc]", "interleaved: {}", text);
+    }
+}
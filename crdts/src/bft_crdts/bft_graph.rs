@@ -0,0 +1,341 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+use crate::bft_crdts::bft_crdt::BFTCRDT;
+use crate::bft_crdts::hash_graph::{GraphView, HashType, Node};
+use crate::serialize::Serialize;
+
+//  A directed graph whose vertices and edges are both BFT ORSets: the ID of an Add is the hash
+//   of the node containing it, and a Remove lists the IDs of the Adds it has observed. An edge
+//   Add also names one Add ID of each endpoint, which must be in its causal past, so a peer can
+//   only connect vertices it has seen. The edge is part of the graph while both of these vertex
+//   Adds are, which makes it disappear when an endpoint is removed, including when the removal
+//   is concurrent with the edge. Adding the vertex again does not bring the edge back.
+
+type GraphID = HashType; // in BFT graph, ID is the hash value of the Add operation
+
+#[derive(Debug, Clone)]
+pub enum BFTGraphOp<V> {
+    AddVertex(V),
+    // v, ids
+    RemoveVertex(V, Vec<GraphID>),
+    // from, to, id of the Add of from, id of the Add of to
+    AddEdge(V, V, GraphID, GraphID),
+    // from, to, ids
+    RemoveEdge(V, V, Vec<GraphID>),
+}
+
+impl <V> Display for BFTGraphOp<V>
+where
+    V: Eq + Hash + Clone + Serialize + Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BFTGraphOp::AddVertex(v) => {
+                write!(f, "AddVertex({:?})", v)
+            }
+            BFTGraphOp::RemoveVertex(v, ids) => {
+                write!(f, "RemoveVertex({:?}, {:?})", v, ids)
+            }
+            BFTGraphOp::AddEdge(from, to, from_id, to_id) => {
+                write!(f, "AddEdge({:?}, {:?}, {:?}, {:?})", from, to, from_id, to_id)
+            }
+            BFTGraphOp::RemoveEdge(from, to, ids) => {
+                write!(f, "RemoveEdge({:?}, {:?}, {:?})", from, to, ids)
+            }
+        }
+    }
+}
+
+impl<V> Serialize for BFTGraphOp<V>
+where
+    V: Eq + Hash + Clone + Serialize,
+{
+    fn to_bytes(&self) -> Vec<u8> {
+        // vertices are length-prefixed so that the two endpoints of an edge stay apart
+        let vertex = |v: &V| {
+            let bytes = v.to_bytes();
+            [(bytes.len() as u64).to_bytes(), bytes].concat()
+        };
+        let ids_bytes = |ids: &Vec<GraphID>| {
+            let mut sorted_ids = ids.clone();
+            sorted_ids.sort();
+            sorted_ids.concat().into_bytes()
+        };
+        match self {
+            BFTGraphOp::AddVertex(v) => {
+                [vec![0u8], vertex(v)].concat()
+            }
+            BFTGraphOp::RemoveVertex(v, ids) => {
+                [vec![1u8], vertex(v), ids_bytes(ids)].concat()
+            }
+            BFTGraphOp::AddEdge(from, to, from_id, to_id) => {
+                [vec![2u8], vertex(from), vertex(to), from_id.to_bytes(), to_id.to_bytes()].concat()
+            }
+            BFTGraphOp::RemoveEdge(from, to, ids) => {
+                [vec![3u8], vertex(from), vertex(to), ids_bytes(ids)].concat()
+            }
+        }
+    }
+}
+
+/// A change to the vertices or edges of a `BFTGraph`. Removing a vertex reports the removal
+/// of its edges first.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum BFTGraphEvent<V> {
+    VertexAdded(V),
+    VertexRemoved(V),
+    EdgeAdded(V, V),
+    EdgeRemoved(V, V),
+}
+
+pub struct BFTGraph<V>
+where
+    V: Eq + Hash + Clone + Serialize,
+{
+    vertices: HashMap<V, HashSet<GraphID>>,
+    // the Adds of each edge that have not been removed, with the vertex Adds they connect
+    edges: HashMap<(V, V), HashMap<GraphID, (GraphID, GraphID)>>,
+    // the edges of each vertex, in either direction
+    incident: HashMap<V, HashSet<(V, V)>>,
+}
+
+impl<V> BFTGraph<V>
+where
+    V: Eq + Hash + Clone + Serialize,
+{
+    pub fn new() -> Self {
+        BFTGraph {
+            vertices: HashMap::new(),
+            edges: HashMap::new(),
+            incident: HashMap::new(),
+        }
+    }
+
+    pub fn add_vertex(&mut self, v: V) -> BFTGraphOp<V> {
+        BFTGraphOp::AddVertex(v)
+    }
+
+    pub fn remove_vertex(&mut self, v: V) -> BFTGraphOp<V> {
+        let ids = self.vertices.get(&v).map(|ids| ids.iter().cloned().collect()).unwrap_or_default();
+        BFTGraphOp::RemoveVertex(v, ids)
+    }
+
+    /// Returns `None` unless both endpoints are in the graph.
+    pub fn add_edge(&mut self, from: V, to: V) -> Option<BFTGraphOp<V>> {
+        let from_id = self.vertices.get(&from)?.iter().min()?.clone();
+        let to_id = self.vertices.get(&to)?.iter().min()?.clone();
+        Some(BFTGraphOp::AddEdge(from, to, from_id, to_id))
+    }
+
+    pub fn remove_edge(&mut self, from: V, to: V) -> BFTGraphOp<V> {
+        let ids = self.edges.get(&(from.clone(), to.clone())).map(|adds| adds.keys().cloned().collect()).unwrap_or_default();
+        BFTGraphOp::RemoveEdge(from, to, ids)
+    }
+
+    pub fn contains_vertex(&self, v: &V) -> bool {
+        self.vertices.get(v).is_some_and(|ids| !ids.is_empty())
+    }
+
+    pub fn contains_edge(&self, from: &V, to: &V) -> bool {
+        self.is_edge_visible(&(from.clone(), to.clone()))
+    }
+
+    pub fn get_vertices(&self) -> HashSet<V> {
+        self.vertices.iter().filter(|(_, ids)| !ids.is_empty()).map(|(v, _)| v.clone()).collect()
+    }
+
+    pub fn get_edges(&self) -> HashSet<(V, V)> {
+        self.edges.keys().filter(|e| self.is_edge_visible(e)).cloned().collect()
+    }
+
+    /// Returns the vertices that `v` has an edge to.
+    pub fn successors(&self, v: &V) -> HashSet<V> {
+        let Some(edges) = self.incident.get(v) else { return HashSet::new() };
+        edges.iter().filter(|(from, _)| from == v).filter(|e| self.is_edge_visible(e)).map(|(_, to)| to.clone()).collect()
+    }
+
+    fn is_edge_visible(&self, edge: &(V, V)) -> bool {
+        let (from, to) = edge;
+        let is_live = |v: &V, id: &GraphID| self.vertices.get(v).is_some_and(|ids| ids.contains(id));
+        self.edges.get(edge).is_some_and(|adds| adds.values().any(|(from_id, to_id)| is_live(from, from_id) && is_live(to, to_id)))
+    }
+
+    // whether `id` is the hash of a node in the causal past of `node` whose operation is the
+    // expected Add
+    fn is_add_in_past(id: &GraphID, node: &Node<BFTGraphOp<V>>, hash_graph: &dyn GraphView<BFTGraphOp<V>>, is_expected: impl Fn(&BFTGraphOp<V>) -> bool) -> bool {
+        match hash_graph.get_node(id) {
+            Some(n) => is_expected(&n.value) && hash_graph.is_ancestor(id, node),
+            None => false,
+        }
+    }
+}
+
+impl<V> Default for BFTGraph<V>
+where
+    V: Eq + Hash + Clone + Serialize,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> BFTCRDT<BFTGraphOp<V>> for BFTGraph<V>
+where
+    V: Eq + Hash + Clone + Serialize,
+{
+    type Event = BFTGraphEvent<V>;
+
+    fn interpret_node(&mut self, node: &Node<BFTGraphOp<V>>) -> Vec<BFTGraphEvent<V>> {
+        let mut events = vec![];
+        match &node.value {
+            BFTGraphOp::AddVertex(v) => {
+                let ids = self.vertices.entry(v.clone()).or_default();
+                if ids.is_empty() {
+                    events.push(BFTGraphEvent::VertexAdded(v.clone()));
+                }
+                ids.insert(node.get_hash());
+            }
+            BFTGraphOp::RemoveVertex(v, ids) => {
+                let edges: Vec<(V, V)> = self.incident.get(v).map(|e| e.iter().cloned().collect()).unwrap_or_default();
+                let visible_edges: Vec<(V, V)> = edges.into_iter().filter(|e| self.is_edge_visible(e)).collect();
+                let was_in = self.contains_vertex(v);
+                if let Some(v_ids) = self.vertices.get_mut(v) {
+                    for id in ids {
+                        v_ids.remove(id);
+                    }
+                }
+                for (from, to) in visible_edges {
+                    if !self.is_edge_visible(&(from.clone(), to.clone())) {
+                        events.push(BFTGraphEvent::EdgeRemoved(from, to));
+                    }
+                }
+                if was_in && !self.contains_vertex(v) {
+                    events.push(BFTGraphEvent::VertexRemoved(v.clone()));
+                }
+            }
+            BFTGraphOp::AddEdge(from, to, from_id, to_id) => {
+                let edge = (from.clone(), to.clone());
+                let was_in = self.is_edge_visible(&edge);
+                self.edges.entry(edge.clone()).or_default().insert(node.get_hash(), (from_id.clone(), to_id.clone()));
+                self.incident.entry(from.clone()).or_default().insert(edge.clone());
+                self.incident.entry(to.clone()).or_default().insert(edge.clone());
+                if !was_in && self.is_edge_visible(&edge) {
+                    events.push(BFTGraphEvent::EdgeAdded(from.clone(), to.clone()));
+                }
+            }
+            BFTGraphOp::RemoveEdge(from, to, ids) => {
+                let edge = (from.clone(), to.clone());
+                let was_in = self.is_edge_visible(&edge);
+                if let Some(adds) = self.edges.get_mut(&edge) {
+                    for id in ids {
+                        adds.remove(id);
+                    }
+                }
+                if was_in && !self.is_edge_visible(&edge) {
+                    events.push(BFTGraphEvent::EdgeRemoved(from.clone(), to.clone()));
+                }
+            }
+        }
+        events
+    }
+
+    fn is_sem_valid(&self, node: &Node<BFTGraphOp<V>>, hash_graph: &dyn GraphView<BFTGraphOp<V>>) -> bool {
+        match &node.value {
+            BFTGraphOp::AddVertex(_v) => true,
+            // as `is_orset_sem_valid` for Rem
+            BFTGraphOp::RemoveVertex(v, ids) => ids.iter().all(|id| {
+                Self::is_add_in_past(id, node, hash_graph, |op| matches!(op, BFTGraphOp::AddVertex(v2) if v2 == v))
+            }),
+            // both endpoints must have been added in the causal past of the edge
+            BFTGraphOp::AddEdge(from, to, from_id, to_id) => {
+                Self::is_add_in_past(from_id, node, hash_graph, |op| matches!(op, BFTGraphOp::AddVertex(v2) if v2 == from))
+                    && Self::is_add_in_past(to_id, node, hash_graph, |op| matches!(op, BFTGraphOp::AddVertex(v2) if v2 == to))
+            }
+            BFTGraphOp::RemoveEdge(from, to, ids) => ids.iter().all(|id| {
+                Self::is_add_in_past(id, node, hash_graph, |op| matches!(op, BFTGraphOp::AddEdge(f2, t2, _, _) if f2 == from && t2 == to))
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use crate::bft_crdts::replica::Replica;
+    use super::*;
+
+    #[test]
+    fn test_bft_graph_edges_follow_endpoints() {
+        let mut a: Replica<_, BFTGraph<&str>> = Replica::new(BFTGraph::new());
+        let mut nodes = vec![];
+        for v in ["design", "build", "test"] {
+            let op = a.crdt.add_vertex(v);
+            nodes.push(a.handle_local_op(op));
+        }
+        for (from, to) in [("design", "build"), ("build", "test")] {
+            let op = a.crdt.add_edge(from, to).unwrap();
+            nodes.push(a.handle_local_op(op));
+        }
+        assert!(a.crdt.add_edge("design", "deploy").is_none());
+        assert_eq!(a.crdt.successors(&"design"), HashSet::from(["build"]));
+
+        let mut b: Replica<_, BFTGraph<&str>> = Replica::new(BFTGraph::new());
+        for node in nodes.iter().rev() {
+            b.handle_remote_node(node.clone());
+        }
+        assert_eq!(b.crdt.get_edges(), a.crdt.get_edges());
+
+        // b removes "build" while a concurrently adds another edge to it
+        let op = b.crdt.remove_vertex("build");
+        let remove = b.handle_local_op(op);
+        let op = a.crdt.add_vertex("deploy");
+        let deploy = a.handle_local_op(op);
+        let op = a.crdt.add_edge("build", "deploy").unwrap();
+        let edge = a.handle_local_op(op);
+        let events = a.record_events();
+        a.handle_remote_node(remove);
+        b.handle_remote_node(deploy);
+        b.handle_remote_node(edge);
+        assert!(a.crdt.get_edges().is_empty());
+        assert!(b.crdt.get_edges().is_empty());
+        assert_eq!(a.crdt.get_vertices(), HashSet::from(["design", "test", "deploy"]));
+        assert_eq!(b.crdt.get_vertices(), a.crdt.get_vertices());
+        let removed: HashSet<_> = events.borrow()[..3].iter().cloned().collect();
+        assert_eq!(removed, HashSet::from([
+            BFTGraphEvent::EdgeRemoved("design", "build"),
+            BFTGraphEvent::EdgeRemoved("build", "test"),
+            BFTGraphEvent::EdgeRemoved("build", "deploy"),
+        ]));
+        assert_eq!(events.borrow()[3], BFTGraphEvent::VertexRemoved("build"));
+
+        // adding the vertex again does not restore its edges
+        let op = b.crdt.add_vertex("build");
+        b.handle_local_op(op);
+        assert!(!b.crdt.contains_edge(&"design", &"build"));
+    }
+
+    #[test]
+    fn test_bft_graph_sem_validity() {
+        let mut handler: Replica<_, BFTGraph<&str>> = Replica::new(BFTGraph::new());
+        let op = handler.crdt.add_vertex("x");
+        let x = handler.handle_local_op(op);
+        let op = handler.crdt.add_vertex("y");
+        let y = handler.handle_local_op(op);
+        let heads = handler.hash_graph.get_heads().to_vec();
+
+        let valid = Node::new(heads.clone(), BFTGraphOp::AddEdge("x", "y", x.get_hash(), y.get_hash()));
+        assert!(handler.crdt.is_sem_valid(&valid, &handler.hash_graph));
+        // the endpoint Adds must be in the causal past and add the named vertices
+        let concurrent = Node::new(vec![x.get_hash()], BFTGraphOp::AddEdge("x", "y", x.get_hash(), y.get_hash()));
+        assert!(!handler.crdt.is_sem_valid(&concurrent, &handler.hash_graph));
+        let swapped = Node::new(heads.clone(), BFTGraphOp::AddEdge("x", "y", y.get_hash(), x.get_hash()));
+        assert!(!handler.crdt.is_sem_valid(&swapped, &handler.hash_graph));
+        let edge = handler.handle_local_op(BFTGraphOp::AddEdge("x", "y", x.get_hash(), y.get_hash()));
+        let heads = handler.hash_graph.get_heads().to_vec();
+        let reversed = Node::new(heads.clone(), BFTGraphOp::RemoveEdge("y", "x", vec![edge.get_hash()]));
+        assert!(!handler.crdt.is_sem_valid(&reversed, &handler.hash_graph));
+        let edge_as_vertex = Node::new(heads, BFTGraphOp::RemoveVertex("x", vec![edge.get_hash()]));
+        assert!(!handler.crdt.is_sem_valid(&edge_as_vertex, &handler.hash_graph));
+    }
+}
@@ -0,0 +1,712 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Debug, Display};
+use std::hash::{Hash, Hasher};
+use crate::bft_crdts::bft_counter::{BFTPNCounter, BFTPNCounterOp};
+use crate::bft_crdts::bft_crdt::BFTCRDT;
+use crate::bft_crdts::bft_ormap::{BFTORMap, BFTORMapEvent, BFTORMapOp};
+use crate::bft_crdts::bft_register::{BFTLWWRegister, BFTRegisterOp};
+use crate::bft_crdts::bft_rga::{BFTRGA, BFTRGAEvent, BFTRGAOp};
+use crate::bft_crdts::hash_graph::{GraphView, HashType, Node, ProjectedView};
+use crate::serialize::Serialize;
+
+//  A BFT JSON document is a tree of maps, lists, counters and primitive values that is built
+//   from the other BFT CRDTs and lives in a single HashGraph. A map is a BFT ORMap from keys to
+//   BFT LWW registers of values, a list is a BFT RGA of values with Lamport counters as the
+//   peer-chosen IDs, and a counter is a BFT PN counter on top of its initial value. Each of them
+//   validates its operations as it does on its own, e.g. a write to a key must overwrite all
+//   current values of the key.
+//
+//   A map, list or counter is identified by the hash of the node that wrote it to a key or
+//   inserted it into a list. It is changed by an Edit of that key or list, which carries an
+//   operation for the nested object; the Edit is valid if the object was created by a node in
+//   its causal past and the object accepts the operation. Edits thus keep their meaning under
+//   concurrent changes to the indices of enclosing lists; the path-based methods of
+//   `BFTJsonDoc` translate paths into nested operations.
+//
+//   A value that is overwritten disappears together with everything nested inside it, but
+//   edits of its contents remain valid. Deleting a key removes it as in the ORMap: a concurrent
+//   edit inside the value of the key keeps the key in the map.
+
+type JsonID = HashType; // ID of a map, list or counter: the hash of the node that created it
+
+/// A JSON document, as materialized from a `BFTJsonDoc`. Counters appear as numbers.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(BTreeMap<String, JsonValue>),
+}
+
+/// The value a write or insert puts into the document. Maps and lists start empty. Values
+/// are compared by their serialization, so that numbers can be held by registers and lists.
+#[derive(Debug, Clone)]
+pub enum JsonInit {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Map,
+    List,
+    Counter(i64),
+}
+
+impl PartialEq for JsonInit {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_bytes() == other.to_bytes()
+    }
+}
+
+impl Eq for JsonInit {}
+
+impl Hash for JsonInit {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.to_bytes().hash(state);
+    }
+}
+
+impl JsonInit {
+    // the value as it is materialized right after it has been put into the document
+    fn to_value(&self) -> JsonValue {
+        match self {
+            JsonInit::Null => JsonValue::Null,
+            JsonInit::Bool(b) => JsonValue::Bool(*b),
+            JsonInit::Number(n) => JsonValue::Number(*n),
+            JsonInit::String(s) => JsonValue::String(s.clone()),
+            JsonInit::Map => JsonValue::Object(BTreeMap::new()),
+            JsonInit::List => JsonValue::Array(vec![]),
+            JsonInit::Counter(n) => JsonValue::Number(*n as f64),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// An operation on the values of a map key or of a list. `Values` is an operation of the
+/// register or RGA holding them, `Edit` changes the map, list or counter with the given ID.
+#[derive(Debug, Clone)]
+pub enum BFTJsonValuesOp<O> {
+    Values(O),
+    // object, operation on it
+    Edit(JsonID, Box<BFTJsonOp>),
+}
+
+pub type BFTJsonKeyOp = BFTJsonValuesOp<BFTRegisterOp<JsonInit>>;
+pub type BFTJsonListOp = BFTJsonValuesOp<BFTRGAOp<u64, JsonInit>>;
+
+/// An operation on a map, list or counter of a JSON document; the operations of the document
+/// are those of its root map.
+#[derive(Debug, Clone)]
+pub enum BFTJsonOp {
+    Map(BFTORMapOp<String, BFTJsonKeyOp>),
+    List(BFTJsonListOp),
+    Counter(BFTPNCounterOp<()>),
+}
+
+impl<O: Display> Display for BFTJsonValuesOp<O> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BFTJsonValuesOp::Values(op) => write!(f, "{}", op),
+            BFTJsonValuesOp::Edit(id, op) => write!(f, "Edit({}, {})", id.get(..8).unwrap_or(id), op),
+        }
+    }
+}
+
+impl Display for BFTJsonOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BFTJsonOp::Map(op) => write!(f, "Map({})", op),
+            BFTJsonOp::List(op) => write!(f, "List({})", op),
+            BFTJsonOp::Counter(op) => write!(f, "Counter({})", op),
+        }
+    }
+}
+
+impl Serialize for JsonInit {
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            JsonInit::Null => vec![0],
+            JsonInit::Bool(b) => vec![1, *b as u8],
+            JsonInit::Number(n) => [vec![2], n.to_le_bytes().to_vec()].concat(),
+            // length-prefixed, since the RGA puts the element ID after the value
+            JsonInit::String(s) => [vec![3], (s.len() as u64).to_bytes(), s.to_bytes()].concat(),
+            JsonInit::Map => vec![4],
+            JsonInit::List => vec![5],
+            JsonInit::Counter(n) => [vec![6], n.to_bytes()].concat(),
+        }
+    }
+}
+
+impl<O: Serialize> Serialize for BFTJsonValuesOp<O> {
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            BFTJsonValuesOp::Values(op) => [vec![0], op.to_bytes()].concat(),
+            BFTJsonValuesOp::Edit(id, op) => [vec![1], id.to_bytes(), op.to_bytes()].concat(),
+        }
+    }
+}
+
+impl Serialize for BFTJsonOp {
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            BFTJsonOp::Map(op) => [vec![0], op.to_bytes()].concat(),
+            BFTJsonOp::List(op) => [vec![1], op.to_bytes()].concat(),
+            BFTJsonOp::Counter(op) => [vec![2], op.to_bytes()].concat(),
+        }
+    }
+}
+
+/// A change to a `BFTJsonDoc` at a path, with list indices as of right after the change.
+/// Changes inside values that are not part of the document (any more) are not reported.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BFTJsonEvent {
+    // the value at the path was set, replaced, or (for counters) incremented
+    Put(Vec<PathSegment>, JsonValue),
+    // a list element was inserted at the path
+    Inserted(Vec<PathSegment>, JsonValue),
+    // the key or list element at the path was deleted; the index is the one it had
+    Removed(Vec<PathSegment>),
+}
+
+impl BFTJsonEvent {
+    // the event with `prefix` put in front of its path
+    fn prefixed(self, prefix: &[PathSegment]) -> BFTJsonEvent {
+        let prefix = |path: Vec<PathSegment>| [prefix.to_vec(), path].concat();
+        match self {
+            BFTJsonEvent::Put(path, value) => BFTJsonEvent::Put(prefix(path), value),
+            BFTJsonEvent::Inserted(path, value) => BFTJsonEvent::Inserted(prefix(path), value),
+            BFTJsonEvent::Removed(path) => BFTJsonEvent::Removed(prefix(path)),
+        }
+    }
+}
+
+// The register of a map key or the RGA of a list, as the holder of the values of a
+// `BFTJsonValues`.
+trait JsonValues<O: Serialize + Clone>: BFTCRDT<O> + Default {
+    // the value that `op` puts in, if any
+    fn created(op: &O) -> Option<&JsonInit>;
+
+    // the visible values, in order, with the hashes of the nodes that put them in
+    fn visible(&self) -> Vec<(HashType, JsonInit)>;
+
+    // where the value put in by the node `id` with operation `op` is, relative to the holder,
+    // or `None` if it is not visible
+    fn path_of(&self, id: &HashType, op: &O) -> Option<Vec<PathSegment>>;
+
+    // the document events for the events of interpreting a node, given the value of each
+    // value that was put in
+    fn json_events(&self, events: Vec<Self::Event>, value: &dyn Fn(&HashType, &JsonInit) -> JsonValue) -> Vec<BFTJsonEvent>;
+}
+
+impl JsonValues<BFTRegisterOp<JsonInit>> for BFTLWWRegister<JsonInit> {
+    fn created(op: &BFTRegisterOp<JsonInit>) -> Option<&JsonInit> {
+        let BFTRegisterOp::Write(v, _ids) = op;
+        Some(v)
+    }
+
+    fn visible(&self) -> Vec<(HashType, JsonInit)> {
+        self.get_id().zip(self.get()).into_iter().collect()
+    }
+
+    fn path_of(&self, id: &HashType, _op: &BFTRegisterOp<JsonInit>) -> Option<Vec<PathSegment>> {
+        (self.get_id().as_ref() == Some(id)).then(Vec::new)
+    }
+
+    fn json_events(&self, events: Vec<Self::Event>, value: &dyn Fn(&HashType, &JsonInit) -> JsonValue) -> Vec<BFTJsonEvent> {
+        if events.is_empty() {
+            return vec![];
+        }
+        // a write leaves the register with a value
+        self.visible().iter().map(|(id, v)| BFTJsonEvent::Put(vec![], value(id, v))).collect()
+    }
+}
+
+impl JsonValues<BFTRGAOp<u64, JsonInit>> for BFTRGA<u64, JsonInit> {
+    fn created(op: &BFTRGAOp<u64, JsonInit>) -> Option<&JsonInit> {
+        match op {
+            BFTRGAOp::Insert(v, _i, _after) => Some(v),
+            _ => None,
+        }
+    }
+
+    fn visible(&self) -> Vec<(HashType, JsonInit)> {
+        self.get_elements().into_iter().map(|((_i, id), v)| (id, v)).collect()
+    }
+
+    fn path_of(&self, id: &HashType, op: &BFTRGAOp<u64, JsonInit>) -> Option<Vec<PathSegment>> {
+        let BFTRGAOp::Insert(_v, i, _after) = op else { return None };
+        let idx = self.index_of(&(*i, id.clone()))?;
+        Some(vec![PathSegment::Index(idx)])
+    }
+
+    fn json_events(&self, events: Vec<Self::Event>, value: &dyn Fn(&HashType, &JsonInit) -> JsonValue) -> Vec<BFTJsonEvent> {
+        let visible = self.visible();
+        events.into_iter().filter_map(|event| match event {
+            // a move is reported as a deletion followed by the insert, so the index of an
+            // insert is also its final one
+            BFTRGAEvent::Insert(idx, _v) => {
+                let (id, v) = visible.get(idx)?;
+                Some(BFTJsonEvent::Inserted(vec![PathSegment::Index(idx)], value(id, v)))
+            }
+            BFTRGAEvent::Delete(idx, _v) => Some(BFTJsonEvent::Removed(vec![PathSegment::Index(idx)])),
+        }).collect()
+    }
+}
+
+// The values of a map key or a list, together with the maps, lists and counters among them.
+struct BFTJsonValues<C, O> {
+    values: C,
+    // the objects put in, by the hash of the node that put them in, with its operation
+    objects: HashMap<JsonID, (O, BFTJsonObject)>,
+}
+
+type BFTJsonKey = BFTJsonValues<BFTLWWRegister<JsonInit>, BFTRegisterOp<JsonInit>>;
+type BFTJsonList = BFTJsonValues<BFTRGA<u64, JsonInit>, BFTRGAOp<u64, JsonInit>>;
+
+impl<C: Default, O> Default for BFTJsonValues<C, O> {
+    fn default() -> Self {
+        BFTJsonValues {
+            values: C::default(),
+            objects: HashMap::new(),
+        }
+    }
+}
+
+impl<C, O> BFTJsonValues<C, O>
+where
+    C: JsonValues<O>,
+    O: Serialize + Clone,
+{
+    fn value(&self, id: &HashType, init: &JsonInit) -> JsonValue {
+        match self.objects.get(id) {
+            Some((_op, object)) => object.materialize(),
+            None => init.to_value(),
+        }
+    }
+
+    fn materialize(&self) -> Vec<JsonValue> {
+        self.values.visible().iter().map(|(id, v)| self.value(id, v)).collect()
+    }
+
+    // the value below the visible value put in by the node `id`
+    fn value_at(&self, id: &HashType, init: &JsonInit, path: &[PathSegment]) -> Option<JsonValue> {
+        if path.is_empty() {
+            return Some(self.value(id, init));
+        }
+        self.objects.get(id)?.1.value_at(path)
+    }
+
+    // the Edit that makes `edit` at `path` below the object put in by the node `id`
+    fn edit_at(&mut self, id: &HashType, path: &[PathSegment], edit: &JsonEdit) -> Option<BFTJsonValuesOp<O>> {
+        let (_op, object) = self.objects.get_mut(id)?;
+        Some(BFTJsonValuesOp::Edit(id.clone(), Box::new(object.op_at(path, edit)?)))
+    }
+}
+
+impl<C, O> BFTCRDT<BFTJsonValuesOp<O>> for BFTJsonValues<C, O>
+where
+    C: JsonValues<O>,
+    O: Serialize + Clone,
+{
+    type Event = BFTJsonEvent;
+
+    fn interpret_node(&mut self, node: &Node<BFTJsonValuesOp<O>>) -> Vec<BFTJsonEvent> {
+        match &node.value {
+            BFTJsonValuesOp::Values(op) => {
+                let events = self.values.interpret_node(&node.project(op.clone()));
+                if let Some(object) = C::created(op).and_then(BFTJsonObject::new) {
+                    self.objects.insert(node.get_hash(), (op.clone(), object));
+                }
+                self.values.json_events(events, &|id, v| self.value(id, v))
+            }
+            BFTJsonValuesOp::Edit(id, op) => {
+                let Some((created_by, object)) = self.objects.get_mut(id) else { return vec![] };
+                let events = object.interpret_node(&node.project((**op).clone()));
+                match self.values.path_of(id, created_by) {
+                    Some(path) => events.into_iter().map(|event| event.prefixed(&path)).collect(),
+                    None => vec![],
+                }
+            }
+        }
+    }
+
+    fn is_sem_valid(&self, node: &Node<BFTJsonValuesOp<O>>, hash_graph: &dyn GraphView<BFTJsonValuesOp<O>>) -> bool {
+        match &node.value {
+            BFTJsonValuesOp::Values(op) => {
+                let view = ProjectedView::new(
+                    hash_graph,
+                    |op: &BFTJsonValuesOp<O>| match op {
+                        BFTJsonValuesOp::Values(op) => Some(op.clone()),
+                        _ => None,
+                    },
+                    BFTJsonValuesOp::Values,
+                );
+                self.values.is_sem_valid(&node.project(op.clone()), &view)
+            }
+            // the object must have been created in the causal past, and judges the operation
+            // against the Edits of it
+            BFTJsonValuesOp::Edit(id, op) => {
+                let Some((_created_by, object)) = self.objects.get(id) else { return false };
+                if !hash_graph.is_ancestor(id, node) {
+                    return false;
+                }
+                let view = ProjectedView::new(
+                    hash_graph,
+                    |op: &BFTJsonValuesOp<O>| match op {
+                        BFTJsonValuesOp::Edit(id2, op) if id2 == id => Some((**op).clone()),
+                        _ => None,
+                    },
+                    |op| BFTJsonValuesOp::Edit(id.clone(), Box::new(op)),
+                );
+                object.is_sem_valid(&node.project((**op).clone()), &view)
+            }
+        }
+    }
+}
+
+enum BFTJsonObject {
+    Map(BFTORMap<String, BFTJsonKey>),
+    List(Box<BFTJsonList>),
+    // initial value, changes
+    Counter(i64, BFTPNCounter<()>),
+}
+
+// A change to make at a path of a document.
+enum JsonEdit {
+    Set(JsonInit),
+    Insert(JsonInit),
+    Delete,
+    Increment(i64),
+}
+
+impl BFTJsonObject {
+    fn new(init: &JsonInit) -> Option<Self> {
+        match init {
+            JsonInit::Map => Some(BFTJsonObject::Map(BFTORMap::new())),
+            JsonInit::List => Some(BFTJsonObject::List(Box::default())),
+            JsonInit::Counter(n) => Some(BFTJsonObject::Counter(*n, BFTPNCounter::new())),
+            _ => None,
+        }
+    }
+
+    fn materialize(&self) -> JsonValue {
+        match self {
+            BFTJsonObject::Map(map) => JsonValue::Object(map.get_keys().into_iter()
+                .filter_map(|k| Some((k.clone(), map.get(&k)?.materialize().pop()?)))
+                .collect()),
+            BFTJsonObject::List(list) => JsonValue::Array(list.materialize()),
+            BFTJsonObject::Counter(initial, counter) => JsonValue::Number(initial.saturating_add(counter.get_value()) as f64),
+        }
+    }
+
+    fn value_at(&self, path: &[PathSegment]) -> Option<JsonValue> {
+        let Some((first, rest)) = path.split_first() else { return Some(self.materialize()) };
+        match (self, first) {
+            (BFTJsonObject::Map(map), PathSegment::Key(key)) => {
+                let values = map.get(key)?;
+                let (id, init) = values.values.visible().pop()?;
+                values.value_at(&id, &init, rest)
+            }
+            (BFTJsonObject::List(list), PathSegment::Index(idx)) => {
+                let (id, init) = list.values.visible().into_iter().nth(*idx)?;
+                list.value_at(&id, &init, rest)
+            }
+            _ => None,
+        }
+    }
+
+    // Returns the operation that makes `edit` at `path` below this object: `Set` needs a key
+    // of a map and `Insert` an index of a list, `Delete` either, and `Increment` a counter.
+    fn op_at(&mut self, path: &[PathSegment], edit: &JsonEdit) -> Option<BFTJsonOp> {
+        match (self, path, edit) {
+            (BFTJsonObject::Map(map), [PathSegment::Key(key)], JsonEdit::Set(value)) => {
+                // a hidden key still has the values a write must overwrite
+                let write = map.child(key.clone()).values.write(value.clone());
+                Some(BFTJsonOp::Map(map.update(key.clone(), BFTJsonValuesOp::Values(write))))
+            }
+            (BFTJsonObject::Map(map), [PathSegment::Key(key)], JsonEdit::Delete) => {
+                map.contains_key(key).then(|| BFTJsonOp::Map(map.remove(key.clone())))
+            }
+            (BFTJsonObject::Map(map), [PathSegment::Key(key), rest @ ..], _) => {
+                if !map.contains_key(key) {
+                    return None;
+                }
+                let values = map.child(key.clone());
+                let id = values.values.get_id()?;
+                let op = values.edit_at(&id, rest, edit)?;
+                Some(BFTJsonOp::Map(map.update(key.clone(), op)))
+            }
+            (BFTJsonObject::List(list), [PathSegment::Index(idx)], JsonEdit::Insert(value)) => {
+                let counter = list.values.max_id().map_or(0, |max| max.saturating_add(1));
+                let insert = list.values.insert(*idx, value.clone(), counter)?;
+                Some(BFTJsonOp::List(BFTJsonValuesOp::Values(insert)))
+            }
+            (BFTJsonObject::List(list), [PathSegment::Index(idx)], JsonEdit::Delete) => {
+                Some(BFTJsonOp::List(BFTJsonValuesOp::Values(list.values.delete(*idx)?)))
+            }
+            (BFTJsonObject::List(list), [PathSegment::Index(idx), rest @ ..], _) => {
+                let (id, _init) = list.values.visible().into_iter().nth(*idx)?;
+                Some(BFTJsonOp::List(list.edit_at(&id, rest, edit)?))
+            }
+            (BFTJsonObject::Counter(..), [], JsonEdit::Increment(n)) => {
+                let op = if *n >= 0 { BFTPNCounterOp::Increment((), *n) } else { BFTPNCounterOp::Decrement((), n.checked_neg()?) };
+                Some(BFTJsonOp::Counter(op))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl BFTCRDT<BFTJsonOp> for BFTJsonObject {
+    type Event = BFTJsonEvent;
+
+    fn interpret_node(&mut self, node: &Node<BFTJsonOp>) -> Vec<BFTJsonEvent> {
+        match (self, &node.value) {
+            (BFTJsonObject::Map(map), BFTJsonOp::Map(op)) => {
+                let events = map.interpret_node(&node.project(op.clone()));
+                // a key that appears is reported with its value instead of the changes to it
+                let inserted: Vec<String> = events.iter().filter_map(|event| match event {
+                    BFTORMapEvent::Inserted(key) => Some(key.clone()),
+                    _ => None,
+                }).collect();
+                events.into_iter().filter_map(|event| match event {
+                    BFTORMapEvent::Inserted(key) => {
+                        let value = map.get(&key)?.materialize().pop()?;
+                        Some(BFTJsonEvent::Put(vec![PathSegment::Key(key)], value))
+                    }
+                    BFTORMapEvent::Updated(key, event) if !inserted.contains(&key) => Some(event.prefixed(&[PathSegment::Key(key)])),
+                    BFTORMapEvent::Updated(..) => None,
+                    BFTORMapEvent::Removed(key) => Some(BFTJsonEvent::Removed(vec![PathSegment::Key(key)])),
+                }).collect()
+            }
+            (BFTJsonObject::List(list), BFTJsonOp::List(op)) => list.interpret_node(&node.project(op.clone())),
+            (BFTJsonObject::Counter(initial, counter), BFTJsonOp::Counter(op)) => {
+                if counter.interpret_node(&node.project(op.clone())).is_empty() {
+                    return vec![];
+                }
+                vec![BFTJsonEvent::Put(vec![], JsonValue::Number(initial.saturating_add(counter.get_value()) as f64))]
+            }
+            _ => vec![],
+        }
+    }
+
+    fn is_sem_valid(&self, node: &Node<BFTJsonOp>, hash_graph: &dyn GraphView<BFTJsonOp>) -> bool {
+        match (self, &node.value) {
+            (BFTJsonObject::Map(map), BFTJsonOp::Map(op)) => {
+                let view = ProjectedView::new(
+                    hash_graph,
+                    |op: &BFTJsonOp| match op {
+                        BFTJsonOp::Map(op) => Some(op.clone()),
+                        _ => None,
+                    },
+                    BFTJsonOp::Map,
+                );
+                map.is_sem_valid(&node.project(op.clone()), &view)
+            }
+            (BFTJsonObject::List(list), BFTJsonOp::List(op)) => {
+                let view = ProjectedView::new(
+                    hash_graph,
+                    |op: &BFTJsonOp| match op {
+                        BFTJsonOp::List(op) => Some(op.clone()),
+                        _ => None,
+                    },
+                    BFTJsonOp::List,
+                );
+                list.is_sem_valid(&node.project(op.clone()), &view)
+            }
+            (BFTJsonObject::Counter(_initial, counter), BFTJsonOp::Counter(op)) => {
+                let view = ProjectedView::new(
+                    hash_graph,
+                    |op: &BFTJsonOp| match op {
+                        BFTJsonOp::Counter(op) => Some(op.clone()),
+                        _ => None,
+                    },
+                    BFTJsonOp::Counter,
+                );
+                counter.is_sem_valid(&node.project(op.clone()), &view)
+            }
+            // an operation for another kind of object
+            _ => false,
+        }
+    }
+}
+
+pub struct BFTJsonDoc {
+    root: BFTJsonObject,
+}
+
+impl BFTJsonDoc {
+    pub fn new() -> Self {
+        BFTJsonDoc {
+            root: BFTJsonObject::Map(BFTORMap::new()),
+        }
+    }
+
+    /// Returns the document as a JSON value.
+    pub fn materialize(&self) -> JsonValue {
+        self.root.materialize()
+    }
+
+    /// Returns the value at `path`, if there is one.
+    pub fn get(&self, path: &[PathSegment]) -> Option<JsonValue> {
+        self.root.value_at(path)
+    }
+
+    /// Sets the value at `path`, whose last segment must be a key of a map.
+    pub fn set(&mut self, path: &[PathSegment], value: JsonInit) -> Option<BFTJsonOp> {
+        self.root.op_at(path, &JsonEdit::Set(value))
+    }
+
+    /// Inserts a value into a list; the last segment of `path` is the index of the new element.
+    pub fn insert(&mut self, path: &[PathSegment], value: JsonInit) -> Option<BFTJsonOp> {
+        self.root.op_at(path, &JsonEdit::Insert(value))
+    }
+
+    /// Deletes the key or list element at `path`.
+    pub fn delete(&mut self, path: &[PathSegment]) -> Option<BFTJsonOp> {
+        self.root.op_at(path, &JsonEdit::Delete)
+    }
+
+    /// Increments the counter at `path` by `n` (which may be negative).
+    pub fn increment(&mut self, path: &[PathSegment], n: i64) -> Option<BFTJsonOp> {
+        self.root.op_at(path, &JsonEdit::Increment(n))
+    }
+}
+
+impl Default for BFTJsonDoc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BFTCRDT<BFTJsonOp> for BFTJsonDoc {
+    type Event = BFTJsonEvent;
+
+    fn interpret_node(&mut self, node: &Node<BFTJsonOp>) -> Vec<BFTJsonEvent> {
+        self.root.interpret_node(node)
+    }
+
+    fn is_sem_valid(&self, node: &Node<BFTJsonOp>, hash_graph: &dyn GraphView<BFTJsonOp>) -> bool {
+        self.root.is_sem_valid(node, hash_graph)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bft_crdts::replica::Replica;
+    use super::*;
+
+    type LocalOp<'a> = Box<dyn Fn(&mut BFTJsonDoc) -> Option<BFTJsonOp> + 'a>;
+
+    fn key(k: &str) -> PathSegment {
+        PathSegment::Key(k.to_string())
+    }
+
+    #[test]
+    fn test_bft_json_doc() {
+        let mut local: Replica<BFTJsonOp, BFTJsonDoc> = Replica::new(BFTJsonDoc::new());
+        let mut nodes = vec![];
+        let items = || key("items");
+        let ops: Vec<LocalOp<'_>> = vec![
+            Box::new(|doc| doc.set(&[key("title")], JsonInit::String("todo".to_string()))),
+            Box::new(|doc| doc.set(&[items()], JsonInit::List)),
+            Box::new(|doc| doc.insert(&[items(), PathSegment::Index(0)], JsonInit::Map)),
+            Box::new(|doc| doc.set(&[items(), PathSegment::Index(0), key("done")], JsonInit::Bool(false))),
+            Box::new(|doc| doc.insert(&[items(), PathSegment::Index(0)], JsonInit::String("first".to_string()))),
+            Box::new(|doc| doc.set(&[key("views")], JsonInit::Counter(0))),
+            Box::new(|doc| doc.increment(&[key("views")], 3)),
+            Box::new(|doc| doc.delete(&[key("title")])),
+        ];
+        for op in ops {
+            let op = op(&mut local.crdt).unwrap();
+            nodes.push(local.handle_local_op(op));
+        }
+        let expected = JsonValue::Object([
+            ("items".to_string(), JsonValue::Array(vec![
+                JsonValue::String("first".to_string()),
+                JsonValue::Object([("done".to_string(), JsonValue::Bool(false))].into()),
+            ])),
+            ("views".to_string(), JsonValue::Number(3.0)),
+        ].into());
+        assert_eq!(local.crdt.materialize(), expected);
+        assert_eq!(local.crdt.get(&[key("items"), PathSegment::Index(1), key("done")]), Some(JsonValue::Bool(false)));
+
+        let mut remote: Replica<BFTJsonOp, BFTJsonDoc> = Replica::new(BFTJsonDoc::new());
+        let events = remote.record_events();
+        for node in nodes.iter().rev() {
+            remote.handle_remote_node(node.clone());
+        }
+        assert_eq!(remote.crdt.materialize(), expected);
+        assert_eq!(events.borrow()[0], BFTJsonEvent::Put(vec![key("title")], JsonValue::String("todo".to_string())));
+        assert_eq!(events.borrow()[3], BFTJsonEvent::Put(vec![key("items"), PathSegment::Index(0), key("done")], JsonValue::Bool(false)));
+        assert_eq!(events.borrow()[4], BFTJsonEvent::Inserted(vec![key("items"), PathSegment::Index(0)], JsonValue::String("first".to_string())));
+        assert_eq!(events.borrow().last(), Some(&BFTJsonEvent::Removed(vec![key("title")])));
+    }
+
+    #[test]
+    fn test_bft_json_doc_concurrent_edits() {
+        let mut a: Replica<BFTJsonOp, BFTJsonDoc> = Replica::new(BFTJsonDoc::new());
+        let op = a.crdt.set(&[key("list")], JsonInit::List).unwrap();
+        let list_node = a.handle_local_op(op);
+        let op = a.crdt.insert(&[key("list"), PathSegment::Index(0)], JsonInit::Number(1.0)).unwrap();
+        let elem_node = a.handle_local_op(op);
+        let mut b: Replica<BFTJsonOp, BFTJsonDoc> = Replica::new(BFTJsonDoc::new());
+        b.handle_remote_node(list_node.clone());
+        b.handle_remote_node(elem_node.clone());
+
+        // a overwrites the list while b inserts into it and sets a key concurrently
+        let op = a.crdt.set(&[key("list")], JsonInit::Null).unwrap();
+        let a_node = a.handle_local_op(op);
+        let op = b.crdt.insert(&[key("list"), PathSegment::Index(1)], JsonInit::Number(2.0)).unwrap();
+        let b_insert = b.handle_local_op(op);
+        let op = b.crdt.set(&[key("k")], JsonInit::Bool(true)).unwrap();
+        let b_set = b.handle_local_op(op);
+        let op = a.crdt.set(&[key("k")], JsonInit::Bool(false)).unwrap();
+        let a_set = a.handle_local_op(op);
+        a.handle_remote_node(b_insert.clone());
+        a.handle_remote_node(b_set.clone());
+        b.handle_remote_node(a_node.clone());
+        b.handle_remote_node(a_set.clone());
+        assert!(a.pending_nodes.is_empty() && b.pending_nodes.is_empty());
+        assert_eq!(a.crdt.materialize(), b.crdt.materialize());
+        assert_eq!(a.crdt.get(&[key("list")]), Some(JsonValue::Null));
+
+        // references must be objects of the right kind in the causal past
+        let heads = b.hash_graph.get_heads().to_vec();
+        let list_id = list_node.get_hash();
+        let edit_list = |op: BFTJsonOp| BFTJsonOp::Map(BFTORMapOp::Update("list".to_string(), BFTJsonValuesOp::Edit(list_id.clone(), Box::new(op))));
+        let BFTJsonOp::Map(BFTORMapOp::Update(_, BFTJsonValuesOp::Edit(_, insert))) = &elem_node.value else { panic!() };
+        let BFTJsonOp::List(BFTJsonValuesOp::Values(BFTRGAOp::Insert(_, c, _))) = **insert else { panic!() };
+        let after = Some((c, elem_node.get_hash()));
+        let insert = BFTJsonOp::List(BFTJsonValuesOp::Values(BFTRGAOp::Insert(JsonInit::Null, c + 1, after.clone())));
+        let valid = Node::new(heads.clone(), edit_list(insert));
+        assert!(b.crdt.is_sem_valid(&valid, &b.hash_graph));
+        let set = BFTJsonOp::Map(BFTORMapOp::Update("x".to_string(), BFTJsonValuesOp::Values(BFTRegisterOp::Write(JsonInit::Null, vec![]))));
+        let not_a_map = Node::new(heads.clone(), edit_list(set));
+        assert!(!b.crdt.is_sem_valid(&not_a_map, &b.hash_graph));
+        let delete = BFTJsonOp::List(BFTJsonValuesOp::Values(BFTRGAOp::Delete((c, elem_node.get_hash()))));
+        let not_in_past = Node::new(vec![list_node.get_hash()], edit_list(delete));
+        assert!(!b.crdt.is_sem_valid(&not_in_past, &b.hash_graph));
+        let not_a_counter = Node::new(heads.clone(), edit_list(BFTJsonOp::Counter(BFTPNCounterOp::Increment((), 1))));
+        assert!(!b.crdt.is_sem_valid(&not_a_counter, &b.hash_graph));
+        let write = |ids: Vec<HashType>| BFTJsonOp::Map(BFTORMapOp::Update("k".to_string(), BFTJsonValuesOp::Values(BFTRegisterOp::Write(JsonInit::Null, ids))));
+        let foreign_id = Node::new(heads.clone(), write(vec![a_node.get_hash()]));
+        assert!(!b.crdt.is_sem_valid(&foreign_id, &b.hash_graph));
+        // a write must overwrite all current values of the key, as in the register
+        let partial = Node::new(heads.clone(), write(vec![a_set.get_hash()]));
+        assert!(!b.crdt.is_sem_valid(&partial, &b.hash_graph));
+        let complete = Node::new(heads, write(vec![a_set.get_hash(), b_set.get_hash()]));
+        assert!(b.crdt.is_sem_valid(&complete, &b.hash_graph));
+
+        // IDs come from peers, so they are printed whatever their length
+        let counter = Box::new(BFTJsonOp::Counter(BFTPNCounterOp::Increment((), 1)));
+        assert!(BFTJsonListOp::Edit("é".to_string(), counter.clone()).to_string().starts_with("Edit(é, "));
+        assert!(BFTJsonListOp::Edit("aéééé".to_string(), counter).to_string().starts_with("Edit(aéééé, "));
+    }
+}
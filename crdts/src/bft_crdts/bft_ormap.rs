@@ -0,0 +1,275 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+use crate::bft_crdts::bft_crdt::BFTCRDT;
+use crate::bft_crdts::hash_graph::{GraphView, HashType, Node, ProjectedView};
+use crate::serialize::Serialize;
+
+//  A BFT ORMap maps keys to nested BFT CRDTs. Keys follow the BFT ORSet: every Update of a key
+//   acts as an Add of the key whose ID is the hash of the node containing it, and a Remove
+//   lists the IDs it removes. The operation of an Update is applied to the nested CRDT of its
+//   key as if it was a node of its own: it keeps the hash and predecessors of the node that
+//   carries it, so element IDs of the nested CRDT are hashes of nodes in the shared HashGraph.
+//   Removing a key only hides it; a later or concurrent Update of the key continues from the
+//   state of the nested CRDT, which thus never depends on the order of Updates and Removes.
+
+type ORMapID = HashType; // in BFT ORMap, ID is the hash value of the key's Update operation
+
+#[derive(Debug, Clone)]
+pub enum BFTORMapOp<K, O> {
+    // k, operation of the nested CRDT
+    Update(K, O),
+    // k, ids
+    Remove(K, Vec<ORMapID>),
+}
+
+impl <K, O> Display for BFTORMapOp<K, O>
+where
+    K: Eq + Hash + Clone + Serialize + Debug,
+    O: Serialize + Clone + Display,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BFTORMapOp::Update(k, op) => {
+                write!(f, "Update({:?}, {})", k, op)
+            }
+            BFTORMapOp::Remove(k, ids) => {
+                write!(f, "Remove({:?}, {:?})", k, ids)
+            }
+        }
+    }
+}
+
+impl<K, O> Serialize for BFTORMapOp<K, O>
+where
+    K: Eq + Hash + Clone + Serialize,
+    O: Serialize + Clone,
+{
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            BFTORMapOp::Update(k, op) => {
+                let mut bytes = vec![0u8];
+                bytes.extend_from_slice(&k.to_bytes());
+                bytes.extend_from_slice(&op.to_bytes());
+                bytes
+            }
+            BFTORMapOp::Remove(k, ids) => {
+                let mut bytes = vec![1u8];
+                let mut sorted_ids = ids.clone();
+                sorted_ids.sort();
+                for id in sorted_ids.iter() {
+                    bytes.extend_from_slice(id.as_bytes());
+                }
+                bytes.extend_from_slice(&k.to_bytes());
+                bytes
+            }
+        }
+    }
+}
+
+/// A change to a `BFTORMap`: a key appeared or disappeared, or its nested CRDT changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BFTORMapEvent<K, E> {
+    Inserted(K),
+    Removed(K),
+    Updated(K, E),
+}
+
+struct Entry<C> {
+    ids: HashSet<ORMapID>,
+    crdt: C,
+}
+
+pub struct BFTORMap<K, C>
+where
+    K: Eq + Hash + Clone + Serialize,
+{
+    entries: HashMap<K, Entry<C>>,
+}
+
+impl<K, C> BFTORMap<K, C>
+where
+    K: Eq + Hash + Clone + Serialize,
+    C: Default,
+{
+    pub fn new() -> Self {
+        BFTORMap {
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn update<O>(&mut self, k: K, op: O) -> BFTORMapOp<K, O> {
+        BFTORMapOp::Update(k, op)
+    }
+
+    pub fn remove<O>(&mut self, k: K) -> BFTORMapOp<K, O> {
+        let ids = self.entries.get(&k).map(|e| e.ids.iter().cloned().collect()).unwrap_or_default();
+        BFTORMapOp::Remove(k, ids)
+    }
+
+    pub fn contains_key(&self, k: &K) -> bool {
+        self.entries.get(k).is_some_and(|e| !e.ids.is_empty())
+    }
+
+    /// Returns the nested CRDT of `k` if the key is in the map.
+    pub fn get(&self, k: &K) -> Option<&C> {
+        self.entries.get(k).filter(|e| !e.ids.is_empty()).map(|e| &e.crdt)
+    }
+
+    /// Returns the nested CRDT of `k`, e.g. to generate an operation for `update`. The state
+    /// of a key that is not in the map is the state it would continue from.
+    pub fn child(&mut self, k: K) -> &mut C {
+        &mut self.entries.entry(k).or_insert_with(|| Entry { ids: HashSet::new(), crdt: C::default() }).crdt
+    }
+
+    pub fn get_keys(&self) -> HashSet<K> {
+        self.entries.iter().filter(|(_, e)| !e.ids.is_empty()).map(|(k, _)| k.clone()).collect()
+    }
+}
+
+impl<K, C> Default for BFTORMap<K, C>
+where
+    K: Eq + Hash + Clone + Serialize,
+    C: Default,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, O, C> BFTCRDT<BFTORMapOp<K, O>> for BFTORMap<K, C>
+where
+    K: Eq + Hash + Clone + Serialize,
+    O: Serialize + Clone,
+    C: BFTCRDT<O> + Default,
+{
+    type Event = BFTORMapEvent<K, C::Event>;
+
+    fn interpret_node(&mut self, node: &Node<BFTORMapOp<K, O>>) -> Vec<Self::Event> {
+        let op = &node.value;
+        match op {
+            BFTORMapOp::Update(k, child_op) => {
+                let entry = self.entries.entry(k.clone()).or_insert_with(|| Entry { ids: HashSet::new(), crdt: C::default() });
+                let inserted = entry.ids.is_empty();
+                entry.ids.insert(node.get_hash());
+                let child_events = entry.crdt.interpret_node(&node.project(child_op.clone()));
+                let mut events = vec![];
+                if inserted {
+                    events.push(BFTORMapEvent::Inserted(k.clone()));
+                }
+                events.extend(child_events.into_iter().map(|e| BFTORMapEvent::Updated(k.clone(), e)));
+                events
+            }
+            BFTORMapOp::Remove(k, ids) => {
+                if let Some(entry) = self.entries.get_mut(k) {
+                    let was_in = !entry.ids.is_empty();
+                    for id in ids {
+                        entry.ids.remove(id);
+                    }
+                    if was_in && entry.ids.is_empty() {
+                        return vec![BFTORMapEvent::Removed(k.clone())];
+                    }
+                }
+                vec![]
+            }
+        }
+    }
+
+    fn is_sem_valid(&self, node: &Node<BFTORMapOp<K, O>>, hash_graph: &dyn GraphView<BFTORMapOp<K, O>>) -> bool {
+        let op = &node.value;
+        match op {
+            // the nested CRDT judges its operation against the nodes of its key
+            BFTORMapOp::Update(k, child_op) => {
+                // the nodes of the shared graph that carry operations on the nested CRDT of `k`
+                let view = ProjectedView::new(
+                    hash_graph,
+                    |op: &BFTORMapOp<K, O>| match op {
+                        BFTORMapOp::Update(k2, child_op) if k2 == k => Some(child_op.clone()),
+                        _ => None,
+                    },
+                    |child_op| BFTORMapOp::Update(k.clone(), child_op),
+                );
+                let child_node = node.project(child_op.clone());
+                match self.entries.get(k) {
+                    Some(entry) => entry.crdt.is_sem_valid(&child_node, &view),
+                    None => C::default().is_sem_valid(&child_node, &view),
+                }
+            }
+            // as `is_orset_sem_valid` for Rem, with Update k in place of Add e
+            BFTORMapOp::Remove(k, ids) => {
+                ids.iter().all(|id| {
+                    match hash_graph.get_node(id) {
+                        Some(n) => {
+                            matches!(&n.value, BFTORMapOp::Update(k2, _) if k2 == k) && hash_graph.is_ancestor(id, node)
+                        }
+                        None => false,
+                    }
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use crate::bft_crdts::bft_orset::BFTORSet;
+    use crate::bft_crdts::bft_rga::{BFTRGA, BFTRGAEvent, BFTRGAOp};
+    use crate::bft_crdts::replica::Replica;
+    use super::*;
+
+    #[test]
+    fn test_bft_ormap_nested_rga() {
+        let mut local: Replica<_, BFTORMap<&str, BFTRGA<&str, &str>>> = Replica::new(BFTORMap::new());
+        let mut nodes = vec![];
+        for (key, values) in [("title", ["h", "i"]), ("body", ["y", "o"])] {
+            for (i, v) in values.iter().enumerate() {
+                let insert_op = local.crdt.child(key).insert(i, *v, "0").unwrap();
+                let op = local.crdt.update(key, insert_op);
+                nodes.push(local.handle_local_op(op));
+            }
+        }
+        assert_eq!(local.crdt.get(&"title").unwrap().get_list(), vec!["h", "i"]);
+        assert_eq!(local.crdt.get_keys(), HashSet::from(["title", "body"]));
+
+        let mut remote: Replica<_, BFTORMap<&str, BFTRGA<&str, &str>>> = Replica::new(BFTORMap::new());
+        let events = remote.record_events();
+        for node in nodes.iter().rev() {
+            remote.handle_remote_node(node.clone());
+        }
+        assert_eq!(remote.crdt.get(&"body").unwrap().get_list(), vec!["y", "o"]);
+        assert_eq!(events.borrow()[0], BFTORMapEvent::Inserted("title"));
+        assert_eq!(events.borrow()[1], BFTORMapEvent::Updated("title", BFTRGAEvent::Insert(0, "h")));
+
+        // a nested operation may only refer to elements of its own key
+        let BFTORMapOp::Update(_, BFTRGAOp::Insert(_, _, Some(title_ref))) = &nodes[1].value else { panic!() };
+        let cross_key = Node::new(remote.hash_graph.get_heads().to_vec(), BFTORMapOp::Update("body", BFTRGAOp::Insert("!", "1", Some(title_ref.clone()))));
+        assert!(!remote.crdt.is_sem_valid(&cross_key, &remote.hash_graph));
+        let same_key = Node::new(remote.hash_graph.get_heads().to_vec(), BFTORMapOp::Update("title", BFTRGAOp::Insert("!", "1", Some(title_ref.clone()))));
+        assert!(remote.crdt.is_sem_valid(&same_key, &remote.hash_graph));
+
+        let remove_op = local.crdt.remove("title");
+        let node = local.handle_local_op(remove_op);
+        remote.handle_remote_node(node);
+        assert!(!remote.crdt.contains_key(&"title"));
+        assert!(remote.crdt.get(&"title").is_none());
+        assert_eq!(events.borrow().last(), Some(&BFTORMapEvent::Removed("title")));
+    }
+
+    #[test]
+    fn test_bft_ormap_nested_orset() {
+        let mut handler: Replica<_, BFTORMap<&str, BFTORSet<&str>>> = Replica::new(BFTORMap::new());
+        let add_op = handler.crdt.child("tags").add("red");
+        let op = handler.crdt.update("tags", add_op);
+        handler.handle_local_op(op);
+        let remove_op = handler.crdt.child("tags").remove_elem("red");
+        let op = handler.crdt.update("tags", remove_op);
+        let node = handler.handle_local_op(op);
+        assert!(handler.crdt.get(&"tags").unwrap().get_set().is_empty());
+
+        // removing an element requires the Add to be an Update of the same key in the causal past
+        let BFTORMapOp::Update(_, remove_op) = node.value else { panic!() };
+        let other_key = Node::new(handler.hash_graph.get_heads().to_vec(), BFTORMapOp::Update("colors", remove_op));
+        assert!(!handler.crdt.is_sem_valid(&other_key, &handler.hash_graph));
+    }
+}
@@ -2,9 +2,10 @@ use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Display};
 use std::hash::Hash;
 use crate::bft_crdts::bft_crdt::BFTCRDT;
-use crate::bft_crdts::hash_graph::{HashGraph, Node};
+use crate::bft_crdts::hash_graph::{GraphView, Node};
 use tracing::{trace};
 use crate::bft_crdts::hash_graph::HashType;
+use crate::crdts::crdt::SetQuery;
 use crate::serialize::Serialize;
 
 type ORSetID = HashType; // in BFT ORSet, ID is the hash value of the element's Add operation
@@ -56,6 +57,13 @@ where
     }
 }
 
+/// A change to the observable contents of a `BFTORSet`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BFTORSetEvent<E> {
+    Appeared(E),
+    Disappeared(E),
+}
+
 pub struct BFTORSet<E>
 where
     E: Eq + Hash + Clone + Serialize,
@@ -105,28 +113,62 @@ where
     
 }
 
+impl<E> Default for BFTORSet<E>
+where
+    E: Eq + Hash + Clone + Serialize,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E> SetQuery<E> for BFTORSet<E>
+where
+    E: Eq + Hash + Clone + Serialize,
+{
+    fn contains(&self, e: &E) -> bool {
+        self.elements.get(e).is_some_and(|ids| !ids.is_empty())
+    }
+
+    fn elements(&self) -> HashSet<E> {
+        self.get_set()
+    }
+}
+
 impl<E> BFTCRDT<BFTORSetOp<E>> for BFTORSet<E>
 where
     E: Eq + Hash + Clone + Serialize,
 {
-    fn interpret_node(&mut self, node: &Node<BFTORSetOp<E>>) {
+    type Event = BFTORSetEvent<E>;
+
+    fn interpret_node(&mut self, node: &Node<BFTORSetOp<E>>) -> Vec<BFTORSetEvent<E>> {
         let op = &node.value;
         match op {
             BFTORSetOp::Add(e) => {
                 let id = node.get_hash();
-                self.elements.entry(e.clone()).or_insert(HashSet::new()).insert(id);
+                let e_ids = self.elements.entry(e.clone()).or_default();
+                let appeared = e_ids.is_empty();
+                e_ids.insert(id);
+                if appeared {
+                    return vec![BFTORSetEvent::Appeared(e.clone())];
+                }
             }
             BFTORSetOp::Remove(e, ids) => {
                 if let Some(e_ids) = self.elements.get_mut(e) {
+                    let was_in = !e_ids.is_empty();
                     for id in ids {
                         e_ids.remove(id);
                     }
+                    if was_in && e_ids.is_empty() {
+                        return vec![BFTORSetEvent::Disappeared(e.clone())];
+                    }
                 }
             }
         }
+        vec![]
     }
 
-    fn is_sem_valid(&self, node: &Node<BFTORSetOp<E>>, hash_graph: &HashGraph<BFTORSetOp<E>>) -> bool {
+    fn is_sem_valid(&self, node: &Node<BFTORSetOp<E>>, hash_graph: &dyn GraphView<BFTORSetOp<E>>) -> bool {
         // fun is_orset_sem_valid :: ‹('hash, 'a) ORSetC ⇒ ('hash, 'a) ORSetH ⇒ ('hash, 'a) ORSetN set ⇒ ('hash, 'a) ORSetN ⇒ bool› where
         //   ‹is_orset_sem_valid C H S (hs, Add e) = True›
         // | ‹is_orset_sem_valid C H S (hs, Rem is e) = 
@@ -163,4 +205,29 @@ where
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bft_crdts::replica::Replica;
+    use super::*;
+
+    #[test]
+    fn test_bft_orset_remote_events() {
+        let mut local = Replica::new(BFTORSet::new());
+        let add_op = local.crdt.add("a");
+        let first_add = local.handle_local_op(add_op.clone());
+        let second_add = local.handle_local_op(add_op);
+        let remove_op = local.crdt.remove_elem("a");
+        let remove = local.handle_local_op(remove_op);
+
+        let mut remote = Replica::new(BFTORSet::new());
+        let events = remote.record_events();
+
+        remote.handle_remote_node(first_add);
+        remote.handle_remote_node(second_add);
+        assert_eq!(*events.borrow(), vec![BFTORSetEvent::Appeared("a")]);
+        remote.handle_remote_node(remove);
+        assert_eq!(*events.borrow(), vec![BFTORSetEvent::Appeared("a"), BFTORSetEvent::Disappeared("a")]);
+    }
+}
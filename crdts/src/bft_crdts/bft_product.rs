@@ -0,0 +1,197 @@
+use std::fmt::Display;
+use crate::bft_crdts::bft_crdt::BFTCRDT;
+use crate::bft_crdts::hash_graph::{GraphView, Node, ProjectedView};
+use crate::serialize::Serialize;
+
+//  A BFT product lets two BFT CRDTs share one HashGraph, and thus one causal history and one
+//   stream of nodes between peers: every operation is tagged with the child it belongs to and
+//   is interpreted by that child only. A child judges its operations against the nodes of the
+//   shared graph that carry its own operations, while causality is that of the whole graph, so
+//   an operation of one child may follow operations of the other. Products nest to compose more
+//   than two CRDTs, e.g. `BFTProduct<BFTORSet<E>, BFTProduct<BFTRGA<I, V>, BFTPNCounter<A>>>`.
+//   As with the BFT ORMap, the operations of a child keep the hash of the node carrying them.
+
+#[derive(Debug, Clone)]
+pub enum BFTProductOp<OA, OB> {
+    Left(OA),
+    Right(OB),
+}
+
+impl<OA, OB> Display for BFTProductOp<OA, OB>
+where
+    OA: Display,
+    OB: Display,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BFTProductOp::Left(op) => {
+                write!(f, "Left({})", op)
+            }
+            BFTProductOp::Right(op) => {
+                write!(f, "Right({})", op)
+            }
+        }
+    }
+}
+
+impl<OA, OB> Serialize for BFTProductOp<OA, OB>
+where
+    OA: Serialize,
+    OB: Serialize,
+{
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            BFTProductOp::Left(op) => [vec![0u8], op.to_bytes()].concat(),
+            BFTProductOp::Right(op) => [vec![1u8], op.to_bytes()].concat(),
+        }
+    }
+}
+
+/// A change to one of the children of a `BFTProduct`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BFTProductEvent<EA, EB> {
+    Left(EA),
+    Right(EB),
+}
+
+pub struct BFTProduct<A, B> {
+    pub left: A,
+    pub right: B,
+}
+
+impl<A, B> BFTProduct<A, B> {
+    pub fn new(left: A, right: B) -> Self {
+        BFTProduct { left, right }
+    }
+}
+
+impl<A: Default, B: Default> Default for BFTProduct<A, B> {
+    fn default() -> Self {
+        Self::new(A::default(), B::default())
+    }
+}
+
+impl<OA, OB, A, B> BFTCRDT<BFTProductOp<OA, OB>> for BFTProduct<A, B>
+where
+    OA: Serialize + Clone,
+    OB: Serialize + Clone,
+    A: BFTCRDT<OA>,
+    B: BFTCRDT<OB>,
+{
+    type Event = BFTProductEvent<A::Event, B::Event>;
+
+    fn interpret_node(&mut self, node: &Node<BFTProductOp<OA, OB>>) -> Vec<Self::Event> {
+        match &node.value {
+            BFTProductOp::Left(op) => {
+                self.left.interpret_node(&node.project(op.clone())).into_iter().map(BFTProductEvent::Left).collect()
+            }
+            BFTProductOp::Right(op) => {
+                self.right.interpret_node(&node.project(op.clone())).into_iter().map(BFTProductEvent::Right).collect()
+            }
+        }
+    }
+
+    fn is_sem_valid(&self, node: &Node<BFTProductOp<OA, OB>>, hash_graph: &dyn GraphView<BFTProductOp<OA, OB>>) -> bool {
+        match &node.value {
+            BFTProductOp::Left(op) => {
+                let view = ProjectedView::new(
+                    hash_graph,
+                    |op: &BFTProductOp<OA, OB>| match op {
+                        BFTProductOp::Left(op) => Some(op.clone()),
+                        _ => None,
+                    },
+                    BFTProductOp::Left,
+                );
+                self.left.is_sem_valid(&node.project(op.clone()), &view)
+            }
+            BFTProductOp::Right(op) => {
+                let view = ProjectedView::new(
+                    hash_graph,
+                    |op: &BFTProductOp<OA, OB>| match op {
+                        BFTProductOp::Right(op) => Some(op.clone()),
+                        _ => None,
+                    },
+                    BFTProductOp::Right,
+                );
+                self.right.is_sem_valid(&node.project(op.clone()), &view)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bft_crdts::bft_counter::{BFTPNCounter, BFTPNCounterOp};
+    use crate::bft_crdts::bft_orset::{BFTORSet, BFTORSetEvent, BFTORSetOp};
+    use crate::bft_crdts::bft_rga::BFTRGA;
+    use crate::bft_crdts::replica::Replica;
+    use rand::SeedableRng;
+    use rand::seq::SliceRandom;
+    use rand_pcg::Pcg32;
+    use super::*;
+
+    type SetListCounter = BFTProduct<BFTORSet<&'static str>, BFTProduct<BFTRGA<&'static str, &'static str>, BFTPNCounter<&'static str>>>;
+
+    fn set_list_counter() -> SetListCounter {
+        // only "a" can be authenticated
+        let counter = BFTPNCounter::with_author_cap(10, |node: &Node<BFTPNCounterOp<&'static str>>| Some(*node.value.author()).filter(|a| *a == "a"));
+        BFTProduct::new(BFTORSet::new(), BFTProduct::new(BFTRGA::default(), counter))
+    }
+
+    #[test]
+    fn test_bft_product_shares_one_graph() {
+        let mut local = Replica::new(set_list_counter());
+        let mut nodes = vec![];
+        let op = local.crdt.left.add("draft");
+        nodes.push(local.handle_local_op(BFTProductOp::Left(op)));
+        for (i, v) in ["a", "b"].iter().enumerate() {
+            let op = local.crdt.right.left.insert(i, *v, "0").unwrap();
+            nodes.push(local.handle_local_op(BFTProductOp::Right(BFTProductOp::Left(op))));
+        }
+        let op = local.crdt.right.right.increment("a", 3);
+        nodes.push(local.handle_local_op(BFTProductOp::Right(BFTProductOp::Right(op))));
+        let op = local.crdt.left.remove_elem("draft");
+        nodes.push(local.handle_local_op(BFTProductOp::Left(op)));
+
+        let mut rng = Pcg32::seed_from_u64(3);
+        for _ in 0..10 {
+            nodes.shuffle(&mut rng);
+            let mut remote = Replica::new(set_list_counter());
+            let events = remote.record_events();
+            for node in nodes.iter() {
+                remote.handle_remote_node(node.clone());
+            }
+            assert!(remote.pending_nodes.is_empty());
+            assert!(remote.crdt.left.get_set().is_empty());
+            assert_eq!(remote.crdt.right.left.get_list(), vec!["a", "b"]);
+            assert_eq!(remote.crdt.right.right.get_value(), 3);
+            // every node is interpreted after all of its predecessors, whichever child they belong to
+            assert_eq!(events.borrow()[0], BFTProductEvent::Left(BFTORSetEvent::Appeared("draft")));
+            assert_eq!(events.borrow().last(), Some(&BFTProductEvent::Left(BFTORSetEvent::Disappeared("draft"))));
+        }
+    }
+
+    #[test]
+    fn test_bft_product_sem_validity() {
+        let mut handler = Replica::new(set_list_counter());
+        let op = handler.crdt.left.add("x");
+        let add = handler.handle_local_op(BFTProductOp::Left(op));
+        let op = handler.crdt.right.left.insert(0, "a", "0").unwrap();
+        let insert = handler.handle_local_op(BFTProductOp::Right(BFTProductOp::Left(op)));
+        let heads = handler.hash_graph.get_heads().to_vec();
+
+        let valid = Node::new(heads.clone(), BFTProductOp::Left(BFTORSetOp::Remove("x", vec![add.get_hash()])));
+        assert!(handler.crdt.is_sem_valid(&valid, &handler.hash_graph));
+        // an operation may only refer to operations of its own child
+        let cross_child = Node::new(heads.clone(), BFTProductOp::Left(BFTORSetOp::Remove("x", vec![insert.get_hash()])));
+        assert!(!handler.crdt.is_sem_valid(&cross_child, &handler.hash_graph));
+        // the referenced operation must be in the causal past in the shared graph
+        let concurrent = Node::new(vec![], BFTProductOp::Left(BFTORSetOp::Remove("x", vec![add.get_hash()])));
+        assert!(!handler.crdt.is_sem_valid(&concurrent, &handler.hash_graph));
+        // the children validate their own operations, e.g. the author cap of the counter
+        let over_cap = Node::new(heads.clone(), BFTProductOp::Right(BFTProductOp::Right(BFTPNCounterOp::Increment("a", 11))));
+        assert!(!handler.crdt.is_sem_valid(&over_cap, &handler.hash_graph));
+        let unauthenticated = Node::new(heads, BFTProductOp::Right(BFTProductOp::Right(BFTPNCounterOp::Increment("b", 1))));
+        assert!(!handler.crdt.is_sem_valid(&unauthenticated, &handler.hash_graph));
+    }
+}
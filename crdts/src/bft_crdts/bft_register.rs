@@ -0,0 +1,303 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+use crate::bft_crdts::bft_crdt::BFTCRDT;
+use crate::bft_crdts::hash_graph::{GraphView, HashType, Node};
+use crate::serialize::Serialize;
+
+//  A write to a register lists the writes it overwrites, i.e. the writes the peer sees as the
+//   current values of the register. As in the BFT ORSet, the ID of a write is the hash of the
+//   node containing it. Unlike the ORSet, a peer must not leave out any current value: a write
+//   that overwrote only some of them would let a causally older value win over a newer one in
+//   the LWW register. The current values are the writes that no write has overwritten, which
+//   are the concurrent writes among the HashGraph heads. The LWW register picks the one with
+//   the greatest hash, so no peer-supplied timestamp is trusted.
+
+type RegisterID = HashType; // in BFT registers, ID is the hash value of the Write operation
+
+#[derive(Debug, Clone)]
+pub enum BFTRegisterOp<V> {
+    // v, overwritten ids
+    Write(V, Vec<RegisterID>),
+}
+
+impl <V> Display for BFTRegisterOp<V>
+where
+    V: Eq + Hash + Clone + Serialize + Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BFTRegisterOp::Write(v, ids) => {
+                write!(f, "Write({:?}, {:?})", v, ids)
+            }
+        }
+    }
+}
+
+impl<V> Serialize for BFTRegisterOp<V>
+where
+    V: Eq + Hash + Clone + Serialize,
+{
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            BFTRegisterOp::Write(v, ids) => {
+                let mut bytes = vec![];
+                let mut sorted_ids = ids.clone();
+                sorted_ids.sort();
+                for id in sorted_ids.iter() {
+                    bytes.extend_from_slice(id.as_bytes());
+                }
+                bytes.extend_from_slice(&v.to_bytes());
+                bytes
+            }
+        }
+    }
+}
+
+/// A change to the value(s) of a register.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BFTRegisterEvent<V> {
+    Assigned(V),
+    Unassigned(V),
+}
+
+pub struct BFTMVRegister<V>
+where
+    V: Eq + Hash + Clone + Serialize,
+{
+    // the writes that have not been overwritten, ordered by hash
+    values: BTreeMap<RegisterID, V>,
+    // the writes each interpreted write overwrote
+    overwrites: HashMap<RegisterID, Vec<RegisterID>>,
+    // the writes each interpreted write was overwritten by
+    overwritten_by: HashMap<RegisterID, Vec<RegisterID>>,
+}
+
+impl<V> BFTMVRegister<V>
+where
+    V: Eq + Hash + Clone + Serialize,
+{
+    pub fn new() -> Self {
+        BFTMVRegister {
+            values: BTreeMap::new(),
+            overwrites: HashMap::new(),
+            overwritten_by: HashMap::new(),
+        }
+    }
+
+    pub fn write(&mut self, v: V) -> BFTRegisterOp<V> {
+        BFTRegisterOp::Write(v, self.values.keys().cloned().collect())
+    }
+
+    /// Returns the values of the concurrent writes that have not been overwritten, ordered by
+    /// the hashes of the writes.
+    pub fn get(&self) -> Vec<V> {
+        self.values.values().cloned().collect()
+    }
+
+    pub fn get_ids(&self) -> Vec<RegisterID> {
+        self.values.keys().cloned().collect()
+    }
+
+    // Returns the current values in the causal past of `node`, i.e. the writes in its causal
+    // past that no write in its causal past overwrote. Every write is overwritten by one that
+    // is current or overwritten in turn, so they are found by going back from the current
+    // values along the overwritten writes until reaching the causal past of the node. Only the
+    // writes concurrent to the node are visited besides the result.
+    fn current_values_before(&self, node: &Node<BFTRegisterOp<V>>, hash_graph: &dyn GraphView<BFTRegisterOp<V>>) -> HashSet<RegisterID> {
+        let mut current = HashSet::new();
+        let mut visited = HashSet::new();
+        let mut stack: Vec<&RegisterID> = self.values.keys().collect();
+        while let Some(id) = stack.pop() {
+            if !visited.insert(id) {
+                continue;
+            }
+            if !hash_graph.is_ancestor(id, node) {
+                stack.extend(self.overwrites[id].iter());
+            } else if !self.overwritten_by[id].iter().any(|by| hash_graph.is_ancestor(by, node)) {
+                current.insert(id.clone());
+            }
+        }
+        current
+    }
+
+    // A write may only overwrite writes in its causal past, and must overwrite all current
+    // values there.
+    fn is_write_valid(&self, node: &Node<BFTRegisterOp<V>>, hash_graph: &dyn GraphView<BFTRegisterOp<V>>) -> bool {
+        let BFTRegisterOp::Write(_v, ids) = &node.value;
+        let ids_in_past = ids.iter().all(|id| self.overwrites.contains_key(id) && hash_graph.is_ancestor(id, node));
+        ids_in_past && self.current_values_before(node, hash_graph).iter().all(|id| ids.contains(id))
+    }
+}
+
+impl<V> Default for BFTMVRegister<V>
+where
+    V: Eq + Hash + Clone + Serialize,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> BFTCRDT<BFTRegisterOp<V>> for BFTMVRegister<V>
+where
+    V: Eq + Hash + Clone + Serialize,
+{
+    type Event = BFTRegisterEvent<V>;
+
+    fn interpret_node(&mut self, node: &Node<BFTRegisterOp<V>>) -> Vec<BFTRegisterEvent<V>> {
+        let BFTRegisterOp::Write(v, ids) = &node.value;
+        let hash = node.get_hash();
+        let mut events = vec![];
+        for id in ids {
+            if let Some(old) = self.values.remove(id) {
+                events.push(BFTRegisterEvent::Unassigned(old));
+            }
+            self.overwritten_by.entry(id.clone()).or_default().push(hash.clone());
+        }
+        self.overwrites.insert(hash.clone(), ids.clone());
+        self.overwritten_by.entry(hash.clone()).or_default();
+        self.values.insert(hash, v.clone());
+        events.push(BFTRegisterEvent::Assigned(v.clone()));
+        events
+    }
+
+    fn is_sem_valid(&self, node: &Node<BFTRegisterOp<V>>, hash_graph: &dyn GraphView<BFTRegisterOp<V>>) -> bool {
+        self.is_write_valid(node, hash_graph)
+    }
+}
+
+/// A register holding the value of the write that wins among the concurrent current writes
+/// of a `BFTMVRegister`, i.e. the one with the greatest hash.
+pub struct BFTLWWRegister<V>
+where
+    V: Eq + Hash + Clone + Serialize,
+{
+    register: BFTMVRegister<V>,
+}
+
+impl<V> BFTLWWRegister<V>
+where
+    V: Eq + Hash + Clone + Serialize,
+{
+    pub fn new() -> Self {
+        BFTLWWRegister {
+            register: BFTMVRegister::new(),
+        }
+    }
+
+    pub fn write(&mut self, v: V) -> BFTRegisterOp<V> {
+        self.register.write(v)
+    }
+
+    pub fn get(&self) -> Option<V> {
+        self.register.values.values().next_back().cloned()
+    }
+
+    /// Returns the ID of the winning write, i.e. the hash of its node.
+    pub fn get_id(&self) -> Option<RegisterID> {
+        self.register.values.keys().next_back().cloned()
+    }
+}
+
+impl<V> Default for BFTLWWRegister<V>
+where
+    V: Eq + Hash + Clone + Serialize,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> BFTCRDT<BFTRegisterOp<V>> for BFTLWWRegister<V>
+where
+    V: Eq + Hash + Clone + Serialize,
+{
+    type Event = BFTRegisterEvent<V>;
+
+    fn interpret_node(&mut self, node: &Node<BFTRegisterOp<V>>) -> Vec<BFTRegisterEvent<V>> {
+        let before = self.register.values.keys().next_back().cloned();
+        let old = self.get();
+        self.register.interpret_node(node);
+        if self.register.values.keys().next_back().cloned() == before {
+            return vec![];
+        }
+        let mut events = vec![];
+        if let Some(old) = old {
+            events.push(BFTRegisterEvent::Unassigned(old));
+        }
+        if let Some(new) = self.get() {
+            events.push(BFTRegisterEvent::Assigned(new));
+        }
+        events
+    }
+
+    fn is_sem_valid(&self, node: &Node<BFTRegisterOp<V>>, hash_graph: &dyn GraphView<BFTRegisterOp<V>>) -> bool {
+        self.register.is_write_valid(node, hash_graph)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bft_crdts::replica::Replica;
+    use super::*;
+
+    #[test]
+    fn test_bft_registers() {
+        let mut a = Replica::new(BFTMVRegister::new());
+        let mut b = Replica::new(BFTMVRegister::new());
+        let write_op = a.crdt.write(1);
+        let first = a.handle_local_op(write_op);
+        b.handle_remote_node(first.clone());
+        assert_eq!(b.crdt.get(), vec![1]);
+
+        let write_op = a.crdt.write(2);
+        let a_node = a.handle_local_op(write_op);
+        let write_op = b.crdt.write(3);
+        let b_node = b.handle_local_op(write_op);
+        a.handle_remote_node(b_node.clone());
+        b.handle_remote_node(a_node.clone());
+        let mut values = a.crdt.get();
+        assert_eq!(values, b.crdt.get());
+        values.sort();
+        assert_eq!(values, vec![2, 3]);
+
+        // the LWW register picks the same concurrent write on every replica
+        let mut c = Replica::new(BFTLWWRegister::new());
+        let mut d = Replica::new(BFTLWWRegister::new());
+        for node in [first.clone(), a_node.clone(), b_node.clone()] {
+            c.handle_remote_node(node);
+        }
+        for node in [first, b_node, a_node] {
+            d.handle_remote_node(node);
+        }
+        assert!(c.pending_nodes.is_empty());
+        assert_eq!(c.crdt.get(), d.crdt.get());
+        assert!(c.crdt.get() == Some(2) || c.crdt.get() == Some(3));
+
+        // a write must overwrite all current values
+        let write_op = a.crdt.write(4);
+        let node = a.handle_local_op(write_op);
+        c.handle_remote_node(node);
+        assert_eq!(c.crdt.get(), Some(4));
+        assert_eq!(a.crdt.get(), vec![4]);
+        let partial_ids = vec![b.crdt.get_ids()[0].clone()];
+        let partial = Node::new(b.hash_graph.get_heads().to_vec(), BFTRegisterOp::Write(5, partial_ids));
+        assert!(!b.crdt.is_sem_valid(&partial, &b.hash_graph));
+        let unknown = Node::new(vec![], BFTRegisterOp::Write(5, vec!["0".repeat(64)]));
+        assert!(!b.crdt.is_sem_valid(&unknown, &b.hash_graph));
+
+        // a write must overwrite the current values of its causal past, even those that a
+        // concurrent write has overwritten since
+        let mut e = Replica::new(BFTMVRegister::new());
+        let write_op = e.crdt.write(6);
+        let old = e.handle_local_op(write_op);
+        let skipping = Node::new(vec![old.get_hash()], BFTRegisterOp::Write(7, vec![]));
+        assert!(!e.crdt.is_sem_valid(&skipping, &e.hash_graph));
+        let write_op = e.crdt.write(8);
+        e.handle_local_op(write_op);
+        assert!(!e.crdt.is_sem_valid(&skipping, &e.hash_graph));
+        let overwriting = Node::new(vec![old.get_hash()], BFTRegisterOp::Write(7, vec![old.get_hash()]));
+        assert!(e.crdt.is_sem_valid(&overwriting, &e.hash_graph));
+    }
+}
@@ -1,8 +1,10 @@
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt::{Debug, Display};
 use std::hash::Hash;
-use crate::bft_crdts::hash_graph::{HashGraph, HashType, Node};
+use std::ops::Range;
+use crate::bft_crdts::hash_graph::{GraphView, HashGraph, HashType, Node};
 use crate::bft_crdts::bft_crdt::BFTCRDT;
-use crate::crdts::ordered_list::OrderedList;
+use crate::crdts::ordered_list::{OrderedList, SpanId};
 use crate::serialize::Serialize;
 
 //  The ID of each element in RGA affects the position of the element in the list, since 
@@ -14,15 +16,136 @@ use crate::serialize::Serialize;
 //   we first compare the IDs chosen by the peers. If they are identical, we then compare the hashes. 
 //   This approach ensures that the operation generator can control the position of the inserted element, 
 //   while still guaranteeing the uniqueness of the ID.
+//
+//  A Move of an element inserts a new position for it, with an ID of its own that is chosen
+//   the same way, and the element is shown at the position with the greatest ID; its other
+//   positions remain as tombstones. The ID of a move must be greater than those of all positions
+//   of the element in its causal past, so a move made after seeing another move always wins,
+//   and the IDs only decide between concurrent moves. These agree on one position without
+//   duplicating the element. A deleted element stays deleted when it is moved.
+//
+//  A `LamportId`, as allocated by `insert_auto`, is only valid if its counter is at most one
+//   more than the greatest counter in the causal past of the node. Otherwise a faulty peer could
+//   pick a huge counter, and the correct peers allocating after it would run out of counters.
+//   Other IDs, e.g. plain integers, are left to the caller and only need to be valid as above.
 
 type RGAID<I> = (I, HashType);
 
+/// A peer-chosen element ID that a replica can allocate on its own: `BFTRGA::insert_auto`
+/// picks a counter greater than that of every element the replica has seen, and the replica
+/// number breaks ties between concurrent inserts. Since `counter` is compared first, a new
+/// element is placed right after its predecessor instead of being skipped over by elements
+/// that were inserted there earlier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LamportId {
+    pub counter: u64,
+    pub replica: u64,
+}
+
+impl Serialize for LamportId {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.counter.to_bytes();
+        bytes.extend_from_slice(&self.replica.to_bytes());
+        bytes
+    }
+}
+
+/// The Lamport counter of a peer-chosen element ID, if it has one. A `BFTRGA` only accepts a
+/// counter that is at most one more than the greatest counter in the causal past of its node.
+/// Only `LamportId` has one; IDs chosen by the caller, like integers, do not.
+pub trait LamportCounter {
+    fn lamport_counter(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl LamportCounter for LamportId {
+    fn lamport_counter(&self) -> Option<u64> {
+        Some(self.counter)
+    }
+}
+
+impl LamportCounter for u64 {}
+
+impl LamportCounter for String {}
+
+impl LamportCounter for &str {}
+
+// consecutive inserts of one replica form a run
+impl SpanId for LamportId {
+    type Key = (u64, u64);
+    type Extra = ();
+
+    fn span_key(&self) -> (u64, u64) {
+        (self.replica, self.counter)
+    }
+
+    fn extra(&self) {}
+
+    fn nth_successor(&self, n: usize, _extra: ()) -> Option<LamportId> {
+        let counter = self.counter.nth_successor(n, ())?;
+        Some(LamportId { counter, replica: self.replica })
+    }
+
+    fn offset_from(&self, start: &LamportId) -> Option<usize> {
+        if self.replica == start.replica { self.counter.offset_from(&start.counter) } else { None }
+    }
+}
+
+/// Which neighbour of a gap between two elements an `Anchor` sticks to. With `Left` bias, text
+/// inserted into the gap later ends up after the anchor; with `Right` bias it ends up before.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bias {
+    Left,
+    Right,
+}
+
+/// A position in a `BFTRGA` that survives concurrent edits, e.g. a cursor or one end of a
+/// selection. The anchor refers to the element on its biased side, or to the start (left bias)
+/// or end (right bias) of the list if `id` is `None`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Anchor<I> {
+    pub id: Option<RGAID<I>>,
+    pub bias: Bias,
+}
+
+/// A change to the visible contents of a `BFTRGA`, by index into the list as it was right
+/// before the change. Moving an element is reported as its deletion and reinsertion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BFTRGAEvent<V> {
+    Insert(usize, V),
+    Delete(usize, V),
+}
+
+/// Who inserted a run of visible elements of a `BFTRGA`, see `BFTRGA::blame`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Blame<A> {
+    /// The indices of the elements in the list.
+    pub range: Range<usize>,
+    pub author: A,
+    /// The hash of the node that inserted each element, in list order.
+    pub inserted_by: Vec<HashType>,
+}
+
+/// Who inserted and who deleted a deleted element of a `BFTRGA`, see `BFTRGA::blame_deletions`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeletionBlame<V, A> {
+    /// The index of the gap the element used to be in.
+    pub index: usize,
+    pub value: V,
+    pub inserted_by: (HashType, A),
+    /// The nodes that deleted the element concurrently, usually just one.
+    pub deleted_by: Vec<(HashType, A)>,
+}
+
 #[derive(Debug, Clone)]
 pub enum BFTRGAOp<I, V> {
     // v, i, ei
     Insert(V, I, Option<RGAID<I>>),
     // ei
     Delete(RGAID<I>),
+    // ei of the moved element, i, ei of the element the new position follows
+    Move(RGAID<I>, I, Option<RGAID<I>>),
 }
 
 impl <I, V> Display for BFTRGAOp<I, V>
@@ -38,6 +161,9 @@ where
             BFTRGAOp::Delete(rga_id) => {
                 write!(f, "Delete({:?})", rga_id)
             }
+            BFTRGAOp::Move(rga_id, i, after) => {
+                write!(f, "Move({:?}, {:?}, {:?})", rga_id, i, after)
+            }
         }
     }
 }
@@ -48,25 +174,23 @@ where
     V: Eq + Hash + Clone + Serialize,
 {
     fn to_bytes(&self) -> Vec<u8> {
+        // every variant is tagged and every field is length-prefixed, so that no two
+        // operations serialize to the same bytes
+        let field = |bytes: Vec<u8>| [(bytes.len() as u64).to_bytes(), bytes].concat();
+        let rga_id = |(id, hash): &RGAID<I>| [field(id.to_bytes()), field(hash.to_bytes())].concat();
+        let after = |after: &Option<RGAID<I>>| match after {
+            Some(ei) => [vec![1u8], rga_id(ei)].concat(),
+            None => vec![0u8],
+        };
         match self {
-            BFTRGAOp::Insert(v, i, rga_id) => {
-                let mut bytes = vec![];
-                bytes.extend_from_slice(&v.to_bytes());
-                bytes.extend_from_slice(&i.to_bytes());
-                match rga_id {
-                    Some((id, hash)) => {
-                        bytes.extend_from_slice(&id.to_bytes());
-                        bytes.extend_from_slice(hash.as_bytes());
-                    }
-                    None => {}
-                }
-                bytes
+            BFTRGAOp::Insert(v, i, ei) => {
+                [vec![0u8], field(v.to_bytes()), field(i.to_bytes()), after(ei)].concat()
             }
-            BFTRGAOp::Delete(rga_id) => {
-                let mut bytes = vec![];
-                bytes.extend_from_slice(&rga_id.0.to_bytes());
-                bytes.extend_from_slice(&rga_id.1.as_bytes());
-                bytes
+            BFTRGAOp::Delete(ei) => {
+                [vec![1u8], rga_id(ei)].concat()
+            }
+            BFTRGAOp::Move(ei, i, ei2) => {
+                [vec![2u8], rga_id(ei), field(i.to_bytes()), after(ei2)].concat()
             }
         }
     }
@@ -74,32 +198,88 @@ where
 
 pub struct BFTRGA<I, V>
 where
-    I: Eq + Hash + Clone + Serialize + PartialOrd,
+    I: Eq + Hash + Clone + Serialize + PartialOrd + SpanId + LamportCounter,
     V: Eq + Hash + Clone + Serialize,
 {
     elements: OrderedList<RGAID<I>, V>,
+    // hashes of the Delete nodes of each deleted element that has not been collected
+    deleted_by: HashMap<RGAID<I>, Vec<HashType>>,
+    // the positions of each moved element that have not been collected, and the element of
+    // each such position
+    positions: HashMap<RGAID<I>, Vec<RGAID<I>>>,
+    moved: HashMap<RGAID<I>, RGAID<I>>,
+    // the greatest peer-chosen ID of all interpreted inserts, see `insert_auto`
+    max_id: Option<I>,
+    // the greatest Lamport counter of the inserts and moves in the causal past of each
+    // interpreted node, as far as the interpreted nodes tell
+    max_counter: HashMap<HashType, u64>,
+    replica: Option<u64>,
+    // whether references are checked as `collect_tombstones` needs them to be
+    collects_tombstones: bool,
 }
 
 impl <I, V> BFTCRDT<BFTRGAOp<I, V>> for BFTRGA<I, V>
 where
-    I: Eq + Hash + Clone + Serialize + PartialOrd,
+    I: Eq + Hash + Clone + Serialize + PartialOrd + SpanId + LamportCounter,
     V: Eq + Hash + Clone + Serialize,
 {
-    fn interpret_node(&mut self, node: &Node<BFTRGAOp<I, V>>) {
+    type Event = BFTRGAEvent<V>;
+
+    fn interpret_node(&mut self, node: &Node<BFTRGAOp<I, V>>) -> Vec<BFTRGAEvent<V>> {
         let op = &node.value;
+        self.record_counter(node);
         match op {
             BFTRGAOp::Insert(value, id, after) => {
                 let h = node.get_hash();
-                self.elements.insert_by_id((id.clone(), h.clone()), value.clone(), after.clone());
+                let eid = (id.clone(), h.clone());
+                self.record_id(id);
+                self.elements.insert_by_id(eid.clone(), value.clone(), after.clone());
+                self.elements.index_of(&eid)
+                    .map(|idx| vec![BFTRGAEvent::Insert(idx, value.clone())])
+                    .unwrap_or_default()
             }
             BFTRGAOp::Delete(eid) => {
-                self.elements.delete_by_id(eid.clone());
+                let position = self.position_of(eid);
+                let deleted = self.elements.index_of(&position).zip(self.elements.get_by_id(position.clone()));
+                self.elements.delete_by_id(position);
+                self.deleted_by.entry(eid.clone()).or_default().push(node.get_hash());
+                deleted
+                    .map(|(idx, (_, value, _))| vec![BFTRGAEvent::Delete(idx, value)])
+                    .unwrap_or_default()
+            }
+            BFTRGAOp::Move(eid, id, after) => {
+                let new_position = (id.clone(), node.get_hash());
+                self.record_id(id);
+                let old_position = self.position_of(eid);
+                let Some((_, value, _)) = self.elements.get_by_id(old_position.clone()) else { return vec![] };
+                let old_idx = self.elements.index_of(&old_position);
+                self.elements.insert_by_id(new_position.clone(), value.clone(), after.clone());
+                self.moved.insert(new_position.clone(), eid.clone());
+                self.positions.entry(eid.clone()).or_default().push(new_position.clone());
+                if self.deleted_by.contains_key(eid) || new_position < old_position {
+                    self.elements.delete_by_id(new_position);
+                    return vec![];
+                }
+                self.elements.delete_by_id(old_position);
+                let mut events = vec![];
+                if let Some(idx) = old_idx {
+                    events.push(BFTRGAEvent::Delete(idx, value.clone()));
+                }
+                if let Some(idx) = self.elements.index_of(&new_position) {
+                    events.push(BFTRGAEvent::Insert(idx, value));
+                }
+                events
             }
         }
     }
 
-    fn is_sem_valid(&self, node: &Node<BFTRGAOp<I, V>>, hash_graph: &HashGraph<BFTRGAOp<I, V>>) -> bool {
+    fn is_sem_valid(&self, node: &Node<BFTRGAOp<I, V>>, hash_graph: &dyn GraphView<BFTRGAOp<I, V>>) -> bool {
         let op = node.clone().value;
+        if let BFTRGAOp::Insert(_, i, _) = &op {
+            if !self.is_counter_valid(i, node, hash_graph) {
+                return false;
+            }
+        }
         match op {
             // ‹is_rga_sem_valid C H G (hs, Insert v i ei) = (
             //     case ei of
@@ -115,14 +295,20 @@ where
                     Some((id, hash)) => {
                         let ref_node_res = hash_graph.get_node(&hash); // H (hs', Insert v' i' ei') = snd ii
                         if let Some(ref_node) = ref_node_res {
-                            if let BFTRGAOp::Insert(_v2, i2, _ei2) = &ref_node.value {
+                            // the position of a Move can be referred to like that of an Insert
+                            if let BFTRGAOp::Insert(_, i2, _) | BFTRGAOp::Move(_, i2, _) = &ref_node.value {
                                 let ref_hash = ref_node.get_hash();
                                 // fast path
-                                if !hash_graph.nodes.contains_key(&ref_hash) {
+                                if !hash_graph.contains(&ref_hash) {
                                     return false;
                                 }
                                 if hash_graph.is_ancestor(&ref_hash, node) && &id == i2 {
-                                    true
+                                    let position = (id, hash);
+                                    !self.collects_tombstones || (
+                                        self.elements.get_by_id(position.clone()).is_some()
+                                            && !self.is_superseded(&position, node, hash_graph)
+                                            && self.is_live_reference(&position, node, hash_graph)
+                                    )
                                 } else {
                                     false
                                 }
@@ -149,7 +335,7 @@ where
                     if let BFTRGAOp::Insert(v2, i2, ei2) = &n.value {
                         if &ei.0 == i2 {
                             if hash_graph.is_ancestor(&hash, node) {
-                                true
+                                !self.collects_tombstones || self.is_live_reference(&ei, node, hash_graph)
                             } else {
                                 false
                             }
@@ -163,21 +349,162 @@ where
                     false
                 }
             }
+            // Not part of the Isabelle specification: a Move is valid if deleting its element
+            // and inserting the element's value at the new position would be, and if its ID is
+            // greater than those of the element's positions in its causal past.
+            BFTRGAOp::Move(ei, i, after) => {
+                let Some(inserted) = hash_graph.get_node(&ei.1) else { return false };
+                let BFTRGAOp::Insert(v, ..) = &inserted.value else { return false };
+                let later = |p: &RGAID<I>| i > p.0 || !hash_graph.is_ancestor(&p.1, node);
+                if !later(&ei) || !self.positions.get(&ei).into_iter().flatten().all(later) {
+                    return false;
+                }
+                self.is_sem_valid(&node.project(BFTRGAOp::Delete(ei)), hash_graph)
+                    && self.is_sem_valid(&node.project(BFTRGAOp::Insert(v.clone(), i, after)), hash_graph)
+            }
         }
     }
 }
 
 impl<I, V> BFTRGA<I, V> 
 where 
-    I: Eq + Hash + Clone + Serialize + PartialOrd,
+    I: Eq + Hash + Clone + Serialize + PartialOrd + SpanId + LamportCounter,
     V: Eq + Hash + Clone + Serialize,
 {
-    pub fn new() -> Self {
+    /// Creates an empty list for the replica with the given number, which must differ from
+    /// the numbers of all other replicas that insert with `insert_auto`. A list that only gets
+    /// IDs from its caller, e.g. one nested in another CRDT, can be created with `default`.
+    pub fn new(replica: u64) -> Self {
         BFTRGA {
-            elements: OrderedList::new(),
+            replica: Some(replica),
+            ..Self::default()
+        }
+    }
+
+    /// Creates an empty list like `new` whose tombstones can be collected. Its nodes must
+    /// satisfy stricter rules than those of the Isabelle specification (see
+    /// `collect_tombstones`), so either all replicas of the list are created this way or none.
+    pub fn with_tombstone_collection(replica: u64) -> Self {
+        BFTRGA {
+            collects_tombstones: true,
+            ..Self::new(replica)
+        }
+    }
+
+    fn record_id(&mut self, id: &I) {
+        if self.max_id.as_ref().is_none_or(|max| id > max) {
+            self.max_id = Some(id.clone());
+        }
+    }
+
+    fn record_counter(&mut self, node: &Node<BFTRGAOp<I, V>>) {
+        let own = match &node.value {
+            BFTRGAOp::Insert(_, id, _) | BFTRGAOp::Move(_, id, _) => id.lamport_counter(),
+            BFTRGAOp::Delete(_) => None,
+        };
+        let preds = node.predecessors.iter().filter_map(|p| self.max_counter.get(p).copied());
+        if let Some(max) = preds.chain(own).max() {
+            self.max_counter.insert(node.get_hash(), max);
         }
     }
 
+    // Not part of the Isabelle specification: a counter is valid if it is 0 or if an insert or
+    // move in the causal past of the node has a counter at most one less. The causal past is
+    // searched from the predecessors on, which usually ends at one of them; if it reaches a
+    // node the view does not show, e.g. a mark of a rich text, the whole causal past is checked.
+    fn is_counter_valid(&self, id: &I, node: &Node<BFTRGAOp<I, V>>, hash_graph: &dyn GraphView<BFTRGAOp<I, V>>) -> bool {
+        let counter = match id.lamport_counter() {
+            None | Some(0) => return true,
+            Some(counter) => counter,
+        };
+        // no interpreted node has a counter that high
+        let max_id = self.max_id.as_ref().and_then(|max| max.lamport_counter());
+        if max_id.is_none_or(|max| max < counter - 1) {
+            return false;
+        }
+        let high_enough = |hash: &HashType| self.max_counter.get(hash).is_some_and(|max| *max >= counter - 1);
+        let mut queue: VecDeque<HashType> = node.predecessors.iter().cloned().collect();
+        let mut visited = HashSet::new();
+        while let Some(hash) = queue.pop_front() {
+            if !visited.insert(hash.clone()) {
+                continue;
+            }
+            if high_enough(&hash) {
+                return true;
+            }
+            match hash_graph.get_node(&hash) {
+                Some(pred) => queue.extend(pred.predecessors.iter().cloned()),
+                None => return hash_graph.causal_past(&node.predecessors).iter().any(high_enough),
+            }
+        }
+        false
+    }
+
+    // Not part of the Isabelle specification, and only checked if tombstones are collected: a
+    // node must not refer to an element whose deletion it has already observed (correct peers
+    // only address visible elements), nor to an element that has been collected. Together these
+    // make it safe to collect a tombstone once its deletion is causally stable.
+    fn is_live_reference(&self, eid: &RGAID<I>, node: &Node<BFTRGAOp<I, V>>, hash_graph: &dyn GraphView<BFTRGAOp<I, V>>) -> bool {
+        // the insert position of a moved element may have been collected
+        if self.elements.get_by_id(eid.clone()).is_none() && !self.positions.contains_key(eid) {
+            return false;
+        }
+        let element = self.moved.get(eid).unwrap_or(eid);
+        match self.deleted_by.get(element) {
+            Some(deletes) => !deletes.iter().any(|d| hash_graph.is_ancestor(d, node)),
+            None => true,
+        }
+    }
+
+    // Not part of the Isabelle specification, and only checked if tombstones are collected: the
+    // position of a moved element is superseded once a position of the element with a greater
+    // ID is in the causal past of the node. Correct peers only insert after visible positions,
+    // so a superseded position can be collected once this is causally stable.
+    fn is_superseded(&self, position: &RGAID<I>, node: &Node<BFTRGAOp<I, V>>, hash_graph: &dyn GraphView<BFTRGAOp<I, V>>) -> bool {
+        let element = self.moved.get(position).unwrap_or(position);
+        self.positions.get(element).into_iter().flatten()
+            .any(|p| p > position && hash_graph.is_ancestor(&p.1, node))
+    }
+
+    // the position the element is shown at, unless it is deleted
+    fn position_of(&self, eid: &RGAID<I>) -> RGAID<I> {
+        let moves = self.positions.get(eid).into_iter().flatten();
+        moves.fold(eid, |max, p| if p > max { p } else { max }).clone()
+    }
+
+    /// Removes the tombstones whose deletion is in the causal past of `stable_heads`, and the
+    /// positions that moves of an element left behind once a later position of the element is
+    /// in it. The caller must know that every replica has `stable_heads` in its causal past;
+    /// from then on, nodes referring to a collected element or position are not semantically
+    /// valid; neither are nodes that refer to an element whose deletion, or to a position
+    /// whose replacement, is in their causal past. Returns the number of collected tombstones,
+    /// which is 0 unless the list was created with `with_tombstone_collection`.
+    pub fn collect_tombstones(&mut self, stable_heads: &[HashType], hash_graph: &HashGraph<BFTRGAOp<I, V>>) -> usize {
+        if !self.collects_tombstones {
+            return 0;
+        }
+        let stable = hash_graph.causal_past(stable_heads);
+        let (deleted_by, positions, moved) = (&self.deleted_by, &self.positions, &self.moved);
+        let collected = self.elements.purge_tombstones(|eid| {
+            let element = moved.get(eid).unwrap_or(eid);
+            let deleted = deleted_by.get(element).is_some_and(|deletes| deletes.iter().any(|d| stable.contains(d)));
+            let superseded = positions.get(element).into_iter().flatten().any(|p| p > eid && stable.contains(&p.1));
+            deleted || superseded
+        });
+        for eid in collected.iter() {
+            let element = self.moved.remove(eid).unwrap_or_else(|| eid.clone());
+            if let Some(positions) = self.positions.get_mut(&element) {
+                positions.retain(|p| p != eid);
+            }
+            // the deletion is still needed while a position of the element is left
+            if self.elements.get_by_id(element.clone()).is_none() && self.positions.get(&element).is_none_or(|p| p.is_empty()) {
+                self.deleted_by.remove(&element);
+                self.positions.remove(&element);
+            }
+        }
+        collected.len()
+    }
+
     pub fn get(&self, idx: usize) -> Option<V> {
         self.elements.get_by_idx(idx).map(|(_, v, _)| v)
     }
@@ -186,6 +513,106 @@ where
         self.elements.get_list()
     }
 
+    /// Returns the IDs and values of the visible elements in list order. A moved element is
+    /// listed with the ID it was inserted with.
+    pub fn get_elements(&self) -> Vec<(RGAID<I>, V)> {
+        self.elements.iter()
+            .filter(|(_, _, deleted)| !deleted)
+            .map(|(position, value, _)| (self.moved.get(&position).cloned().unwrap_or(position), value))
+            .collect()
+    }
+
+    /// Returns the index of the element with the given ID, or `None` if it is not visible.
+    pub fn index_of(&self, eid: &RGAID<I>) -> Option<usize> {
+        self.elements.index_of(&self.position_of(eid))
+    }
+
+    /// Returns the greatest peer-chosen ID of all interpreted inserts and moves.
+    pub fn max_id(&self) -> Option<&I> {
+        self.max_id.as_ref()
+    }
+
+    /// Creates an anchor for the gap before the element at `idx` (`idx` may equal the length
+    /// of the list to anchor the end).
+    pub fn anchor_at(&self, idx: usize, bias: Bias) -> Option<Anchor<I>> {
+        if idx > self.elements.len() {
+            return None;
+        }
+        let neighbour = match bias {
+            Bias::Left => idx.checked_sub(1),
+            Bias::Right => Some(idx),
+        };
+        let id = neighbour.and_then(|i| self.elements.get_by_idx(i)).map(|(id, _, _)| id);
+        Some(Anchor { id, bias })
+    }
+
+    /// Returns the current index of the gap the anchor refers to. If the anchored element has
+    /// been deleted, the gap is where the element used to be. Returns `None` if the element is
+    /// unknown, e.g. because its tombstone has been collected.
+    pub fn resolve_anchor(&self, anchor: &Anchor<I>) -> Option<usize> {
+        match (&anchor.id, anchor.bias) {
+            (None, Bias::Left) => Some(0),
+            (None, Bias::Right) => Some(self.elements.len()),
+            (Some(id), Bias::Left) => {
+                let (_, _, deleted) = self.elements.get_by_id(id.clone())?;
+                Some(self.elements.rank_of(id)? + !deleted as usize)
+            }
+            (Some(id), Bias::Right) => self.elements.rank_of(id),
+        }
+    }
+
+    /// Splits the list into runs of elements that were inserted by the same author, as told
+    /// by `author_of` for the node that inserted each element, e.g. from its signature or the
+    /// peer it was received from. A moved element is attributed to whoever inserted it, since
+    /// the hash of that node is the second half of its ID. Returns `None` if an inserting node
+    /// is not in `hash_graph`.
+    pub fn blame<A: PartialEq>(
+        &self,
+        hash_graph: &dyn GraphView<BFTRGAOp<I, V>>,
+        author_of: impl Fn(&Node<BFTRGAOp<I, V>>) -> A,
+    ) -> Option<Vec<Blame<A>>> {
+        let mut blame: Vec<Blame<A>> = vec![];
+        for (idx, (id, _, _)) in self.elements.iter().filter(|(_, _, deleted)| !deleted).enumerate() {
+            let hash = self.moved.get(&id).unwrap_or(&id).1.clone();
+            let author = author_of(&*hash_graph.get_node(&hash)?);
+            match blame.last_mut() {
+                Some(last) if last.author == author => {
+                    last.range.end = idx + 1;
+                    last.inserted_by.push(hash);
+                }
+                _ => blame.push(Blame { range: idx..idx + 1, author, inserted_by: vec![hash] }),
+            }
+        }
+        Some(blame)
+    }
+
+    /// Returns who inserted and who deleted each deleted element whose tombstone has not been
+    /// collected, in list order; see `blame`. Returns `None` if one of these nodes is not in
+    /// `hash_graph`.
+    pub fn blame_deletions<A>(
+        &self,
+        hash_graph: &dyn GraphView<BFTRGAOp<I, V>>,
+        author_of: impl Fn(&Node<BFTRGAOp<I, V>>) -> A,
+    ) -> Option<Vec<DeletionBlame<V, A>>> {
+        let attribute = |hash: &HashType| Some((hash.clone(), author_of(&*hash_graph.get_node(hash)?)));
+        let mut blame = vec![];
+        for (id, value, deleted) in self.elements.iter() {
+            let element = self.moved.get(&id).unwrap_or(&id);
+            // the other positions of a moved element are tombstones of the move
+            let Some(deletes) = self.deleted_by.get(element) else { continue };
+            if !deleted || self.position_of(element) != id {
+                continue;
+            }
+            blame.push(DeletionBlame {
+                index: self.elements.rank_of(&id)?,
+                value,
+                inserted_by: attribute(&element.1)?,
+                deleted_by: deletes.iter().map(attribute).collect::<Option<_>>()?,
+            });
+        }
+        Some(blame)
+    }
+
     pub fn insert(&mut self, idx: usize, value: V, iid: I) -> Option<BFTRGAOp<I, V>> {
         if idx == 0 {
             Some(BFTRGAOp::Insert(value, iid, None))
@@ -202,20 +629,435 @@ where
     pub fn delete(&mut self, idx: usize) -> Option<BFTRGAOp<I, V>> {
         let elem = self.elements.get_by_idx(idx);
         if let Some((id, _, _)) = elem {
-            Some(BFTRGAOp::Delete(id.clone()))
+            let element = self.moved.get(&id).cloned().unwrap_or(id);
+            Some(BFTRGAOp::Delete(element))
         } else {
             None
         }
     }
+
+    /// Moves the element at index `from` so that it ends up at index `to`. Returns `None` unless
+    /// `iid` is greater than the ID of the element's current position, which is the greatest
+    /// of its positions.
+    pub fn move_elem(&mut self, from: usize, to: usize, iid: I) -> Option<BFTRGAOp<I, V>> {
+        let (id, _, _) = self.elements.get_by_idx(from)?;
+        if to >= self.elements.len() || iid <= id.0 {
+            return None;
+        }
+        // the element the new position follows, in the list without the moved element
+        let after = match to {
+            0 => None,
+            _ => Some(self.elements.get_by_idx(if to <= from { to - 1 } else { to })?.0),
+        };
+        let element = self.moved.get(&id).cloned().unwrap_or(id);
+        Some(BFTRGAOp::Move(element, iid, after))
+    }
     
     // used only for benchmarking
     pub fn raw_delete(&mut self, idx: usize) -> Option<BFTRGAOp<I, V>> {
-        let iter = self.elements.elements.iter().enumerate();
-        for (i, (id, _, _)) in iter {
-            if i == idx {
-                return Some(BFTRGAOp::Delete(id.clone()));
-            }
+        self.elements.get_by_raw_idx(idx).map(|(id, _, _)| BFTRGAOp::Delete(id))
+    }
+}
+
+impl<I, V> Default for BFTRGA<I, V>
+where
+    I: Eq + Hash + Clone + Serialize + PartialOrd + SpanId + LamportCounter,
+    V: Eq + Hash + Clone + Serialize,
+{
+    fn default() -> Self {
+        BFTRGA {
+            elements: OrderedList::new(),
+            deleted_by: HashMap::new(),
+            positions: HashMap::new(),
+            moved: HashMap::new(),
+            max_id: None,
+            max_counter: HashMap::new(),
+            replica: None,
+            collects_tombstones: false,
         }
-        None
     }
-}
\ No newline at end of file
+}
+
+impl<V> BFTRGA<LamportId, V>
+where
+    V: Eq + Hash + Clone + Serialize,
+{
+    /// Like `insert`, but allocates the element ID: its counter is one more than the greatest
+    /// counter the replica has seen, which covers the causal past of the node the local
+    /// operation ends up in. The operation must be interpreted (e.g. by
+    /// `Replica::handle_local_op`) before the next ID is allocated. Returns `None` if the list
+    /// was created without a replica number.
+    pub fn insert_auto(&mut self, idx: usize, value: V) -> Option<BFTRGAOp<LamportId, V>> {
+        let id = self.next_id()?;
+        self.insert(idx, value, id)
+    }
+
+    /// Like `move_elem`, but allocates the ID of the new position as `insert_auto` does, so
+    /// that the move wins over every move the replica has seen.
+    pub fn move_auto(&mut self, from: usize, to: usize) -> Option<BFTRGAOp<LamportId, V>> {
+        let id = self.next_id()?;
+        self.move_elem(from, to, id)
+    }
+
+    fn next_id(&self) -> Option<LamportId> {
+        let counter = self.max_id.map_or(0, |max| max.counter.saturating_add(1));
+        Some(LamportId { counter, replica: self.replica? })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use crate::bft_crdts::hash_graph::{HashGraph, HashType};
+    use crate::bft_crdts::replica::Replica;
+    use super::*;
+
+    #[test]
+    fn test_bft_rga_collect_tombstones() {
+        let rga = BFTRGA::with_tombstone_collection(1);
+        let mut handler = Replica::new(rga);
+        let insert_op = handler.crdt.insert(0, "a", "0").unwrap();
+        let insert_a = handler.handle_local_op(insert_op);
+        let insert_op = handler.crdt.insert(1, "b", "1").unwrap();
+        let insert_b = handler.handle_local_op(insert_op);
+        let delete_op = handler.crdt.delete(1).unwrap();
+        let delete_b = handler.handle_local_op(delete_op.clone());
+
+        // a node that has observed the deletion may not refer to the deleted element
+        let stale_delete = Node::new(vec![delete_b.get_hash()], delete_op.clone());
+        assert!(!handler.crdt.is_sem_valid(&stale_delete, &handler.hash_graph));
+        // a node concurrent with the deletion still may
+        let insert_after_b = Node::new(vec![insert_b.get_hash()], BFTRGAOp::Insert("c", "2", Some(("1", insert_b.get_hash()))));
+        assert!(handler.crdt.is_sem_valid(&insert_after_b, &handler.hash_graph));
+
+        let heads = handler.hash_graph.get_heads().to_vec();
+        assert_eq!(handler.crdt.collect_tombstones(&heads, &handler.hash_graph), 1);
+        assert_eq!(handler.crdt.get_list(), vec!["a"]);
+        // once collected, the element cannot be referenced at all
+        assert!(!handler.crdt.is_sem_valid(&insert_after_b, &handler.hash_graph));
+
+        let insert_op = handler.crdt.insert(1, "c", "2").unwrap();
+        handler.handle_local_op(insert_op);
+        assert_eq!(handler.crdt.get_list(), vec!["a", "c"]);
+
+        // without tombstone collection, validity is that of the Isabelle specification
+        let mut spec = Replica::new(BFTRGA::default());
+        for node in [insert_a, insert_b.clone(), delete_b.clone()] {
+            spec.handle_remote_node(node);
+        }
+        let after_deleted = Node::new(vec![delete_b.get_hash()], BFTRGAOp::Insert("c", "2", Some(("1", insert_b.get_hash()))));
+        assert!(spec.crdt.is_sem_valid(&stale_delete, &spec.hash_graph));
+        assert!(spec.crdt.is_sem_valid(&after_deleted, &spec.hash_graph));
+        let heads = spec.hash_graph.get_heads().to_vec();
+        assert_eq!(spec.crdt.collect_tombstones(&heads, &spec.hash_graph), 0);
+    }
+
+    #[test]
+    fn test_bft_rga_anchors() {
+        let mut handler = Replica::new(BFTRGA::default());
+        for (i, v) in ["a", "b", "c"].iter().enumerate() {
+            let insert_op = handler.crdt.insert(i, *v, "0").unwrap();
+            handler.handle_local_op(insert_op);
+        }
+        // a selection of "b": [1, 2)
+        let start = handler.crdt.anchor_at(1, Bias::Right).unwrap();
+        let end = handler.crdt.anchor_at(2, Bias::Left).unwrap();
+        let cursor_end = handler.crdt.anchor_at(3, Bias::Right).unwrap();
+        assert_eq!(cursor_end.id, None);
+        assert!(handler.crdt.anchor_at(4, Bias::Left).is_none());
+
+        let insert_op = handler.crdt.insert(0, "x", "1").unwrap();
+        handler.handle_local_op(insert_op);
+        assert_eq!(handler.crdt.get_list(), vec!["x", "a", "b", "c"]);
+        assert_eq!(handler.crdt.resolve_anchor(&start), Some(2));
+        assert_eq!(handler.crdt.resolve_anchor(&end), Some(3));
+        assert_eq!(handler.crdt.resolve_anchor(&cursor_end), Some(4));
+
+        // text typed at either end of the selection stays outside of it
+        let insert_op = handler.crdt.insert(2, "y", "2").unwrap();
+        handler.handle_local_op(insert_op);
+        let insert_op = handler.crdt.insert(4, "z", "2").unwrap();
+        handler.handle_local_op(insert_op);
+        assert_eq!(handler.crdt.get_list(), vec!["x", "a", "y", "b", "z", "c"]);
+        assert_eq!(handler.crdt.resolve_anchor(&start), Some(3));
+        assert_eq!(handler.crdt.resolve_anchor(&end), Some(4));
+
+        // deleting the selected element collapses the selection where it used to be
+        let delete_op = handler.crdt.delete(3).unwrap();
+        handler.handle_local_op(delete_op);
+        assert_eq!(handler.crdt.resolve_anchor(&start), Some(3));
+        assert_eq!(handler.crdt.resolve_anchor(&end), Some(3));
+    }
+
+    #[test]
+    fn test_bft_rga_remote_events() {
+        let mut local = Replica::new(BFTRGA::default());
+        let mut nodes = vec![];
+        for (i, v) in ["a", "b", "c"].iter().enumerate() {
+            let insert_op = local.crdt.insert(i, *v, "0").unwrap();
+            nodes.push(local.handle_local_op(insert_op));
+        }
+        let delete_op = local.crdt.delete(1).unwrap();
+        nodes.push(local.handle_local_op(delete_op));
+
+        let mut remote = Replica::new(BFTRGA::default());
+        let events = remote.record_events();
+
+        // everything stays pending until the first insert arrives
+        for node in nodes.into_iter().rev() {
+            remote.handle_remote_node(node);
+        }
+        assert_eq!(remote.crdt.get_list(), vec!["a", "c"]);
+        assert_eq!(*events.borrow(), vec![
+            BFTRGAEvent::Insert(0, "a"),
+            BFTRGAEvent::Insert(1, "b"),
+            BFTRGAEvent::Insert(2, "c"),
+            BFTRGAEvent::Delete(1, "b"),
+        ]);
+    }
+
+    #[test]
+    fn test_bft_rga_insert_auto() {
+        let mut a = Replica::new(BFTRGA::new(1));
+        let mut b = Replica::new(BFTRGA::new(2));
+        let mut a_nodes = vec![];
+        for (i, v) in ["a", "b", "c"].iter().enumerate() {
+            let insert_op = a.crdt.insert_auto(i, *v).unwrap();
+            a_nodes.push(a.handle_local_op(insert_op));
+        }
+        // inserting in the middle lands at the intended position even though "b" and "c"
+        // were inserted after "a" earlier
+        let insert_op = a.crdt.insert_auto(1, "x").unwrap();
+        a_nodes.push(a.handle_local_op(insert_op));
+        assert_eq!(a.crdt.get_list(), vec!["a", "x", "b", "c"]);
+
+        for node in a_nodes {
+            b.handle_remote_node(node);
+        }
+        let insert_op = b.crdt.insert_auto(0, "y").unwrap();
+        assert!(matches!(insert_op, BFTRGAOp::Insert(_, LamportId { counter: 4, replica: 2 }, None)));
+        let b_node = b.handle_local_op(insert_op);
+        let insert_op = a.crdt.insert_auto(0, "z").unwrap();
+        let a_node = a.handle_local_op(insert_op);
+
+        // concurrent inserts at the same position with the same counter are ordered by replica
+        a.handle_remote_node(b_node);
+        b.handle_remote_node(a_node);
+        assert_eq!(a.crdt.get_list(), vec!["y", "z", "a", "x", "b", "c"]);
+        assert_eq!(a.crdt.get_list(), b.crdt.get_list());
+
+        // a counter may exceed the greatest one in the causal past by one only
+        let heads = a.hash_graph.get_heads().to_vec();
+        let next = Node::new(heads.clone(), BFTRGAOp::Insert("n", LamportId { counter: 5, replica: 3 }, None));
+        assert!(a.crdt.is_sem_valid(&next, &a.hash_graph));
+        let inflated = Node::new(heads, BFTRGAOp::Insert("n", LamportId { counter: u64::MAX, replica: 3 }, None));
+        assert!(!a.crdt.is_sem_valid(&inflated, &a.hash_graph));
+        let first = Node::new(vec![], BFTRGAOp::Insert("n", LamportId { counter: 1, replica: 3 }, None));
+        assert!(!a.crdt.is_sem_valid(&first, &a.hash_graph));
+        // integer IDs are the caller's choice
+        let integers: BFTRGA<u64, &str> = BFTRGA::default();
+        let far = Node::new(vec![], BFTRGAOp::Insert("n", 1000, None));
+        assert!(integers.is_sem_valid(&far, &HashGraph::new()));
+
+        // a list without a replica number cannot allocate IDs
+        assert!(BFTRGA::<LamportId, &str>::default().insert_auto(0, "a").is_none());
+    }
+
+    #[test]
+    fn test_bft_rga_typing_forms_runs() {
+        let mut a = Replica::new(BFTRGA::new(1));
+        let mut b = Replica::new(BFTRGA::new(2));
+        for i in 0..100 {
+            let op = a.crdt.insert_auto(i, 'a').unwrap();
+            let node = a.handle_local_op(op);
+            b.handle_remote_node(node);
+        }
+        for i in 0..100 {
+            let op = b.crdt.insert_auto(50 + i, 'b').unwrap();
+            let node = b.handle_local_op(op);
+            a.handle_remote_node(node);
+        }
+        // every element has the hash of its own node in its ID, yet each run of typing is
+        // stored as one span
+        assert_eq!(a.crdt.get_list().len(), 200);
+        assert_eq!(a.crdt.elements.elements.iter().count(), 3);
+        assert_eq!(b.crdt.elements.elements.iter().count(), 3);
+    }
+
+    #[test]
+    fn test_bft_rga_concurrent_moves() {
+        let mut a: Replica<_, BFTRGA<LamportId, char>> = Replica::new(BFTRGA::with_tombstone_collection(1));
+        let mut b: Replica<_, BFTRGA<LamportId, char>> = Replica::new(BFTRGA::with_tombstone_collection(2));
+        for (i, c) in "abcd".chars().enumerate() {
+            let op = a.crdt.insert_auto(i, c).unwrap();
+            let node = a.handle_local_op(op);
+            b.handle_remote_node(node);
+        }
+
+        // both replicas move 'a' concurrently; it must end up in one place only
+        let op = a.crdt.move_auto(0, 3).unwrap();
+        let a_move = a.handle_local_op(op);
+        assert_eq!(a.crdt.get_list(), vec!['b', 'c', 'd', 'a']);
+        let op = b.crdt.move_auto(0, 1).unwrap();
+        let b_move = b.handle_local_op(op);
+        assert_eq!(b.crdt.get_list(), vec!['b', 'a', 'c', 'd']);
+        let events = a.record_events();
+        a.handle_remote_node(b_move.clone());
+        b.handle_remote_node(a_move.clone());
+        assert_eq!(a.crdt.get_list(), b.crdt.get_list());
+        assert_eq!(a.crdt.get_list().len(), 4);
+        // the IDs have equal counters, so replica 2's move wins
+        assert_eq!(a.crdt.get_list(), vec!['b', 'a', 'c', 'd']);
+        assert_eq!(*events.borrow(), vec![BFTRGAEvent::Delete(3, 'a'), BFTRGAEvent::Insert(1, 'a')]);
+
+        // a later move wins over both, and inserting next to a moved element works
+        let op = a.crdt.move_auto(1, 2).unwrap();
+        let node = a.handle_local_op(op);
+        b.handle_remote_node(node);
+        let op = b.crdt.insert_auto(3, 'e').unwrap();
+        let node = b.handle_local_op(op);
+        a.handle_remote_node(node);
+        assert_eq!(a.crdt.get_list(), vec!['b', 'c', 'a', 'e', 'd']);
+        assert_eq!(a.crdt.get_list(), b.crdt.get_list());
+
+        // the positions the moves left behind are collected once a later position is stable,
+        // even though the element is not deleted; the first one is followed by 'b', which has
+        // a greater ID, so it has to stay
+        let heads = a.hash_graph.get_heads().to_vec();
+        assert_eq!(a.crdt.collect_tombstones(&heads, &a.hash_graph), 2);
+        assert_eq!(a.crdt.get_list(), vec!['b', 'c', 'a', 'e', 'd']);
+        let op = a.crdt.move_auto(2, 4).unwrap();
+        let node = a.handle_local_op(op);
+        assert_eq!(a.crdt.get_list(), vec!['b', 'c', 'e', 'd', 'a']);
+        b.handle_remote_node(node);
+        let op = b.crdt.move_auto(4, 2).unwrap();
+        let node = b.handle_local_op(op);
+        a.handle_remote_node(node);
+        assert_eq!(a.crdt.get_list(), vec!['b', 'c', 'a', 'e', 'd']);
+
+        // a move concurrent with a delete does not bring the element back
+        let op = a.crdt.delete(2).unwrap();
+        let delete = a.handle_local_op(op);
+        let op = b.crdt.move_auto(2, 0).unwrap();
+        let b_move = b.handle_local_op(op);
+        a.handle_remote_node(b_move);
+        b.handle_remote_node(delete);
+        assert_eq!(a.crdt.get_list(), vec!['b', 'c', 'e', 'd']);
+        assert_eq!(a.crdt.get_list(), b.crdt.get_list());
+    }
+
+    #[test]
+    fn test_bft_rga_move_sem_validity() {
+        let mut handler: Replica<_, BFTRGA<&str, &str>> = Replica::new(BFTRGA::with_tombstone_collection(1));
+        let op = handler.crdt.insert(0, "a", "0").unwrap();
+        let insert_a = handler.handle_local_op(op);
+        let op = handler.crdt.insert(1, "b", "1").unwrap();
+        handler.handle_local_op(op);
+        let op = handler.crdt.move_elem(0, 1, "2").unwrap();
+        let move_a = handler.handle_local_op(op);
+        assert_eq!(handler.crdt.get_list(), vec!["b", "a"]);
+        assert!(matches!(handler.crdt.delete(1), Some(BFTRGAOp::Delete((_, h))) if h == insert_a.get_hash()));
+
+        // a Move must refer to the Insert of its element in its causal past
+        let heads = handler.hash_graph.get_heads().to_vec();
+        let a_id = ("0", insert_a.get_hash());
+        let valid = Node::new(heads.clone(), BFTRGAOp::Move(a_id.clone(), "3", None));
+        assert!(handler.crdt.is_sem_valid(&valid, &handler.hash_graph));
+        let concurrent = Node::new(vec![], BFTRGAOp::Move(a_id.clone(), "3", None));
+        assert!(!handler.crdt.is_sem_valid(&concurrent, &handler.hash_graph));
+        let via_move = Node::new(heads.clone(), BFTRGAOp::Move(("2", move_a.get_hash()), "3", None));
+        assert!(!handler.crdt.is_sem_valid(&via_move, &handler.hash_graph));
+        let wrong_id = Node::new(heads.clone(), BFTRGAOp::Move(("1", insert_a.get_hash()), "3", None));
+        assert!(!handler.crdt.is_sem_valid(&wrong_id, &handler.hash_graph));
+        let after_move = Node::new(heads.clone(), BFTRGAOp::Insert("c", "3", Some(("2", move_a.get_hash()))));
+        assert!(handler.crdt.is_sem_valid(&after_move, &handler.hash_graph));
+        let after_insert = Node::new(heads.clone(), BFTRGAOp::Insert("c", "3", Some(a_id.clone())));
+        assert!(!handler.crdt.is_sem_valid(&after_insert, &handler.hash_graph));
+
+        // a move must have a greater ID than the positions of its element in its causal past,
+        // so that a causally later move always wins
+        let not_later = Node::new(heads.clone(), BFTRGAOp::Move(a_id.clone(), "1", None));
+        assert!(!handler.crdt.is_sem_valid(&not_later, &handler.hash_graph));
+        assert!(handler.crdt.move_elem(1, 0, "1").is_none());
+        let concurrent_lower = Node::new(vec![insert_a.get_hash()], BFTRGAOp::Move(a_id.clone(), "1", None));
+        assert!(handler.crdt.is_sem_valid(&concurrent_lower, &handler.hash_graph));
+        handler.handle_remote_node(concurrent_lower);
+        assert_eq!(handler.crdt.get_list(), vec!["b", "a"]);
+
+        // nothing may refer to a deleted element through one of its positions
+        let op = handler.crdt.delete(1).unwrap();
+        handler.handle_local_op(op);
+        let heads = handler.hash_graph.get_heads().to_vec();
+        let after_deleted = Node::new(heads.clone(), BFTRGAOp::Insert("c", "3", Some(("2", move_a.get_hash()))));
+        assert!(!handler.crdt.is_sem_valid(&after_deleted, &handler.hash_graph));
+        let move_deleted = Node::new(heads, BFTRGAOp::Move(a_id, "3", None));
+        assert!(!handler.crdt.is_sem_valid(&move_deleted, &handler.hash_graph));
+    }
+
+    #[test]
+    fn test_bft_rga_ops_serialize_apart() {
+        // an Insert whose value spells out a Move must not hash like that Move
+        let (hash, after) = ("h".repeat(64), Some((1u64, "g".repeat(64))));
+        let id = String::from_utf8(65u64.to_bytes()).unwrap();
+        let insert: BFTRGAOp<u64, String> = BFTRGAOp::Insert(format!("move{}{}", id, hash), 2, after.clone());
+        let moved: BFTRGAOp<u64, String> = BFTRGAOp::Move((65, hash.clone()), 2, after);
+        assert_ne!(insert.to_bytes(), moved.to_bytes());
+        assert_ne!(Node::new(vec![], insert).get_hash(), Node::new(vec![], moved).get_hash());
+    }
+
+    #[test]
+    fn test_bft_rga_blame() {
+        let mut alice = Replica::new(BFTRGA::new(1));
+        let mut bob = Replica::new(BFTRGA::new(2));
+        // the author of every node, e.g. as known from its signature
+        let mut authors: HashMap<HashType, &str> = HashMap::new();
+        let mut inserted = vec![];
+        for (i, c) in "abc".chars().enumerate() {
+            let op = alice.crdt.insert_auto(i, c).unwrap();
+            let node = alice.handle_local_op(op);
+            authors.insert(node.get_hash(), "alice");
+            inserted.push(node.get_hash());
+            bob.handle_remote_node(node);
+        }
+        for (i, c) in "xy".chars().enumerate() {
+            let op = bob.crdt.insert_auto(3 + i, c).unwrap();
+            let node = bob.handle_local_op(op);
+            authors.insert(node.get_hash(), "bob");
+            inserted.push(node.get_hash());
+            alice.handle_remote_node(node);
+        }
+        let op = bob.crdt.delete(1).unwrap();
+        let delete = bob.handle_local_op(op);
+        authors.insert(delete.get_hash(), "bob");
+        alice.handle_remote_node(delete.clone());
+        let author_of = |node: &Node<BFTRGAOp<LamportId, char>>| authors[&node.get_hash()];
+
+        assert_eq!(alice.crdt.get_list(), vec!['a', 'c', 'x', 'y']);
+        assert_eq!(alice.crdt.blame(&alice.hash_graph, author_of).unwrap(), vec![
+            Blame { range: 0..2, author: "alice", inserted_by: vec![inserted[0].clone(), inserted[2].clone()] },
+            Blame { range: 2..4, author: "bob", inserted_by: inserted[3..5].to_vec() },
+        ]);
+        let expected = vec![DeletionBlame {
+            index: 1,
+            value: 'b',
+            inserted_by: (inserted[1].clone(), "alice"),
+            deleted_by: vec![(delete.get_hash(), "bob")],
+        }];
+        assert_eq!(alice.crdt.blame_deletions(&alice.hash_graph, author_of).unwrap(), expected);
+
+        // a moved element is still attributed to its inserter, and the move is not a deletion
+        let op = alice.crdt.move_auto(3, 0).unwrap();
+        let node = alice.handle_local_op(op);
+        authors.insert(node.get_hash(), "alice");
+        let author_of = |node: &Node<BFTRGAOp<LamportId, char>>| authors[&node.get_hash()];
+        let blame = alice.crdt.blame(&alice.hash_graph, author_of).unwrap();
+        let ranges: Vec<_> = blame.iter().map(|b| (b.range.clone(), b.author)).collect();
+        assert_eq!(ranges, vec![(0..1, "bob"), (1..3, "alice"), (3..4, "bob")]);
+        let deletions = alice.crdt.blame_deletions(&alice.hash_graph, author_of).unwrap();
+        assert_eq!(deletions.len(), 1);
+        assert_eq!((deletions[0].index, deletions[0].value), (2, 'b'));
+
+        // the inserting nodes must be known
+        assert!(alice.crdt.blame(&HashGraph::new(), author_of).is_none());
+    }
+}
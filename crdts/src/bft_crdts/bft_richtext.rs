@@ -0,0 +1,406 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::{Debug, Display};
+use std::mem::{discriminant, Discriminant};
+use crate::bft_crdts::bft_crdt::BFTCRDT;
+use crate::bft_crdts::bft_rga::{Anchor, Bias, BFTRGA, BFTRGAEvent, BFTRGAOp, LamportId};
+use crate::bft_crdts::hash_graph::{GraphView, HashType, Node, ProjectedView};
+use crate::serialize::Serialize;
+
+//  Rich text in the style of Peritext: the characters are a BFT RGA, and formatting is a set of
+//   operations that add or remove a mark between two anchors. Anchors refer to characters by
+//   their element IDs, so a mark keeps covering the same characters under concurrent edits, and
+//   the bias of each anchor decides whether text inserted at the boundary of the range becomes
+//   part of it (see `Mark::expands`). Marks are applied in the order of a peer-chosen counter
+//   and then the hash of the node, so the latest mark operation covering a character decides
+//   whether it carries the mark, and concurrent operations are resolved the same on all peers.
+//   A mark operation may only refer to characters whose Insert (or Move) is in its causal past,
+//   and its counter may exceed the greatest counter of the mark operations in its causal past
+//   by one at most, so that a faulty peer cannot put its marks out of reach of later ones.
+
+type TextOp = BFTRGAOp<LamportId, char>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Mark {
+    Bold,
+    Italic,
+    // url
+    Link(String),
+}
+
+impl Mark {
+    /// Whether text inserted right before or right after a range with this mark gets the mark
+    /// as well: bold and italic grow at their end, as in most editors; links do not grow.
+    pub fn expands(&self) -> (bool, bool) {
+        match self {
+            Mark::Bold | Mark::Italic => (false, true),
+            Mark::Link(_) => (false, false),
+        }
+    }
+
+    // marks of one kind exclude each other on one character, e.g. two links
+    fn kind(&self) -> Discriminant<Mark> {
+        discriminant(self)
+    }
+}
+
+impl Serialize for Mark {
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Mark::Bold => vec![0u8],
+            Mark::Italic => vec![1u8],
+            Mark::Link(url) => [vec![2u8], url.to_bytes()].concat(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum BFTRichTextOp {
+    Text(TextOp),
+    // mark, counter, start, end
+    AddMark(Mark, u64, Anchor<LamportId>, Anchor<LamportId>),
+    // mark, counter, start, end
+    RemoveMark(Mark, u64, Anchor<LamportId>, Anchor<LamportId>),
+}
+
+impl Display for BFTRichTextOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BFTRichTextOp::Text(op) => {
+                write!(f, "Text({})", op)
+            }
+            BFTRichTextOp::AddMark(mark, c, start, end) => {
+                write!(f, "AddMark({:?}, {}, {:?}, {:?})", mark, c, start, end)
+            }
+            BFTRichTextOp::RemoveMark(mark, c, start, end) => {
+                write!(f, "RemoveMark({:?}, {}, {:?}, {:?})", mark, c, start, end)
+            }
+        }
+    }
+}
+
+fn anchor_to_bytes(anchor: &Anchor<LamportId>) -> Vec<u8> {
+    let mut bytes = vec![anchor.bias as u8];
+    if let Some((id, hash)) = &anchor.id {
+        bytes.extend_from_slice(&id.to_bytes());
+        bytes.extend_from_slice(hash.as_bytes());
+    }
+    bytes
+}
+
+impl Serialize for BFTRichTextOp {
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            BFTRichTextOp::Text(op) => {
+                [vec![0u8], op.to_bytes()].concat()
+            }
+            BFTRichTextOp::AddMark(mark, c, start, end) => {
+                [vec![1u8], mark.to_bytes(), c.to_bytes(), anchor_to_bytes(start), vec![0xff], anchor_to_bytes(end)].concat()
+            }
+            BFTRichTextOp::RemoveMark(mark, c, start, end) => {
+                [vec![2u8], mark.to_bytes(), c.to_bytes(), anchor_to_bytes(start), vec![0xff], anchor_to_bytes(end)].concat()
+            }
+        }
+    }
+}
+
+/// A change to a `BFTRichText`: an edit of the characters, or a mark operation that covers
+/// the characters from `start` (inclusive) to `end` (exclusive) as of right after it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BFTRichTextEvent {
+    Text(BFTRGAEvent<char>),
+    Formatted(usize, usize),
+}
+
+/// A maximal run of characters with the same marks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormattedSpan {
+    pub text: String,
+    pub marks: Vec<Mark>,
+}
+
+struct MarkOp {
+    key: (u64, HashType),
+    mark: Mark,
+    add: bool,
+    start: Anchor<LamportId>,
+    end: Anchor<LamportId>,
+}
+
+pub struct BFTRichText {
+    text: BFTRGA<LamportId, char>,
+    // mark operations, in the order they are applied
+    marks: Vec<MarkOp>,
+    // the greatest counter of all mark operations
+    max_counter: Option<u64>,
+    // the nodes of the mark operations by their counters
+    by_counter: BTreeMap<u64, Vec<HashType>>,
+}
+
+impl BFTRichText {
+    /// Creates empty text for the replica with the given number, see `BFTRGA::new`.
+    pub fn new(replica: u64) -> Self {
+        BFTRichText {
+            text: BFTRGA::new(replica),
+            marks: vec![],
+            max_counter: None,
+            by_counter: BTreeMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, idx: usize, c: char) -> Option<BFTRichTextOp> {
+        self.text.insert_auto(idx, c).map(BFTRichTextOp::Text)
+    }
+
+    pub fn delete(&mut self, idx: usize) -> Option<BFTRichTextOp> {
+        self.text.delete(idx).map(BFTRichTextOp::Text)
+    }
+
+    // the anchors of the range from `start` to `end`, biased as the mark expands
+    fn anchors(&self, start: usize, end: usize, mark: &Mark) -> Option<(Anchor<LamportId>, Anchor<LamportId>)> {
+        if start >= end {
+            return None;
+        }
+        let (before, after) = mark.expands();
+        let start = self.text.anchor_at(start, if before { Bias::Left } else { Bias::Right })?;
+        let end = self.text.anchor_at(end, if after { Bias::Right } else { Bias::Left })?;
+        Some((start, end))
+    }
+
+    fn next_counter(&self) -> u64 {
+        self.max_counter.map_or(0, |c| c.saturating_add(1))
+    }
+
+    /// Adds `mark` to the characters from `start` (inclusive) to `end` (exclusive).
+    pub fn add_mark(&mut self, start: usize, end: usize, mark: Mark) -> Option<BFTRichTextOp> {
+        let (start, end) = self.anchors(start, end, &mark)?;
+        Some(BFTRichTextOp::AddMark(mark, self.next_counter(), start, end))
+    }
+
+    /// Removes marks of the same kind as `mark` (e.g. any link) from the characters from
+    /// `start` (inclusive) to `end` (exclusive).
+    pub fn remove_mark(&mut self, start: usize, end: usize, mark: Mark) -> Option<BFTRichTextOp> {
+        let (start, end) = self.anchors(start, end, &mark)?;
+        Some(BFTRichTextOp::RemoveMark(mark, self.next_counter(), start, end))
+    }
+
+    pub fn get_text(&self) -> String {
+        self.text.get_list().into_iter().collect()
+    }
+
+    // the current range of a mark operation
+    fn resolve(&self, op: &MarkOp) -> Option<(usize, usize)> {
+        let start = self.text.resolve_anchor(&op.start)?;
+        let end = self.text.resolve_anchor(&op.end)?;
+        Some((start, end.max(start)))
+    }
+
+    /// Returns the marks of each character. The marks only change where a mark operation
+    /// starts or ends, so they are worked out at those boundaries alone.
+    pub fn get_marks(&self) -> Vec<Vec<Mark>> {
+        // the index each mark operation starts or ends at, and its position in `self.marks`
+        let mut boundaries = vec![];
+        for (i, op) in self.marks.iter().enumerate() {
+            match self.resolve(op) {
+                Some((start, end)) if start < end => boundaries.extend([(start, i), (end, i)]),
+                _ => {}
+            }
+        }
+        boundaries.sort_unstable();
+        let mut boundaries = boundaries.into_iter().peekable();
+
+        // the operations covering the current character, by kind of mark
+        let mut covering: HashMap<_, BTreeSet<usize>> = HashMap::new();
+        let mut current: Vec<Mark> = vec![];
+        let len = self.text.get_list().len();
+        let mut marks = Vec::with_capacity(len);
+        for idx in 0..len {
+            let mut changed = false;
+            while let Some((_, i)) = boundaries.next_if(|(at, _)| *at == idx) {
+                let ops = covering.entry(self.marks[i].mark.kind()).or_default();
+                if !ops.remove(&i) {
+                    ops.insert(i);
+                }
+                changed = true;
+            }
+            if changed {
+                // the operation applied last decides about each kind of mark
+                let mut deciding: Vec<&usize> = covering.values().filter_map(|ops| ops.last()).collect();
+                deciding.sort_unstable();
+                current = deciding.into_iter().map(|i| &self.marks[*i]).filter(|op| op.add).map(|op| op.mark.clone()).collect();
+            }
+            marks.push(current.clone());
+        }
+        marks
+    }
+
+    /// Returns the text as runs of characters with the same marks.
+    pub fn get_spans(&self) -> Vec<FormattedSpan> {
+        let mut spans: Vec<FormattedSpan> = vec![];
+        for (c, mut marks) in self.text.get_list().into_iter().zip(self.get_marks()) {
+            marks.sort_by_key(|m| m.to_bytes());
+            match spans.last_mut() {
+                Some(span) if span.marks == marks => span.text.push(c),
+                _ => spans.push(FormattedSpan { text: c.to_string(), marks }),
+            }
+        }
+        spans
+    }
+
+    // An anchor is valid if it refers to the start or end of the text, or to a position of a
+    // character that an Insert or Move with the same ID created in the causal past of the node.
+    fn is_anchor_valid(anchor: &Anchor<LamportId>, node: &Node<BFTRichTextOp>, hash_graph: &dyn GraphView<BFTRichTextOp>) -> bool {
+        let Some((id, hash)) = &anchor.id else { return true };
+        match hash_graph.get_node(hash).as_deref() {
+            Some(Node { value: BFTRichTextOp::Text(BFTRGAOp::Insert(_, i, _) | BFTRGAOp::Move(_, i, _)), .. }) => {
+                i == id && hash_graph.is_ancestor(hash, node)
+            }
+            _ => false,
+        }
+    }
+
+    // a counter is valid if it is 0 or if a mark operation in the causal past of the node has
+    // a counter at most one less
+    fn is_counter_valid(&self, counter: u64, node: &Node<BFTRichTextOp>, hash_graph: &dyn GraphView<BFTRichTextOp>) -> bool {
+        counter == 0 || self.by_counter.range(counter - 1..)
+            .flat_map(|(_, hashes)| hashes)
+            .any(|hash| hash_graph.is_ancestor(hash, node))
+    }
+}
+
+impl BFTCRDT<BFTRichTextOp> for BFTRichText {
+    type Event = BFTRichTextEvent;
+
+    fn interpret_node(&mut self, node: &Node<BFTRichTextOp>) -> Vec<BFTRichTextEvent> {
+        let (mark, add, counter, start, end) = match &node.value {
+            BFTRichTextOp::Text(op) => {
+                let events = self.text.interpret_node(&node.project(op.clone()));
+                return events.into_iter().map(BFTRichTextEvent::Text).collect();
+            }
+            BFTRichTextOp::AddMark(mark, c, start, end) => (mark, true, c, start, end),
+            BFTRichTextOp::RemoveMark(mark, c, start, end) => (mark, false, c, start, end),
+        };
+        if self.max_counter.is_none_or(|max| *counter > max) {
+            self.max_counter = Some(*counter);
+        }
+        self.by_counter.entry(*counter).or_default().push(node.get_hash());
+        let op = MarkOp { key: (*counter, node.get_hash()), mark: mark.clone(), add, start: start.clone(), end: end.clone() };
+        let range = self.resolve(&op);
+        let position = self.marks.partition_point(|m| m.key < op.key);
+        self.marks.insert(position, op);
+        match range {
+            Some((start, end)) if start < end => vec![BFTRichTextEvent::Formatted(start, end)],
+            _ => vec![],
+        }
+    }
+
+    fn is_sem_valid(&self, node: &Node<BFTRichTextOp>, hash_graph: &dyn GraphView<BFTRichTextOp>) -> bool {
+        match &node.value {
+            BFTRichTextOp::Text(op) => {
+                let view = ProjectedView::new(
+                    hash_graph,
+                    |op: &BFTRichTextOp| match op {
+                        BFTRichTextOp::Text(text_op) => Some(text_op.clone()),
+                        _ => None,
+                    },
+                    BFTRichTextOp::Text,
+                );
+                self.text.is_sem_valid(&node.project(op.clone()), &view)
+            }
+            BFTRichTextOp::AddMark(_, counter, start, end) | BFTRichTextOp::RemoveMark(_, counter, start, end) => {
+                self.is_counter_valid(*counter, node, hash_graph)
+                    && Self::is_anchor_valid(start, node, hash_graph)
+                    && Self::is_anchor_valid(end, node, hash_graph)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bft_crdts::bft_rga::{Anchor, Bias, BFTRGAOp};
+    use crate::bft_crdts::hash_graph::HashType;
+    use crate::bft_crdts::replica::Replica;
+    use super::*;
+
+    #[test]
+    fn test_bft_richtext_marks() {
+        let mut a: Replica<BFTRichTextOp, BFTRichText> = Replica::new(BFTRichText::new(1));
+        let mut nodes = vec![];
+        for (i, c) in "hello world".chars().enumerate() {
+            let op = a.crdt.insert(i, c).unwrap();
+            nodes.push(a.handle_local_op(op));
+        }
+        let op = a.crdt.add_mark(0, 5, Mark::Bold).unwrap();
+        nodes.push(a.handle_local_op(op));
+        let op = a.crdt.add_mark(6, 11, Mark::Link("https://example.com".to_string())).unwrap();
+        nodes.push(a.handle_local_op(op));
+
+        // bold grows at its end, links do not
+        let op = a.crdt.insert(5, '!').unwrap();
+        nodes.push(a.handle_local_op(op));
+        let op = a.crdt.insert(12, '?').unwrap();
+        nodes.push(a.handle_local_op(op));
+        let op = a.crdt.insert(0, '>').unwrap();
+        nodes.push(a.handle_local_op(op));
+        let link = Mark::Link("https://example.com".to_string());
+        assert_eq!(a.crdt.get_spans(), vec![
+            FormattedSpan { text: ">".to_string(), marks: vec![] },
+            FormattedSpan { text: "hello!".to_string(), marks: vec![Mark::Bold] },
+            FormattedSpan { text: " ".to_string(), marks: vec![] },
+            FormattedSpan { text: "world".to_string(), marks: vec![link.clone()] },
+            FormattedSpan { text: "?".to_string(), marks: vec![] },
+        ]);
+
+        let mut b: Replica<BFTRichTextOp, BFTRichText> = Replica::new(BFTRichText::new(2));
+        let events = b.record_events();
+        for node in nodes.iter().rev() {
+            b.handle_remote_node(node.clone());
+        }
+        assert_eq!(b.crdt.get_spans(), a.crdt.get_spans());
+        assert!(events.borrow().contains(&BFTRichTextEvent::Formatted(0, 5)));
+
+        // a removes bold from "ell" while b makes "hello!" italic concurrently
+        let op = a.crdt.remove_mark(2, 5, Mark::Bold).unwrap();
+        let remove = a.handle_local_op(op);
+        let op = b.crdt.add_mark(1, 6, Mark::Italic).unwrap();
+        let italic = b.handle_local_op(op);
+        a.handle_remote_node(italic);
+        b.handle_remote_node(remove);
+        assert_eq!(a.crdt.get_spans(), b.crdt.get_spans());
+        assert_eq!(a.crdt.get_text(), ">hello! world?");
+        assert_eq!(a.crdt.get_marks()[2], vec![Mark::Italic]);
+        assert_eq!(a.crdt.get_marks()[1], vec![Mark::Bold, Mark::Italic]);
+        assert_eq!(a.crdt.get_marks()[6], vec![Mark::Bold]);
+    }
+
+    #[test]
+    fn test_bft_richtext_sem_validity() {
+        let mut handler: Replica<BFTRichTextOp, BFTRichText> = Replica::new(BFTRichText::new(1));
+        let op = handler.crdt.insert(0, 'a').unwrap();
+        let insert = handler.handle_local_op(op);
+        let op = handler.crdt.add_mark(0, 1, Mark::Bold).unwrap();
+        let bold = handler.handle_local_op(op);
+        let BFTRichTextOp::Text(BFTRGAOp::Insert(_, id, _)) = insert.value else { panic!() };
+
+        // anchors must refer to characters in the causal past
+        let heads = handler.hash_graph.get_heads().to_vec();
+        let anchor = |hash: HashType| Anchor { id: Some((id, hash)), bias: Bias::Right };
+        let end = Anchor { id: None, bias: Bias::Right };
+        let valid = Node::new(heads.clone(), BFTRichTextOp::AddMark(Mark::Italic, 1, anchor(insert.get_hash()), end.clone()));
+        assert!(handler.crdt.is_sem_valid(&valid, &handler.hash_graph));
+        let concurrent = Node::new(vec![], BFTRichTextOp::AddMark(Mark::Italic, 1, anchor(insert.get_hash()), end.clone()));
+        assert!(!handler.crdt.is_sem_valid(&concurrent, &handler.hash_graph));
+        let not_a_char = Node::new(heads.clone(), BFTRichTextOp::RemoveMark(Mark::Bold, 1, anchor(bold.get_hash()), end.clone()));
+        assert!(!handler.crdt.is_sem_valid(&not_a_char, &handler.hash_graph));
+
+        // the counter may only exceed those of the mark operations in the causal past by one
+        let inflated = Node::new(heads.clone(), BFTRichTextOp::AddMark(Mark::Italic, u64::MAX, anchor(insert.get_hash()), end.clone()));
+        assert!(!handler.crdt.is_sem_valid(&inflated, &handler.hash_graph));
+        let skipping = Node::new(vec![insert.get_hash()], BFTRichTextOp::AddMark(Mark::Italic, 1, anchor(insert.get_hash()), end));
+        assert!(!handler.crdt.is_sem_valid(&skipping, &handler.hash_graph));
+
+        // text operations are judged by the RGA against the text nodes only
+        let after_mark = Node::new(heads, BFTRichTextOp::Text(BFTRGAOp::Insert('b', id, Some((id, bold.get_hash())))));
+        assert!(!handler.crdt.is_sem_valid(&after_mark, &handler.hash_graph));
+    }
+}
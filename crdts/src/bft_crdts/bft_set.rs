@@ -0,0 +1,410 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+use crate::bft_crdts::bft_crdt::BFTCRDT;
+use crate::bft_crdts::bft_orset::BFTORSetEvent;
+use crate::bft_crdts::hash_graph::{GraphView, HashType, Node};
+use crate::crdts::crdt::SetQuery;
+use crate::serialize::Serialize;
+
+//  Set variants with other semantics than the add-wins BFT ORSet, validated the same way: as
+//   in the ORSet, the ID of an operation is the hash of the node containing it, and an
+//   operation that cancels other operations lists their IDs, which must be operations on the
+//   same element in its causal past.
+//
+//   In the remove-wins BFTRWSet, a Remove cancels the Adds it has observed and an Add cancels
+//   the Removes it has observed. An element is in the set if an Add and no Remove of it is left,
+//   so a Remove wins over concurrent Adds, and a later Add brings the element back.
+//
+//   In the BFT2PSet, an element that has been removed stays removed. A Remove lists the Adds it
+//   has observed like in the ORSet, but must list at least one, so that only elements that
+//   have been added can be removed.
+
+type SetID = HashType; // in BFT sets, ID is the hash value of the node containing the operation
+
+#[derive(Debug, Clone)]
+pub enum BFTRWSetOp<E> {
+    // e, ids of the observed Removes
+    Add(E, Vec<SetID>),
+    // e, ids of the observed Adds
+    Remove(E, Vec<SetID>),
+}
+
+#[derive(Debug, Clone)]
+pub enum BFT2PSetOp<E> {
+    Add(E),
+    // e, ids of the observed Adds
+    Remove(E, Vec<SetID>),
+}
+
+impl <E> Display for BFTRWSetOp<E>
+where
+    E: Eq + Hash + Clone + Serialize + Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BFTRWSetOp::Add(e, ids) => {
+                write!(f, "Add({:?}, {:?})", e, ids)
+            }
+            BFTRWSetOp::Remove(e, ids) => {
+                write!(f, "Remove({:?}, {:?})", e, ids)
+            }
+        }
+    }
+}
+
+impl <E> Display for BFT2PSetOp<E>
+where
+    E: Eq + Hash + Clone + Serialize + Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BFT2PSetOp::Add(e) => {
+                write!(f, "Add({:?})", e)
+            }
+            BFT2PSetOp::Remove(e, ids) => {
+                write!(f, "Remove({:?}, {:?})", e, ids)
+            }
+        }
+    }
+}
+
+fn set_op_to_bytes<E: Serialize>(tag: u8, e: &E, ids: &[SetID]) -> Vec<u8> {
+    let mut bytes = vec![tag];
+    let mut sorted_ids = ids.to_vec();
+    sorted_ids.sort();
+    for id in sorted_ids.iter() {
+        bytes.extend_from_slice(id.as_bytes());
+    }
+    bytes.extend_from_slice(&e.to_bytes());
+    bytes
+}
+
+impl<E> Serialize for BFTRWSetOp<E>
+where
+    E: Eq + Hash + Clone + Serialize,
+{
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            BFTRWSetOp::Add(e, ids) => set_op_to_bytes(0, e, ids),
+            BFTRWSetOp::Remove(e, ids) => set_op_to_bytes(1, e, ids),
+        }
+    }
+}
+
+impl<E> Serialize for BFT2PSetOp<E>
+where
+    E: Eq + Hash + Clone + Serialize,
+{
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            BFT2PSetOp::Add(e) => set_op_to_bytes(0, e, &[]),
+            BFT2PSetOp::Remove(e, ids) => set_op_to_bytes(1, e, ids),
+        }
+    }
+}
+
+// every ID must be the hash of a node in the causal past of `node` whose operation is of the
+// expected kind on the same element
+fn are_ops_in_past<O>(ids: &[SetID], node: &Node<O>, hash_graph: &dyn GraphView<O>, is_expected: impl Fn(&O) -> bool) -> bool
+where
+    O: Serialize + Clone,
+{
+    ids.iter().all(|id| match hash_graph.get_node(id) {
+        Some(n) => is_expected(&n.value) && hash_graph.is_ancestor(id, node),
+        None => false,
+    })
+}
+
+pub struct BFTRWSet<E>
+where
+    E: Eq + Hash + Clone + Serialize,
+{
+    // the Adds and Removes of each element that have not been cancelled
+    adds: HashMap<E, HashSet<SetID>>,
+    removes: HashMap<E, HashSet<SetID>>,
+}
+
+impl<E> BFTRWSet<E>
+where
+    E: Eq + Hash + Clone + Serialize,
+{
+    pub fn new() -> Self {
+        BFTRWSet {
+            adds: HashMap::new(),
+            removes: HashMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, e: E) -> BFTRWSetOp<E> {
+        let ids = self.removes.get(&e).map(|ids| ids.iter().cloned().collect()).unwrap_or_default();
+        BFTRWSetOp::Add(e, ids)
+    }
+
+    pub fn remove(&mut self, e: E) -> BFTRWSetOp<E> {
+        let ids = self.adds.get(&e).map(|ids| ids.iter().cloned().collect()).unwrap_or_default();
+        BFTRWSetOp::Remove(e, ids)
+    }
+
+    pub fn is_in(&self, e: E) -> bool {
+        self.contains(&e)
+    }
+
+    pub fn get_set(&self) -> HashSet<E> {
+        self.elements()
+    }
+}
+
+impl<E> Default for BFTRWSet<E>
+where
+    E: Eq + Hash + Clone + Serialize,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E> SetQuery<E> for BFTRWSet<E>
+where
+    E: Eq + Hash + Clone + Serialize,
+{
+    fn contains(&self, e: &E) -> bool {
+        self.adds.get(e).is_some_and(|ids| !ids.is_empty()) && self.removes.get(e).is_none_or(|ids| ids.is_empty())
+    }
+
+    fn elements(&self) -> HashSet<E> {
+        self.adds.keys().filter(|e| self.contains(e)).cloned().collect()
+    }
+}
+
+impl<E> BFTCRDT<BFTRWSetOp<E>> for BFTRWSet<E>
+where
+    E: Eq + Hash + Clone + Serialize,
+{
+    type Event = BFTORSetEvent<E>;
+
+    fn interpret_node(&mut self, node: &Node<BFTRWSetOp<E>>) -> Vec<BFTORSetEvent<E>> {
+        let e = match &node.value {
+            BFTRWSetOp::Add(e, _) | BFTRWSetOp::Remove(e, _) => e,
+        };
+        let was_in = self.contains(e);
+        let (ids, cancelled, kept) = match &node.value {
+            BFTRWSetOp::Add(_, ids) => (ids, &mut self.removes, &mut self.adds),
+            BFTRWSetOp::Remove(_, ids) => (ids, &mut self.adds, &mut self.removes),
+        };
+        if let Some(e_ids) = cancelled.get_mut(e) {
+            for id in ids {
+                e_ids.remove(id);
+            }
+        }
+        kept.entry(e.clone()).or_default().insert(node.get_hash());
+        match (was_in, self.contains(e)) {
+            (false, true) => vec![BFTORSetEvent::Appeared(e.clone())],
+            (true, false) => vec![BFTORSetEvent::Disappeared(e.clone())],
+            _ => vec![],
+        }
+    }
+
+    fn is_sem_valid(&self, node: &Node<BFTRWSetOp<E>>, hash_graph: &dyn GraphView<BFTRWSetOp<E>>) -> bool {
+        match &node.value {
+            BFTRWSetOp::Add(e, ids) => {
+                are_ops_in_past(ids, node, hash_graph, |op| matches!(op, BFTRWSetOp::Remove(e2, _) if e2 == e))
+            }
+            BFTRWSetOp::Remove(e, ids) => {
+                are_ops_in_past(ids, node, hash_graph, |op| matches!(op, BFTRWSetOp::Add(e2, _) if e2 == e))
+            }
+        }
+    }
+}
+
+pub struct BFT2PSet<E>
+where
+    E: Eq + Hash + Clone + Serialize,
+{
+    adds: HashMap<E, HashSet<SetID>>,
+    removed: HashSet<E>,
+}
+
+impl<E> BFT2PSet<E>
+where
+    E: Eq + Hash + Clone + Serialize,
+{
+    pub fn new() -> Self {
+        BFT2PSet {
+            adds: HashMap::new(),
+            removed: HashSet::new(),
+        }
+    }
+
+    pub fn add(&mut self, e: E) -> BFT2PSetOp<E> {
+        BFT2PSetOp::Add(e)
+    }
+
+    /// Returns `None` if `e` has never been added, since it could not be removed.
+    pub fn remove(&mut self, e: E) -> Option<BFT2PSetOp<E>> {
+        let ids: Vec<SetID> = self.adds.get(&e)?.iter().cloned().collect();
+        Some(BFT2PSetOp::Remove(e, ids))
+    }
+
+    pub fn is_in(&self, e: E) -> bool {
+        self.contains(&e)
+    }
+
+    /// Returns whether `e` has been removed, after which it cannot be added again.
+    pub fn is_removed(&self, e: &E) -> bool {
+        self.removed.contains(e)
+    }
+
+    pub fn get_set(&self) -> HashSet<E> {
+        self.elements()
+    }
+}
+
+impl<E> Default for BFT2PSet<E>
+where
+    E: Eq + Hash + Clone + Serialize,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E> SetQuery<E> for BFT2PSet<E>
+where
+    E: Eq + Hash + Clone + Serialize,
+{
+    fn contains(&self, e: &E) -> bool {
+        self.adds.contains_key(e) && !self.removed.contains(e)
+    }
+
+    fn elements(&self) -> HashSet<E> {
+        self.adds.keys().filter(|e| !self.removed.contains(e)).cloned().collect()
+    }
+}
+
+impl<E> BFTCRDT<BFT2PSetOp<E>> for BFT2PSet<E>
+where
+    E: Eq + Hash + Clone + Serialize,
+{
+    type Event = BFTORSetEvent<E>;
+
+    fn interpret_node(&mut self, node: &Node<BFT2PSetOp<E>>) -> Vec<BFTORSetEvent<E>> {
+        match &node.value {
+            BFT2PSetOp::Add(e) => {
+                let appeared = !self.adds.contains_key(e) && !self.removed.contains(e);
+                self.adds.entry(e.clone()).or_default().insert(node.get_hash());
+                if appeared {
+                    return vec![BFTORSetEvent::Appeared(e.clone())];
+                }
+            }
+            BFT2PSetOp::Remove(e, _ids) => {
+                let was_in = self.contains(e);
+                self.removed.insert(e.clone());
+                if was_in {
+                    return vec![BFTORSetEvent::Disappeared(e.clone())];
+                }
+            }
+        }
+        vec![]
+    }
+
+    fn is_sem_valid(&self, node: &Node<BFT2PSetOp<E>>, hash_graph: &dyn GraphView<BFT2PSetOp<E>>) -> bool {
+        match &node.value {
+            BFT2PSetOp::Add(_e) => true,
+            BFT2PSetOp::Remove(e, ids) => {
+                !ids.is_empty() && are_ops_in_past(ids, node, hash_graph, |op| matches!(op, BFT2PSetOp::Add(e2) if e2 == e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use crate::bft_crdts::bft_orset::{BFTORSet, BFTORSetEvent};
+    use crate::bft_crdts::replica::Replica;
+    use crate::crdts::crdt::{CRDT, SetQuery};
+    use crate::crdts::orset::ORSet;
+    use super::*;
+
+    #[test]
+    fn test_bft_rwset_remove_wins() {
+        let mut a: Replica<_, BFTRWSet<&str>> = Replica::new(BFTRWSet::new());
+        let op = a.crdt.add("x");
+        let add = a.handle_local_op(op);
+        let mut b: Replica<_, BFTRWSet<&str>> = Replica::new(BFTRWSet::new());
+        b.handle_remote_node(add);
+
+        // a re-adds x while b removes it concurrently: the remove wins
+        let op = a.crdt.add("x");
+        let re_add = a.handle_local_op(op);
+        let op = b.crdt.remove("x");
+        let remove = b.handle_local_op(op);
+        let events = a.record_events();
+        a.handle_remote_node(remove);
+        b.handle_remote_node(re_add);
+        assert!(!a.crdt.is_in("x"));
+        assert!(!b.crdt.is_in("x"));
+        assert_eq!(*events.borrow(), vec![BFTORSetEvent::Disappeared("x")]);
+
+        // an add that has observed the remove brings x back
+        let op = b.crdt.add("x");
+        let add = b.handle_local_op(op);
+        a.handle_remote_node(add);
+        assert_eq!(a.crdt.get_set(), HashSet::from(["x"]));
+        assert_eq!(a.crdt.get_set(), b.crdt.get_set());
+
+        // cancelled operations must be of the opposite kind, on the same element, in the causal past
+        let heads = a.hash_graph.get_heads().to_vec();
+        let BFTRWSetOp::Remove(_, ids) = a.crdt.remove("x") else { panic!() };
+        let valid = Node::new(heads.clone(), BFTRWSetOp::Remove("x", ids.clone()));
+        assert!(a.crdt.is_sem_valid(&valid, &a.hash_graph));
+        let other_elem = Node::new(heads.clone(), BFTRWSetOp::Remove("y", ids.clone()));
+        assert!(!a.crdt.is_sem_valid(&other_elem, &a.hash_graph));
+        let wrong_kind = Node::new(heads, BFTRWSetOp::Add("x", ids.clone()));
+        assert!(!a.crdt.is_sem_valid(&wrong_kind, &a.hash_graph));
+        let concurrent = Node::new(vec![], BFTRWSetOp::Remove("x", ids));
+        assert!(!a.crdt.is_sem_valid(&concurrent, &a.hash_graph));
+    }
+
+    #[test]
+    fn test_bft_2pset() {
+        let mut handler: Replica<_, BFT2PSet<&str>> = Replica::new(BFT2PSet::new());
+        assert!(handler.crdt.remove("x").is_none());
+        let op = handler.crdt.add("x");
+        handler.handle_local_op(op);
+        let op = handler.crdt.remove("x").unwrap();
+        handler.handle_local_op(op);
+        let op = handler.crdt.add("x");
+        handler.handle_local_op(op);
+        assert!(!handler.crdt.is_in("x"));
+        assert!(handler.crdt.is_removed(&"x"));
+
+        // a Remove must have observed an Add of the element
+        let unobserved = Node::new(handler.hash_graph.get_heads().to_vec(), BFT2PSetOp::Remove("y", vec![]));
+        assert!(!handler.crdt.is_sem_valid(&unobserved, &handler.hash_graph));
+    }
+
+    #[test]
+    fn test_set_query_across_set_semantics() {
+        fn query<S: SetQuery<&'static str>>(set: &S) -> (bool, usize) {
+            (set.contains(&"x"), set.len())
+        }
+        let mut orset: Replica<_, BFTORSet<&str>> = Replica::new(BFTORSet::new());
+        let op = orset.crdt.add("x");
+        orset.handle_local_op(op);
+        let mut rwset: Replica<_, BFTRWSet<&str>> = Replica::new(BFTRWSet::new());
+        let op = rwset.crdt.add("x");
+        rwset.handle_local_op(op);
+        let mut twopset: Replica<_, BFT2PSet<&str>> = Replica::new(BFT2PSet::new());
+        let op = twopset.crdt.add("x");
+        twopset.handle_local_op(op);
+        let mut plain = ORSet::new();
+        let op = plain.add("x", 1);
+        plain.interpret_op(&op);
+        assert_eq!(query(&orset.crdt), (true, 1));
+        assert_eq!(query(&rwset.crdt), (true, 1));
+        assert_eq!(query(&twopset.crdt), (true, 1));
+        assert_eq!(query(&plain), (true, 1));
+    }
+}
@@ -0,0 +1,475 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::{Debug, Display};
+use crate::bft_crdts::bft_crdt::BFTCRDT;
+use crate::bft_crdts::hash_graph::{GraphView, HashType, Node};
+use crate::serialize::Serialize;
+
+//  A BFT tree is a tree of nodes below an implicit root. A tree node is created under a parent
+//   and is identified by the hash of the graph node of its Create operation; Move gives it a
+//   new parent and Delete moves it, with its subtree, out of the tree for good.
+//
+//   Concurrent moves can together form a cycle, so every operation is applied in a total order
+//   that is the same on all peers and extends the causal order of the HashGraph: nodes are
+//   ordered by their depth in the graph (one more than the deepest predecessor) and then by
+//   their hash. A move that would make a tree node its own ancestor, or that moves a deleted
+//   node, is skipped when it comes up in that order. When a node arrives that is ordered
+//   before nodes that have already been applied, those are undone, the new operation is
+//   applied, and they are redone, as in Kleppmann et al.'s move operation for replicated trees.
+//   The log entries up to the depth of nodes that every peer has seen can be pruned. A node
+//   that is still in flight, or that a faulty peer gives shallow predecessors, may be ordered
+//   before them, but can no longer be put in its place; such nodes are therefore not valid
+//   once the log has been pruned past them, as nodes referring to collected tombstones are not
+//   valid in the BFT RGA.
+//
+//   Depths are derived from predecessors, so a node is only valid if the tree has interpreted
+//   all of its predecessors. A tree therefore cannot be nested in another CRDT such as a
+//   BFTProduct, where the predecessors of its nodes may carry operations of other CRDTs.
+
+type TreeID = HashType; // in BFT tree, ID is the hash value of the tree node's Create operation
+
+#[derive(Debug, Clone)]
+pub enum BFTTreeOp<V> {
+    // parent (None for the root), value
+    Create(Option<TreeID>, V),
+    // id, new parent (None for the root)
+    Move(TreeID, Option<TreeID>),
+    // id
+    Delete(TreeID),
+}
+
+impl<V> Display for BFTTreeOp<V>
+where
+    V: Clone + Serialize + Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BFTTreeOp::Create(parent, v) => {
+                write!(f, "Create({:?}, {:?})", parent, v)
+            }
+            BFTTreeOp::Move(id, parent) => {
+                write!(f, "Move({:?}, {:?})", id, parent)
+            }
+            BFTTreeOp::Delete(id) => {
+                write!(f, "Delete({:?})", id)
+            }
+        }
+    }
+}
+
+fn parent_to_bytes(parent: &Option<TreeID>) -> Vec<u8> {
+    match parent {
+        Some(id) => [vec![1u8], id.to_bytes()].concat(),
+        None => vec![0u8],
+    }
+}
+
+impl<V> Serialize for BFTTreeOp<V>
+where
+    V: Clone + Serialize,
+{
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            BFTTreeOp::Create(parent, v) => {
+                [vec![0u8], parent_to_bytes(parent), v.to_bytes()].concat()
+            }
+            BFTTreeOp::Move(id, parent) => {
+                [vec![1u8], id.to_bytes(), parent_to_bytes(parent)].concat()
+            }
+            BFTTreeOp::Delete(id) => {
+                [vec![2u8], id.to_bytes()].concat()
+            }
+        }
+    }
+}
+
+/// A change to the structure of a `BFTTree`. Parents are `None` for the root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BFTTreeEvent {
+    // id, parent
+    Created(TreeID, Option<TreeID>),
+    // id, old parent, new parent
+    Moved(TreeID, Option<TreeID>, Option<TreeID>),
+    // id
+    Deleted(TreeID),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Place {
+    Under(Option<TreeID>),
+    Trash,
+}
+
+// An operation in the total order, with the place its tree node had before the operation
+// (None if the node did not exist yet), so that it can be undone, and whether it was skipped.
+struct LogEntry {
+    key: (u64, HashType),
+    id: TreeID,
+    place: Place,
+    old_place: Option<Place>,
+    applied: bool,
+}
+
+pub struct BFTTree<V> {
+    places: HashMap<TreeID, Place>,
+    children: HashMap<Option<TreeID>, BTreeSet<TreeID>>,
+    values: HashMap<TreeID, V>,
+    // all operations in the total order that have not been pruned, including skipped ones
+    log: Vec<LogEntry>,
+    depths: HashMap<HashType, u64>,
+    // the key of the last pruned log entry; only nodes ordered after it are valid
+    pruned: Option<(u64, HashType)>,
+}
+
+impl<V> BFTTree<V>
+where
+    V: Clone + Serialize,
+{
+    pub fn new() -> Self {
+        BFTTree {
+            places: HashMap::new(),
+            children: HashMap::new(),
+            values: HashMap::new(),
+            log: vec![],
+            depths: HashMap::new(),
+            pruned: None,
+        }
+    }
+
+    pub fn create(&mut self, parent: Option<TreeID>, value: V) -> Option<BFTTreeOp<V>> {
+        if parent.as_ref().is_some_and(|p| !self.contains(p)) {
+            return None;
+        }
+        Some(BFTTreeOp::Create(parent, value))
+    }
+
+    /// Moves `id` under `parent`, unless that would make it its own ancestor.
+    pub fn move_node(&mut self, id: TreeID, parent: Option<TreeID>) -> Option<BFTTreeOp<V>> {
+        if !self.contains(&id) || parent.as_ref().is_some_and(|p| !self.contains(p)) {
+            return None;
+        }
+        if self.is_ancestor_or_self(&id, &parent) {
+            return None;
+        }
+        Some(BFTTreeOp::Move(id, parent))
+    }
+
+    pub fn delete(&mut self, id: TreeID) -> Option<BFTTreeOp<V>> {
+        if !self.contains(&id) {
+            return None;
+        }
+        Some(BFTTreeOp::Delete(id))
+    }
+
+    /// Returns whether `id` is in the tree, i.e. neither it nor an ancestor is deleted.
+    pub fn contains(&self, id: &TreeID) -> bool {
+        let mut current = id.clone();
+        loop {
+            match self.places.get(&current) {
+                Some(Place::Under(Some(parent))) => current = parent.clone(),
+                Some(Place::Under(None)) => return true,
+                Some(Place::Trash) | None => return false,
+            }
+        }
+    }
+
+    /// Returns the parent of `id` (`None` for the root) if it is in the tree.
+    pub fn get_parent(&self, id: &TreeID) -> Option<Option<TreeID>> {
+        if !self.contains(id) {
+            return None;
+        }
+        match self.places.get(id) {
+            Some(Place::Under(parent)) => Some(parent.clone()),
+            _ => None,
+        }
+    }
+
+    /// Returns the children of `parent` (`None` for the root), ordered by ID.
+    pub fn get_children(&self, parent: Option<TreeID>) -> Vec<TreeID> {
+        if parent.as_ref().is_some_and(|p| !self.contains(p)) {
+            return vec![];
+        }
+        self.children.get(&parent).map(|c| c.iter().cloned().collect()).unwrap_or_default()
+    }
+
+    pub fn get_value(&self, id: &TreeID) -> Option<&V> {
+        self.values.get(id).filter(|_| self.contains(id))
+    }
+
+    /// Drops the log entries that no operation can be ordered before anymore, since they are
+    /// at most as deep as one of `stable_heads`. The caller must know that every replica has
+    /// `stable_heads` in its causal past; from then on, nodes that are ordered before a pruned
+    /// entry are not semantically valid. Returns the number of pruned entries.
+    pub fn prune_log(&mut self, stable_heads: &[HashType]) -> usize {
+        let Some(depth) = stable_heads.iter().filter_map(|h| self.depths.get(h)).max() else { return 0 };
+        let pruned = self.log.partition_point(|e| e.key.0 <= *depth);
+        if let Some(last) = self.log.drain(..pruned).next_back() {
+            self.pruned = Some(last.key);
+        }
+        pruned
+    }
+
+    // the depth of a node in the graph and its hash, by which operations are ordered
+    fn key_of(&self, node: &Node<BFTTreeOp<V>>) -> (u64, HashType) {
+        let depth = node.predecessors.iter().filter_map(|p| self.depths.get(p)).max().map_or(0, |d| d + 1);
+        (depth, node.get_hash())
+    }
+
+    // whether `id` is `node` or one of its ancestors
+    fn is_ancestor_or_self(&self, id: &TreeID, node: &Option<TreeID>) -> bool {
+        let mut current = node.clone();
+        while let Some(n) = current {
+            if &n == id {
+                return true;
+            }
+            current = match self.places.get(&n) {
+                Some(Place::Under(parent)) => parent.clone(),
+                _ => None,
+            };
+        }
+        false
+    }
+
+    fn set_place(&mut self, id: &TreeID, place: Option<Place>) {
+        let old = match place.clone() {
+            Some(place) => self.places.insert(id.clone(), place),
+            None => self.places.remove(id),
+        };
+        if let Some(Place::Under(parent)) = old {
+            if let Some(siblings) = self.children.get_mut(&parent) {
+                siblings.remove(id);
+            }
+        }
+        if let Some(Place::Under(parent)) = place {
+            self.children.entry(parent).or_default().insert(id.clone());
+        }
+    }
+
+    // Applies the move of `id` to `place` unless it has to be skipped.
+    fn do_op(&mut self, key: (u64, HashType), id: TreeID, place: Place) -> LogEntry {
+        let old_place = self.places.get(&id).cloned();
+        let creates_cycle = match &place {
+            Place::Under(parent) => self.is_ancestor_or_self(&id, parent),
+            Place::Trash => false,
+        };
+        let applied = old_place != Some(Place::Trash) && !creates_cycle;
+        if applied {
+            self.set_place(&id, Some(place.clone()));
+        }
+        LogEntry { key, id, place, old_place, applied }
+    }
+
+    fn undo_op(&mut self, entry: &LogEntry) {
+        if entry.applied {
+            self.set_place(&entry.id, entry.old_place.clone());
+        }
+    }
+}
+
+impl<V> Default for BFTTree<V>
+where
+    V: Clone + Serialize,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> BFTCRDT<BFTTreeOp<V>> for BFTTree<V>
+where
+    V: Clone + Serialize,
+{
+    type Event = BFTTreeEvent;
+
+    fn interpret_node(&mut self, node: &Node<BFTTreeOp<V>>) -> Vec<BFTTreeEvent> {
+        let (depth, hash) = self.key_of(node);
+        self.depths.insert(hash.clone(), depth);
+        if let BFTTreeOp::Create(_parent, v) = &node.value {
+            self.values.insert(hash.clone(), v.clone());
+        }
+        let (id, place) = match &node.value {
+            BFTTreeOp::Create(parent, _v) => (hash.clone(), Place::Under(parent.clone())),
+            BFTTreeOp::Move(id, parent) => (id.clone(), Place::Under(parent.clone())),
+            BFTTreeOp::Delete(id) => (id.clone(), Place::Trash),
+        };
+        let key = (depth, hash);
+
+        // undo the operations ordered after this one, apply it, and redo them
+        let position = self.log.partition_point(|e| e.key < key);
+        let later: Vec<LogEntry> = self.log.drain(position..).collect();
+        let mut old_places: Vec<(TreeID, Option<Place>)> = vec![(id.clone(), self.places.get(&id).cloned())];
+        for entry in later.iter().rev() {
+            old_places.push((entry.id.clone(), self.places.get(&entry.id).cloned()));
+            self.undo_op(entry);
+        }
+        let entry = self.do_op(key, id, place);
+        self.log.push(entry);
+        for entry in later {
+            let entry = self.do_op(entry.key, entry.id, entry.place);
+            self.log.push(entry);
+        }
+
+        let mut events = vec![];
+        let mut seen = HashSet::new();
+        for (id, old_place) in old_places {
+            if !seen.insert(id.clone()) {
+                continue;
+            }
+            match (old_place, self.places.get(&id)) {
+                (None, Some(Place::Under(parent))) => {
+                    events.push(BFTTreeEvent::Created(id, parent.clone()));
+                }
+                (Some(Place::Under(old)), Some(Place::Under(new))) if &old != new => {
+                    events.push(BFTTreeEvent::Moved(id, old, new.clone()));
+                }
+                (Some(Place::Under(_)), Some(Place::Trash)) => {
+                    events.push(BFTTreeEvent::Deleted(id));
+                }
+                _ => {}
+            }
+        }
+        events
+    }
+
+    fn is_sem_valid(&self, node: &Node<BFTTreeOp<V>>, hash_graph: &dyn GraphView<BFTTreeOp<V>>) -> bool {
+        // every predecessor must be a tree node, so that the depth of the node is known
+        if !node.predecessors.iter().all(|p| self.depths.contains_key(p)) {
+            return false;
+        }
+        // the node must not be ordered before a pruned log entry, since it could not be put
+        // in its place
+        if self.pruned.as_ref().is_some_and(|pruned| &self.key_of(node) <= pruned) {
+            return false;
+        }
+        // a tree node referred to must have been created in the causal past of the node
+        let is_tree_node_in_past = |id: &TreeID| match hash_graph.get_node(id) {
+            Some(n) => matches!(n.value, BFTTreeOp::Create(..)) && hash_graph.is_ancestor(id, node),
+            None => false,
+        };
+        match &node.value {
+            BFTTreeOp::Create(parent, _v) => parent.as_ref().is_none_or(is_tree_node_in_past),
+            BFTTreeOp::Move(id, parent) => {
+                is_tree_node_in_past(id) && parent.as_ref().is_none_or(is_tree_node_in_past)
+            }
+            BFTTreeOp::Delete(id) => is_tree_node_in_past(id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bft_crdts::replica::Replica;
+    use super::*;
+
+    #[test]
+    fn test_bft_tree_concurrent_moves() {
+        let mut a: Replica<BFTTreeOp<&str>, BFTTree<&str>> = Replica::new(BFTTree::new());
+        let op = a.crdt.create(None, "x").unwrap();
+        let x = a.handle_local_op(op);
+        let op = a.crdt.create(None, "y").unwrap();
+        let y = a.handle_local_op(op);
+        let (x_id, y_id) = (x.get_hash(), y.get_hash());
+        let mut b: Replica<BFTTreeOp<&str>, BFTTree<&str>> = Replica::new(BFTTree::new());
+        b.handle_remote_node(x.clone());
+        b.handle_remote_node(y.clone());
+        assert_eq!(b.crdt.get_children(None).len(), 2);
+
+        // moving x under y and y under x concurrently must not form a cycle
+        let op = a.crdt.move_node(x_id.clone(), Some(y_id.clone())).unwrap();
+        let a_move = a.handle_local_op(op);
+        assert!(a.crdt.move_node(y_id.clone(), Some(x_id.clone())).is_none());
+        let op = b.crdt.move_node(y_id.clone(), Some(x_id.clone())).unwrap();
+        let b_move = b.handle_local_op(op);
+        let events = a.record_events();
+        a.handle_remote_node(b_move.clone());
+        b.handle_remote_node(a_move.clone());
+        for id in [&x_id, &y_id] {
+            assert_eq!(a.crdt.get_parent(id), b.crdt.get_parent(id));
+        }
+        assert_eq!(a.crdt.get_children(None).len(), 1);
+
+        // both moves have the same depth, so the one with the smaller hash wins
+        let (winner, loser) = if a_move.get_hash() < b_move.get_hash() { (&x_id, &y_id) } else { (&y_id, &x_id) };
+        assert_eq!(a.crdt.get_parent(winner), Some(Some(loser.clone())));
+        assert_eq!(a.crdt.get_parent(loser), Some(None));
+        if winner == &y_id {
+            assert_eq!(*events.borrow(), vec![BFTTreeEvent::Moved(y_id.clone(), None, Some(x_id.clone())), BFTTreeEvent::Moved(x_id.clone(), Some(y_id.clone()), None)]);
+        } else {
+            assert!(events.borrow().is_empty());
+        }
+
+        // deleting a node removes its subtree, and later moves of it are skipped
+        let op = a.crdt.delete(loser.clone()).unwrap();
+        let delete = a.handle_local_op(op);
+        b.handle_remote_node(delete);
+        assert!(!b.crdt.contains(winner));
+        assert!(b.crdt.get_children(None).is_empty());
+        assert_eq!(b.crdt.get_value(winner), None);
+        let resurrect = Node::new(b.hash_graph.get_heads().to_vec(), BFTTreeOp::Move(loser.clone(), None));
+        b.handle_remote_node(resurrect);
+        assert!(!b.crdt.contains(loser));
+    }
+
+    #[test]
+    fn test_bft_tree_sem_validity() {
+        let mut handler: Replica<BFTTreeOp<&str>, BFTTree<&str>> = Replica::new(BFTTree::new());
+        let op = handler.crdt.create(None, "x").unwrap();
+        let x = handler.handle_local_op(op);
+        let op = handler.crdt.create(Some(x.get_hash()), "y").unwrap();
+        let y = handler.handle_local_op(op);
+        assert_eq!(handler.crdt.get_value(&y.get_hash()), Some(&"y"));
+
+        // references must be Creates in the causal past
+        let heads = handler.hash_graph.get_heads().to_vec();
+        let valid = Node::new(heads.clone(), BFTTreeOp::Move(y.get_hash(), None));
+        assert!(handler.crdt.is_sem_valid(&valid, &handler.hash_graph));
+        let concurrent = Node::new(vec![], BFTTreeOp::Move(y.get_hash(), None));
+        assert!(!handler.crdt.is_sem_valid(&concurrent, &handler.hash_graph));
+        let move_node = handler.handle_local_op(BFTTreeOp::Move(y.get_hash(), None));
+        let not_a_create = Node::new(handler.hash_graph.get_heads().to_vec(), BFTTreeOp::Create(Some(move_node.get_hash()), "z"));
+        assert!(!handler.crdt.is_sem_valid(&not_a_create, &handler.hash_graph));
+        let unknown = Node::new(heads, BFTTreeOp::Delete("unknown".to_string()));
+        assert!(!handler.crdt.is_sem_valid(&unknown, &handler.hash_graph));
+
+        // the tree must know the depth of every predecessor, e.g. not one of another CRDT
+        let foreign = Node::new(vec![x.get_hash(), "foreign".to_string()], BFTTreeOp::Create(None, "z"));
+        assert!(!handler.crdt.is_sem_valid(&foreign, &handler.hash_graph));
+    }
+
+    #[test]
+    fn test_bft_tree_prune_log() {
+        let mut a: Replica<BFTTreeOp<&str>, BFTTree<&str>> = Replica::new(BFTTree::new());
+        let mut b: Replica<BFTTreeOp<&str>, BFTTree<&str>> = Replica::new(BFTTree::new());
+        let op = a.crdt.create(None, "x").unwrap();
+        let x = a.handle_local_op(op);
+        b.handle_remote_node(x.clone());
+        let op = a.crdt.create(None, "y").unwrap();
+        let y = a.handle_local_op(op);
+        b.handle_remote_node(y.clone());
+
+        // only the entries up to the stable heads go; a concurrent move is still reordered
+        let op = a.crdt.move_node(x.get_hash(), Some(y.get_hash())).unwrap();
+        let a_move = a.handle_local_op(op);
+        assert_eq!(a.crdt.prune_log(&[x.get_hash()]), 1);
+        assert_eq!(a.crdt.prune_log(&[y.get_hash()]), 1);
+        assert_eq!(a.crdt.log.len(), 1);
+        let op = b.crdt.move_node(y.get_hash(), Some(x.get_hash())).unwrap();
+        let b_move = b.handle_local_op(op);
+        a.handle_remote_node(b_move);
+        b.handle_remote_node(a_move);
+        for id in [x.get_hash(), y.get_hash()] {
+            assert_eq!(a.crdt.get_parent(&id), b.crdt.get_parent(&id));
+        }
+        assert_eq!(a.crdt.get_children(None).len(), 1);
+        assert_eq!(a.crdt.prune_log(&["unknown".to_string()]), 0);
+
+        // a node as shallow as a pruned entry would have to be ordered before it, which is no
+        // longer possible
+        let shallow = Node::new(vec![], BFTTreeOp::Create(None, "z"));
+        assert!(b.crdt.is_sem_valid(&shallow, &b.hash_graph));
+        assert!(!a.crdt.is_sem_valid(&shallow, &a.hash_graph));
+        a.handle_remote_node(shallow.clone());
+        assert!(!a.hash_graph.contains(&shallow.get_hash()));
+        let deeper = Node::new(a.hash_graph.get_heads().to_vec(), BFTTreeOp::Create(None, "z"));
+        assert!(a.crdt.is_sem_valid(&deeper, &a.hash_graph));
+    }
+}
@@ -0,0 +1,276 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use crate::bft_crdts::bft_crdt::BFTCRDT;
+use crate::bft_crdts::hash_graph::Node;
+use crate::bft_crdts::replica::{Delivery, Replica, ReplicaPolicy};
+use crate::serialize::Serialize;
+
+//  A document store keeps many documents, each a `Replica` with its own HashGraph and CRDT, of
+//   which only some are loaded at a time. A document is loaded when it is first used, by
+//   replaying the nodes saved in a `DocumentStorage` into a new replica, so that stored nodes
+//   are validated like any other remote node. A document is saved when it is unloaded, either
+//   explicitly or by eviction, as its nodes in causal order followed by its pending nodes.
+//
+//   The memory of a loaded document is estimated from the sizes of its nodes, which the state
+//   of the CRDT is derived from. When the store holds more documents or more bytes than its
+//   policy allows, it unloads the least recently used documents, except the one just used.
+
+/// Where a `DocumentStore` keeps the nodes of unloaded documents.
+pub trait DocumentStorage<D, O: Serialize + Clone> {
+    /// Returns the saved nodes of `doc`, or `None` if it has never been saved.
+    fn load(&mut self, doc: &D) -> Option<Vec<Node<O>>>;
+
+    /// Replaces the saved nodes of `doc`.
+    fn save(&mut self, doc: &D, nodes: Vec<Node<O>>);
+}
+
+/// Keeps documents in memory, e.g. for tests or as a cache in front of other storage.
+pub struct MemoryStorage<D, O: Serialize + Clone> {
+    pub documents: HashMap<D, Vec<Node<O>>>,
+}
+
+impl<D, O: Serialize + Clone> MemoryStorage<D, O> {
+    pub fn new() -> Self {
+        MemoryStorage {
+            documents: HashMap::new(),
+        }
+    }
+}
+
+impl<D, O: Serialize + Clone> Default for MemoryStorage<D, O> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<D: Eq + Hash + Clone, O: Serialize + Clone> DocumentStorage<D, O> for MemoryStorage<D, O> {
+    fn load(&mut self, doc: &D) -> Option<Vec<Node<O>>> {
+        self.documents.get(doc).cloned()
+    }
+
+    fn save(&mut self, doc: &D, nodes: Vec<Node<O>>) {
+        self.documents.insert(doc.clone(), nodes);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DocumentStorePolicy {
+    /// The greatest number of loaded documents.
+    pub max_loaded: Option<usize>,
+    /// The greatest estimated memory of all loaded documents, in bytes.
+    pub max_bytes: Option<usize>,
+    /// The policy of the replica of every document.
+    pub replica: ReplicaPolicy,
+}
+
+struct LoadedDocument<O: Serialize + Clone, T: BFTCRDT<O>> {
+    replica: Replica<O, T>,
+    last_used: u64,
+    size_bytes: usize,
+}
+
+pub struct DocumentStore<D, O, T, S, F>
+where
+    O: Serialize + Clone,
+    T: BFTCRDT<O>,
+{
+    storage: S,
+    // creates the CRDT of a new replica
+    make_crdt: F,
+    policy: DocumentStorePolicy,
+    loaded: HashMap<D, LoadedDocument<O, T>>,
+    // the loaded documents by the time they were last used
+    lru: BTreeMap<u64, D>,
+    clock: u64,
+    size_bytes: usize,
+}
+
+impl<D, O, T, S, F> DocumentStore<D, O, T, S, F>
+where
+    D: Eq + Hash + Clone,
+    O: Serialize + Clone,
+    T: BFTCRDT<O>,
+    S: DocumentStorage<D, O>,
+    F: Fn() -> T,
+{
+    pub fn new(storage: S, make_crdt: F, policy: DocumentStorePolicy) -> Self {
+        DocumentStore {
+            storage,
+            make_crdt,
+            policy,
+            loaded: HashMap::new(),
+            lru: BTreeMap::new(),
+            clock: 0,
+            size_bytes: 0,
+        }
+    }
+
+    /// Generates an operation from the state of the CRDT of `doc`, see `ReplicaHandle::apply_local`,
+    /// and applies it as a local node. Returns `None` if `generate` does.
+    pub fn apply_local(&mut self, doc: &D, generate: impl FnOnce(&mut T) -> Option<O>) -> Option<Node<O>> {
+        let replica = self.replica_mut(doc);
+        let node = generate(&mut replica.crdt).map(|op| replica.handle_local_op(op));
+        self.update_size(doc);
+        node
+    }
+
+    /// Hands a node of `doc` received from another peer to the replica of the document.
+    pub fn deliver_remote(&mut self, doc: &D, node: Node<O>) -> Delivery {
+        let delivery = self.replica_mut(doc).handle_remote_node(node);
+        self.update_size(doc);
+        delivery
+    }
+
+    pub fn get(&mut self, doc: &D) -> &Replica<O, T> {
+        self.replica_mut(doc)
+    }
+
+    pub fn is_loaded(&self, doc: &D) -> bool {
+        self.loaded.contains_key(doc)
+    }
+
+    /// Returns the loaded documents, from the least to the most recently used.
+    pub fn loaded_documents(&self) -> Vec<D> {
+        self.lru.values().cloned().collect()
+    }
+
+    /// Returns the estimated memory of `doc`, or `None` if it is not loaded.
+    pub fn memory_usage(&self, doc: &D) -> Option<usize> {
+        self.loaded.get(doc).map(|loaded| loaded.size_bytes)
+    }
+
+    /// Returns the estimated memory of all loaded documents.
+    pub fn total_memory_usage(&self) -> usize {
+        self.size_bytes
+    }
+
+    /// Saves and unloads `doc`. Returns whether it was loaded.
+    pub fn unload(&mut self, doc: &D) -> bool {
+        let Some(loaded) = self.loaded.remove(doc) else { return false };
+        self.lru.remove(&loaded.last_used);
+        self.size_bytes -= loaded.size_bytes;
+        self.storage.save(doc, Self::nodes_to_save(&loaded.replica));
+        true
+    }
+
+    /// Saves all loaded documents without unloading them.
+    pub fn flush(&mut self) {
+        for (doc, loaded) in self.loaded.iter() {
+            self.storage.save(doc, Self::nodes_to_save(&loaded.replica));
+        }
+    }
+
+    pub fn storage(&self) -> &S {
+        &self.storage
+    }
+
+    fn nodes_to_save(replica: &Replica<O, T>) -> Vec<Node<O>> {
+        let mut nodes: Vec<Node<O>> = replica.hash_graph.nodes_in_causal_order().into_iter().cloned().collect();
+        nodes.extend(replica.pending_nodes.iter().cloned());
+        nodes
+    }
+
+    // loads `doc` if needed, marks it as used and evicts other documents if the store is full
+    fn replica_mut(&mut self, doc: &D) -> &mut Replica<O, T> {
+        self.clock += 1;
+        match self.loaded.get_mut(doc) {
+            Some(loaded) => {
+                self.lru.remove(&loaded.last_used);
+                loaded.last_used = self.clock;
+            }
+            None => {
+                let mut replica = Replica::with_policy((self.make_crdt)(), self.policy.replica.clone());
+                for node in self.storage.load(doc).unwrap_or_default() {
+                    replica.handle_remote_node(node);
+                }
+                let size_bytes = Self::size_of(&replica);
+                self.size_bytes += size_bytes;
+                self.loaded.insert(doc.clone(), LoadedDocument { replica, last_used: self.clock, size_bytes });
+            }
+        }
+        self.lru.insert(self.clock, doc.clone());
+        self.evict();
+        &mut self.loaded.get_mut(doc).unwrap().replica
+    }
+
+    fn size_of(replica: &Replica<O, T>) -> usize {
+        replica.hash_graph.size_bytes() + replica.pending_nodes.iter().map(|n| n.size_bytes()).sum::<usize>()
+    }
+
+    fn update_size(&mut self, doc: &D) {
+        let loaded = self.loaded.get_mut(doc).unwrap();
+        let size_bytes = Self::size_of(&loaded.replica);
+        self.size_bytes = self.size_bytes - loaded.size_bytes + size_bytes;
+        loaded.size_bytes = size_bytes;
+        self.evict();
+    }
+
+    // unloads the least recently used documents but the most recently used one while the
+    // store is over its limits
+    fn evict(&mut self) {
+        let is_full = |store: &Self| {
+            store.policy.max_loaded.is_some_and(|max| store.loaded.len() > max)
+                || store.policy.max_bytes.is_some_and(|max| store.size_bytes > max)
+        };
+        while self.loaded.len() > 1 && is_full(self) {
+            let Some((_, doc)) = self.lru.first_key_value() else { break };
+            let doc = doc.clone();
+            self.unload(&doc);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bft_crdts::bft_counter::BFTPNCounter;
+    use crate::bft_crdts::replica::Delivery;
+    use super::*;
+
+    #[test]
+    fn test_document_store_lru() {
+        let policy = DocumentStorePolicy { max_loaded: Some(2), ..Default::default() };
+        let mut store = DocumentStore::new(MemoryStorage::new(), BFTPNCounter::new, policy);
+        let mut nodes = vec![];
+        for (doc, n) in [("a", 1), ("b", 2), ("a", 3), ("c", 4)] {
+            nodes.push(store.apply_local(&doc, |counter| Some(counter.increment("x", n))).unwrap());
+        }
+        // "b" was used least recently when "c" was loaded
+        assert_eq!(store.loaded_documents(), vec!["a", "c"]);
+        assert!(store.storage().documents.contains_key("b"));
+        assert_eq!(store.get(&"b").crdt.get_value(), 2);
+        assert_eq!(store.loaded_documents(), vec!["c", "b"]);
+        assert_eq!(store.get(&"a").crdt.get_value(), 4);
+        let usage = store.memory_usage(&"a").unwrap();
+        assert!(usage > 0);
+        assert_eq!(store.total_memory_usage(), usage + store.memory_usage(&"b").unwrap());
+        assert_eq!(store.memory_usage(&"c"), None);
+
+        // remote nodes are routed by document and survive unloading while still pending
+        let mut remote = DocumentStore::new(MemoryStorage::new(), BFTPNCounter::new, DocumentStorePolicy::default());
+        assert_eq!(remote.deliver_remote(&"a", nodes[2].clone()), Delivery::Buffered);
+        assert!(remote.unload(&"a"));
+        assert_eq!(remote.deliver_remote(&"a", nodes[0].clone()), Delivery::Applied);
+        assert_eq!(remote.get(&"a").crdt.get_value(), 4);
+        assert_eq!(remote.get(&"b").crdt.get_value(), 0);
+    }
+
+    #[test]
+    fn test_document_store_memory_limit() {
+        let policy = DocumentStorePolicy { max_bytes: Some(200), ..Default::default() };
+        let mut store = DocumentStore::new(MemoryStorage::new(), BFTPNCounter::new, policy);
+        for doc in 0..5u64 {
+            store.apply_local(&doc, |counter| Some(counter.increment("x", 1)));
+        }
+        let per_document = store.memory_usage(&4).unwrap();
+        assert_eq!(store.loaded_documents().len(), 200 / per_document);
+        assert!(store.total_memory_usage() <= 200);
+        // the document in use stays loaded even if it is over the limit on its own
+        for _ in 0..20 {
+            store.apply_local(&0, |counter| Some(counter.increment("x", 1)));
+        }
+        assert_eq!(store.loaded_documents(), vec![0]);
+        assert!(store.total_memory_usage() > 200);
+        store.flush();
+        assert_eq!(store.storage().documents[&0].len(), 21);
+    }
+}
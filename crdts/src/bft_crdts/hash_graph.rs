@@ -1,4 +1,5 @@
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
 use std::fmt::{Debug, Display};
 use sha2::{Digest, Sha256, Sha512};
 use hex;
@@ -11,6 +12,8 @@ pub type HashType = String;
 pub struct Node<T: Serialize + Clone> {
     pub predecessors: Vec<HashType>,
     pub value: T,
+    // the hash of the node this one is a projection of, see `Node::project`
+    identity: Option<HashType>,
 }
 
 impl <T: Serialize + Clone + Display> Display for Node<T> {
@@ -29,7 +32,35 @@ impl <T: Serialize + Clone> Debug for Node<T> {
 }
 
 impl <T: Serialize + Clone> Node<T> {
+    pub fn new(predecessors: Vec<HashType>, value: T) -> Self {
+        Node {
+            predecessors,
+            value,
+            identity: None,
+        }
+    }
+
+    /// Returns a node with the same predecessors and hash as this one that carries `value`,
+    /// e.g. the operation of a nested CRDT that this node's operation wraps.
+    pub fn project<U: Serialize + Clone>(&self, value: U) -> Node<U> {
+        Node {
+            predecessors: self.predecessors.clone(),
+            value,
+            identity: Some(self.get_hash()),
+        }
+    }
+
+    /// Returns an estimate of the memory taken by the node: its hash, the hashes of its
+    /// predecessors and the serialized value.
+    pub fn size_bytes(&self) -> usize {
+        let hash_size = 64;
+        hash_size * (1 + self.predecessors.len()) + self.value.to_bytes().len()
+    }
+
     pub fn get_hash(&self) -> HashType {
+        if let Some(hash) = &self.identity {
+            return hash.clone();
+        }
         let mut hasher = Sha256::new();
         let mut sorted_preds = self.predecessors.clone();
         sorted_preds.sort();
@@ -43,9 +74,27 @@ impl <T: Serialize + Clone> Node<T> {
     }
 }
 
+/// The parts of a hash graph that semantic validity checks look at. Besides `HashGraph`
+/// itself, the operations of a nested CRDT are checked against a view that projects the
+/// shared graph onto the nodes carrying them.
+pub trait GraphView<T: Serialize + Clone> {
+    /// Returns the node, borrowed from the graph where possible; a projected view has to
+    /// build the projected node.
+    fn get_node(&self, hash: &HashType) -> Option<Cow<'_, Node<T>>>;
+
+    fn contains(&self, hash: &HashType) -> bool;
+
+    fn is_ancestor(&self, ancestor: &HashType, descendant: &Node<T>) -> bool;
+
+    /// Returns the hashes of the given nodes and all of their ancestors that are in the view.
+    fn causal_past(&self, heads: &[HashType]) -> HashSet<HashType>;
+}
+
 pub struct HashGraph<T: Serialize + Clone> {
     pub nodes: HashMap<HashType, Node<T>>,
     heads: Vec<HashType>,
+    // the sum of `Node::size_bytes` over all nodes
+    size_bytes: usize,
 }
 
 impl<T: Serialize + Clone> HashGraph<T> {
@@ -53,6 +102,7 @@ impl<T: Serialize + Clone> HashGraph<T> {
         HashGraph {
             nodes: HashMap::new(),
             heads: vec![],
+            size_bytes: 0,
         }
     }
     
@@ -120,25 +170,120 @@ impl<T: Serialize + Clone> HashGraph<T> {
         self.heads.push(hash.clone());
         
         // Add node to graph
-        self.nodes.insert(hash, node);
+        self.size_bytes += node.size_bytes();
+        if let Some(old) = self.nodes.insert(hash, node) {
+            self.size_bytes -= old.size_bytes();
+        }
     }
     
     pub fn add_value_with_head_preds(&mut self, value: T) -> Option<HashType> {
-        let node = Node {
-            predecessors: self.heads.clone(),
-            value,
-        };
+        let node = Node::new(self.heads.clone(), value);
         
         let hash = node.get_hash();
-        self.nodes.insert(hash.clone(), node);
+        self.size_bytes += node.size_bytes();
+        if let Some(old) = self.nodes.insert(hash.clone(), node) {
+            self.size_bytes -= old.size_bytes();
+        }
         self.heads = vec![hash.clone()];
         Some(hash)
     }
 
+    /// Returns an estimate of the memory taken by the nodes of the graph.
+    pub fn size_bytes(&self) -> usize {
+        self.size_bytes
+    }
+
+    /// Returns all nodes such that every node comes after its predecessors. Concurrent nodes
+    /// are ordered by hash, so the order is the same for all graphs with the same nodes.
+    pub fn nodes_in_causal_order(&self) -> Vec<&Node<T>> {
+        self.hashes_in_causal_order().into_iter().map(|hash| &self.nodes[hash]).collect()
+    }
+
+    /// Returns the hashes of all nodes in the order of `nodes_in_causal_order`.
+    pub fn hashes_in_causal_order(&self) -> Vec<&HashType> {
+        let mut waiting_for: HashMap<&HashType, usize> = HashMap::new();
+        let mut successors: HashMap<&HashType, Vec<&HashType>> = HashMap::new();
+        for (hash, node) in self.nodes.iter() {
+            waiting_for.insert(hash, node.predecessors.len());
+            for pred in node.predecessors.iter() {
+                successors.entry(pred).or_default().push(hash);
+            }
+        }
+        let mut ready: BTreeSet<&HashType> = waiting_for.iter().filter(|(_, n)| **n == 0).map(|(h, _)| *h).collect();
+        let mut order = Vec::with_capacity(self.nodes.len());
+        while let Some(hash) = ready.pop_first() {
+            order.push(hash);
+            for succ in successors.get(hash).into_iter().flatten() {
+                let n = waiting_for.get_mut(succ).unwrap();
+                *n -= 1;
+                if *n == 0 {
+                    ready.insert(succ);
+                }
+            }
+        }
+        order
+    }
+
     pub fn get_node(&self, hash: &HashType) -> Option<&Node<T>> {
         self.nodes.get(hash)
     }
+
+    pub fn contains(&self, hash: &HashType) -> bool {
+        self.nodes.contains_key(hash)
+    }
     
+    pub fn get_heads(&self) -> &[HashType] {
+        &self.heads
+    }
+
+    /// Returns the hashes of the given nodes and all of their ancestors. When `heads` are
+    /// known to be in the causal past of every replica, this is the set of causally stable
+    /// nodes.
+    pub fn causal_past(&self, heads: &[HashType]) -> HashSet<HashType> {
+        let mut visited = HashSet::new();
+        let mut stack: Vec<&HashType> = heads.iter().collect();
+        while let Some(hash) = stack.pop() {
+            if visited.contains(hash) {
+                continue;
+            }
+            if let Some(node) = self.get_node(hash) {
+                visited.insert(hash.clone());
+                stack.extend(node.predecessors.iter());
+            }
+        }
+        visited
+    }
+
+    /// Returns the hashes of `causal_past(heads)` in an order in which every node comes after
+    /// its predecessors. Only the causal past is visited.
+    pub fn causal_past_in_order(&self, heads: &[HashType]) -> Vec<&HashType> {
+        let mut order = vec![];
+        let mut visited = HashSet::new();
+        // each node is pushed again once its predecessors are on the stack, to be emitted after them
+        let mut stack: Vec<(&HashType, bool)> = heads.iter()
+            .filter_map(|head| self.nodes.get_key_value(head))
+            .map(|(hash, _)| (hash, false))
+            .collect();
+        while let Some((hash, expanded)) = stack.pop() {
+            if expanded {
+                order.push(hash);
+                continue;
+            }
+            if !visited.insert(hash) {
+                continue;
+            }
+            stack.push((hash, true));
+            for pred in self.nodes[hash].predecessors.iter() {
+                if let Some((pred, _)) = self.nodes.get_key_value(pred) {
+                    if !visited.contains(pred) {
+                        stack.push((pred, false));
+                    }
+                }
+            }
+        }
+        order
+    }
+
     pub fn is_ancestor(&self, ancestor: &HashType, descendant: &Node<T>) -> bool {
         self.is_ancestor_bfs(ancestor, descendant)
     }
@@ -248,6 +393,65 @@ impl<T: Serialize + Clone> HashGraph<T> {
     }
 }
 
+impl<T: Serialize + Clone> GraphView<T> for HashGraph<T> {
+    fn get_node(&self, hash: &HashType) -> Option<Cow<'_, Node<T>>> {
+        self.nodes.get(hash).map(Cow::Borrowed)
+    }
+
+    fn contains(&self, hash: &HashType) -> bool {
+        self.nodes.contains_key(hash)
+    }
+
+    fn is_ancestor(&self, ancestor: &HashType, descendant: &Node<T>) -> bool {
+        HashGraph::is_ancestor(self, ancestor, descendant)
+    }
+
+    fn causal_past(&self, heads: &[HashType]) -> HashSet<HashType> {
+        HashGraph::causal_past(self, heads)
+    }
+}
+
+/// The nodes of a graph whose operations `project` to operations of a nested CRDT, seen as
+/// nodes of that CRDT, e.g. the nodes of one key of a map. Causality is that of the whole
+/// graph; `embed` turns a nested operation back into an operation of the graph.
+pub struct ProjectedView<'a, T: Serialize + Clone, P, E> {
+    graph: &'a dyn GraphView<T>,
+    project: P,
+    embed: E,
+}
+
+impl<'a, T: Serialize + Clone, P, E> ProjectedView<'a, T, P, E> {
+    pub fn new(graph: &'a dyn GraphView<T>, project: P, embed: E) -> Self {
+        ProjectedView { graph, project, embed }
+    }
+}
+
+impl<T, U, P, E> GraphView<U> for ProjectedView<'_, T, P, E>
+where
+    T: Serialize + Clone,
+    U: Serialize + Clone,
+    P: Fn(&T) -> Option<U>,
+    E: Fn(U) -> T,
+{
+    fn get_node(&self, hash: &HashType) -> Option<Cow<'_, Node<U>>> {
+        let node = self.graph.get_node(hash)?;
+        (self.project)(&node.value).map(|op| Cow::Owned(node.project(op)))
+    }
+
+    fn contains(&self, hash: &HashType) -> bool {
+        self.graph.get_node(hash).is_some_and(|node| (self.project)(&node.value).is_some())
+    }
+
+    fn is_ancestor(&self, ancestor: &HashType, descendant: &Node<U>) -> bool {
+        let descendant = descendant.project((self.embed)(descendant.value.clone()));
+        self.graph.is_ancestor(ancestor, &descendant)
+    }
+
+    fn causal_past(&self, heads: &[HashType]) -> HashSet<HashType> {
+        self.graph.causal_past(heads).into_iter().filter(|h| self.contains(h)).collect()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -284,5 +488,47 @@ mod tests {
         assert_eq!(graph.get_node(&expected_hash2).unwrap().value, b"test2");
         assert_eq!(graph.get_node(&expected_hash2).unwrap().predecessors, vec![expected_hash1]);
     }
-}
 
+    #[test]
+    fn test_causal_past() {
+        let mut graph: HashGraph<Vec<u8>> = HashGraph::new();
+        let hash1 = graph.add_value_with_head_preds(b"test1".to_vec()).unwrap();
+        let hash2 = graph.add_value_with_head_preds(b"test2".to_vec()).unwrap();
+        let concurrent = Node::new(vec![hash1.clone()], b"test3".to_vec());
+        let hash3 = concurrent.get_hash();
+        graph.add_node(concurrent);
+
+        assert_eq!(graph.get_heads().len(), 2);
+        assert_eq!(graph.causal_past(std::slice::from_ref(&hash2)), HashSet::from([hash1.clone(), hash2.clone()]));
+        assert_eq!(graph.causal_past(&[hash2.clone(), hash3.clone()]).len(), 3);
+        assert_eq!(graph.causal_past(&["unknown".to_string()]), HashSet::new());
+
+        let last = Node::new(vec![hash2.clone(), hash3.clone()], b"test4".to_vec());
+        graph.add_node(last.clone());
+        assert_eq!(graph.causal_past_in_order(std::slice::from_ref(&hash2)), vec![&hash1, &hash2]);
+        let order = graph.causal_past_in_order(&[last.get_hash()]);
+        assert_eq!(order.len(), 4);
+        assert_eq!((order[0], order[3]), (&hash1, &last.get_hash()));
+    }
+
+    #[test]
+    fn test_nodes_in_causal_order() {
+        let mut graph: HashGraph<Vec<u8>> = HashGraph::new();
+        let hash1 = graph.add_value_with_head_preds(b"test1".to_vec()).unwrap();
+        let hash2 = graph.add_value_with_head_preds(b"test2".to_vec()).unwrap();
+        let concurrent = Node::new(vec![hash1.clone()], b"test3".to_vec());
+        let hash3 = concurrent.get_hash();
+        graph.add_node(concurrent.clone());
+        let last = Node::new(vec![hash2.clone(), hash3.clone()], b"test4".to_vec());
+        graph.add_node(last.clone());
+
+        let order: Vec<HashType> = graph.nodes_in_causal_order().iter().map(|n| n.get_hash()).collect();
+        let (first, second) = if hash2 < hash3 { (hash2, hash3) } else { (hash3, hash2) };
+        assert_eq!(order, vec![hash1, first, second, last.get_hash()]);
+        // adding a node twice does not count it twice
+        let size = graph.size_bytes();
+        graph.add_node(concurrent);
+        assert_eq!(graph.size_bytes(), size);
+        assert_eq!(size, graph.nodes.values().map(|n| n.size_bytes()).sum::<usize>());
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::{HashMap, VecDeque};
+use crate::bft_crdts::bft_crdt::BFTCRDT;
+use crate::bft_crdts::hash_graph::{HashGraph, HashType};
+use crate::serialize::Serialize;
+
+//  The history of a BFT CRDT is the states it had at the frontiers of its HashGraph. The state
+//   at a set of heads is built by interpreting the heads and their ancestors, in causal order,
+//   in a fresh CRDT; these nodes have been validated when they were added to the graph, so they
+//   are only interpreted. The ancestors of a node never change, so the state at a frontier does
+//   not either, and states are cached by their heads for as long as the cache has room, even
+//   while the graph grows.
+
+pub struct History<T, F> {
+    // creates the fresh CRDT that nodes are interpreted in
+    make_crdt: F,
+    capacity: usize,
+    cache: HashMap<Vec<HashType>, T>,
+    // the cached frontiers, oldest first
+    cached: VecDeque<Vec<HashType>>,
+    // the state last built while there is no cache, only kept to be returned
+    uncached: Option<T>,
+}
+
+impl<T, F: Fn() -> T> History<T, F> {
+    /// Creates a history that caches the states at up to `capacity` frontiers. With a capacity
+    /// of 0, every state is built anew.
+    pub fn new(make_crdt: F, capacity: usize) -> Self {
+        History {
+            make_crdt,
+            capacity,
+            cache: HashMap::new(),
+            cached: VecDeque::new(),
+            uncached: None,
+        }
+    }
+
+    /// Returns the state of the CRDT after the nodes `heads` and all their ancestors, e.g.
+    /// `history.state_at(&replica.hash_graph, &old_heads)?.get_list()`. Returns `None` if one
+    /// of the heads is not in the graph.
+    pub fn state_at<O>(&mut self, hash_graph: &HashGraph<O>, heads: &[HashType]) -> Option<&T>
+    where
+        O: Serialize + Clone,
+        T: BFTCRDT<O>,
+    {
+        let mut key = heads.to_vec();
+        key.sort();
+        key.dedup();
+        if !self.cache.contains_key(&key) {
+            if key.iter().any(|head| !hash_graph.contains(head)) {
+                return None;
+            }
+            let mut crdt = (self.make_crdt)();
+            for hash in hash_graph.causal_past_in_order(&key) {
+                crdt.interpret_node(&hash_graph.nodes[hash]);
+            }
+            if self.capacity == 0 {
+                return Some(self.uncached.insert(crdt));
+            }
+            if self.cached.len() >= self.capacity {
+                if let Some(oldest) = self.cached.pop_front() {
+                    self.cache.remove(&oldest);
+                }
+            }
+            self.cached.push_back(key.clone());
+            self.cache.insert(key.clone(), crdt);
+        }
+        self.cache.get(&key)
+    }
+
+    /// Returns the number of cached states.
+    pub fn cached_len(&self) -> usize {
+        self.cache.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bft_crdts::bft_orset::BFTORSet;
+    use crate::bft_crdts::bft_rga::BFTRGA;
+    use crate::bft_crdts::replica::Replica;
+    use super::*;
+
+    #[test]
+    fn test_history_at_past_heads() {
+        let mut a = Replica::new(BFTRGA::new(1));
+        let mut versions = vec![];
+        for (i, c) in "abc".chars().enumerate() {
+            let op = a.crdt.insert_auto(i, c).unwrap();
+            a.handle_local_op(op);
+            versions.push((a.hash_graph.get_heads().to_vec(), a.crdt.get_list()));
+        }
+        let op = a.crdt.delete(0).unwrap();
+        a.handle_local_op(op);
+        versions.push((a.hash_graph.get_heads().to_vec(), a.crdt.get_list()));
+
+        // a concurrent edit makes a frontier with two heads
+        let mut b = Replica::new(BFTRGA::new(2));
+        for node in a.hash_graph.nodes_in_causal_order().into_iter().take(2) {
+            b.handle_remote_node(node.clone());
+        }
+        let op = b.crdt.insert_auto(2, 'x').unwrap();
+        let concurrent = b.handle_local_op(op);
+        a.handle_remote_node(concurrent);
+        versions.push((a.hash_graph.get_heads().to_vec(), a.crdt.get_list()));
+        assert_eq!(a.hash_graph.get_heads().len(), 2);
+
+        let mut history = History::new(BFTRGA::default, 2);
+        for (heads, list) in versions.iter() {
+            assert_eq!(&history.state_at(&a.hash_graph, heads).unwrap().get_list(), list);
+        }
+        assert_eq!(history.state_at(&a.hash_graph, b.hash_graph.get_heads()).unwrap().get_list(), vec!['a', 'b', 'x']);
+        assert_eq!(history.cached_len(), 2);
+        assert!(history.state_at(&a.hash_graph, &["unknown".to_string()]).is_none());
+
+        // without a cache, every state is still built correctly but none is kept
+        let mut history = History::new(BFTRGA::default, 0);
+        for (heads, list) in versions.iter() {
+            assert_eq!(&history.state_at(&a.hash_graph, heads).unwrap().get_list(), list);
+        }
+        assert_eq!(history.cached_len(), 0);
+
+        let mut orset = Replica::new(BFTORSet::new());
+        let op = orset.crdt.add("x");
+        orset.handle_local_op(op);
+        let before_remove = orset.hash_graph.get_heads().to_vec();
+        let op = orset.crdt.remove_elem("x");
+        orset.handle_local_op(op);
+        let mut history = History::new(BFTORSet::new, 4);
+        assert!(history.state_at(&orset.hash_graph, &before_remove).unwrap().is_in("x"));
+        assert!(history.state_at(&orset.hash_graph, &[]).unwrap().get_set().is_empty());
+    }
+}
@@ -1,4 +1,19 @@
 pub mod bft_orset;
 pub mod bft_crdt;
 pub mod hash_graph;
-pub mod bft_rga;
\ No newline at end of file
+pub mod bft_rga;
+pub mod bft_fugue;
+pub mod bft_register;
+pub mod bft_counter;
+pub mod bft_ormap;
+pub mod bft_json;
+pub mod bft_tree;
+pub mod bft_richtext;
+pub mod bft_set;
+pub mod bft_graph;
+pub mod bft_product;
+pub mod bft_adapter;
+pub mod replica;
+pub mod replica_handle;
+pub mod document_store;
+pub mod history;
@@ -0,0 +1,595 @@
+use std::cmp::min;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::time::SystemTime;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use tracing::trace;
+use crate::bft_crdts::bft_crdt::BFTCRDT;
+use crate::bft_crdts::hash_graph::{HashGraph, HashType, Node};
+use crate::serialize::Serialize;
+
+//  A replica holds a BFT CRDT together with its HashGraph and is the one place where nodes are
+//   validated and applied: local operations become nodes on top of the current heads, and a
+//   remote node is applied if it is structurally valid (all its predecessors are known) and
+//   semantically valid. What happens to the other remote nodes is set by a `ReplicaPolicy`.
+//   Semantic validity only depends on the causal past of a node, so an invalid node whose
+//   predecessors are known is rejected for good, while an orphan may become valid once its
+//   predecessors arrive and can be buffered until then.
+//
+//  The replica keeps a receipt of when and how each node it applied was accepted, from which
+//   `log` builds a change log in the deterministic causal order of the HashGraph. That order is
+//   computed once per `LogCursor`, which the log is paged through.
+
+/// What a replica does with a remote node whose predecessors it does not have (yet).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrphanPolicy {
+    /// Keeps the node and applies it once all its predecessors have been applied.
+    Buffer,
+    /// Rejects the node, e.g. when the transport delivers nodes in causal order.
+    Drop,
+}
+
+/// What a replica does with a remote node that is already in its HashGraph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Skips the node silently, e.g. for transports that may deliver a node more than once.
+    Ignore,
+    /// Skips the node and reports it as rejected, e.g. to notice peers that resend nodes.
+    Reject,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplicaPolicy {
+    pub orphans: OrphanPolicy,
+    pub duplicates: DuplicatePolicy,
+    /// The greatest number of buffered orphans; further orphans are rejected.
+    pub max_pending: Option<usize>,
+    /// Whether subscribers are also told about the changes made by local operations.
+    pub notify_local: bool,
+}
+
+impl Default for ReplicaPolicy {
+    fn default() -> Self {
+        ReplicaPolicy {
+            orphans: OrphanPolicy::Buffer,
+            duplicates: DuplicatePolicy::Ignore,
+            max_pending: None,
+            notify_local: false,
+        }
+    }
+}
+
+/// The outcome of merging a branch into a replica, see `Replica::merge`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MergeReport {
+    /// The nodes the branch added to the replica, in causal order.
+    pub added: Vec<HashType>,
+    /// The nodes of the branch that were not applied, e.g. because they are not valid or
+    /// follow such a node.
+    pub not_applied: Vec<HashType>,
+    /// The buffered nodes that were applied because the branch had their missing
+    /// predecessors, in the order they were buffered.
+    pub released: Vec<HashType>,
+}
+
+/// How a node that a replica applied was accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// The node holds a local operation, which is not validated.
+    Local,
+    /// The remote node was valid when it arrived.
+    Valid,
+    /// The remote node was buffered until its predecessors arrived and then found valid.
+    ValidAfterBuffering,
+}
+
+/// When and how a replica applied a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Receipt {
+    pub received_at: SystemTime,
+    pub verdict: Verdict,
+}
+
+/// A node in the change log of a replica, see `Replica::log`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry<A> {
+    pub hash: HashType,
+    /// The Display form of the operation.
+    pub operation: String,
+    pub author: A,
+    /// `None` for nodes the replica did not apply itself, e.g. those it was forked with.
+    pub receipt: Option<Receipt>,
+}
+
+/// A position in the change log of a replica, see `Replica::log_cursor`. The cursor holds the
+/// causal order of the nodes the replica had when it was created; later nodes are not in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogCursor {
+    order: Vec<HashType>,
+    // the index into `order` of the next node to look at
+    next: usize,
+}
+
+/// Why a replica did not apply a remote node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejection {
+    Orphan,
+    Invalid,
+    Duplicate,
+    BufferFull,
+}
+
+/// What a replica did with a remote node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    /// The node was applied, possibly together with buffered nodes that it released.
+    Applied,
+    Buffered,
+    Ignored,
+    Rejected(Rejection),
+}
+
+type Subscriber<E> = Box<dyn FnMut(&E)>;
+type RejectionHook<O> = Box<dyn FnMut(&Node<O>, Rejection)>;
+
+pub struct Replica<O: Serialize + Clone, T: BFTCRDT<O>> {
+    pub crdt: T,
+    pub hash_graph: HashGraph<O>,
+    pub pending_nodes: Vec<Node<O>>,
+    pub policy: ReplicaPolicy,
+    receipts: HashMap<HashType, Receipt>,
+    subscribers: Vec<Subscriber<T::Event>>,
+    rejection_hooks: Vec<RejectionHook<O>>,
+}
+
+impl<O: Serialize + Clone, T: BFTCRDT<O>> Replica<O, T> {
+    pub fn new(crdt: T) -> Self {
+        Self::with_policy(crdt, ReplicaPolicy::default())
+    }
+
+    pub fn with_policy(crdt: T, policy: ReplicaPolicy) -> Self {
+        Replica {
+            crdt,
+            hash_graph: HashGraph::new(),
+            pending_nodes: vec![],
+            policy,
+            receipts: HashMap::new(),
+            subscribers: vec![],
+            rejection_hooks: vec![],
+        }
+    }
+
+    /// Registers a callback that receives the change events of every remote node applied by
+    /// the replica, including nodes released from the pending queue. Changes made by local
+    /// operations are only reported if the policy asks for it.
+    pub fn subscribe(&mut self, subscriber: impl FnMut(&T::Event) + 'static) {
+        self.subscribers.push(Box::new(subscriber));
+    }
+
+    /// Registers a callback that receives every remote node the replica rejects, with the reason.
+    pub fn on_rejection(&mut self, hook: impl FnMut(&Node<O>, Rejection) + 'static) {
+        self.rejection_hooks.push(Box::new(hook));
+    }
+
+    pub fn handle_local_op(&mut self, op: O) -> Node<O> {
+        let h = self.hash_graph.add_value_with_head_preds(op);
+        if h.is_none() {
+            // this should never happen
+            panic!("Failed to add local node");
+        }
+        let node = self.hash_graph.get_node(&h.unwrap()).unwrap().clone();
+        self.record(node.get_hash(), Verdict::Local);
+        let events = self.crdt.interpret_node(&node);
+        if self.policy.notify_local {
+            self.notify(&events);
+        }
+        node
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn handle_remote_node(&mut self, remote_node: Node<O>) -> Delivery {
+        if self.hash_graph.contains(&remote_node.get_hash()) {
+            trace!("Node is a duplicate");
+            return match self.policy.duplicates {
+                DuplicatePolicy::Ignore => Delivery::Ignored,
+                DuplicatePolicy::Reject => self.reject(&remote_node, Rejection::Duplicate),
+            };
+        }
+        if !self.hash_graph.is_structurally_valid(&remote_node) {
+            trace!("Node is not structurally valid");
+            return match self.policy.orphans {
+                OrphanPolicy::Drop => self.reject(&remote_node, Rejection::Orphan),
+                OrphanPolicy::Buffer if self.policy.max_pending.is_some_and(|max| self.pending_nodes.len() >= max) => {
+                    self.reject(&remote_node, Rejection::BufferFull)
+                }
+                OrphanPolicy::Buffer => {
+                    self.pending_nodes.push(remote_node);
+                    Delivery::Buffered
+                }
+            };
+        }
+        if !self.crdt.is_sem_valid(&remote_node, &self.hash_graph) {
+            trace!("Node is not semantically valid");
+            return self.reject(&remote_node, Rejection::Invalid);
+        }
+        self.apply_remote_node(remote_node, Verdict::Valid);
+        self.handle_pending_nodes();
+        Delivery::Applied
+    }
+
+    /// Applies the buffered nodes whose predecessors are now all known, and rejects those of
+    /// them that are not semantically valid.
+    pub fn handle_pending_nodes(&mut self) {
+        loop {
+            let (ready, pending): (Vec<Node<O>>, Vec<Node<O>>) = std::mem::take(&mut self.pending_nodes)
+                .into_iter()
+                .partition(|node| self.hash_graph.is_structurally_valid(node));
+            self.pending_nodes = pending;
+            if ready.is_empty() {
+                break;
+            }
+            for node in ready {
+                if self.hash_graph.contains(&node.get_hash()) {
+                    // buffered more than once
+                    continue;
+                }
+                if self.crdt.is_sem_valid(&node, &self.hash_graph) {
+                    self.apply_remote_node(node, Verdict::ValidAfterBuffering);
+                } else {
+                    self.reject(&node, Rejection::Invalid);
+                }
+            }
+        }
+    }
+
+    /// Creates an independent replica, with the same policy, that has the nodes `heads` and
+    /// their ancestors interpreted in `crdt`, e.g. a CRDT for another replica number. Edits
+    /// on the fork and on this replica can be exchanged later with `merge`. Returns `None` if
+    /// one of the heads is not in the HashGraph.
+    pub fn fork(&self, heads: &[HashType], crdt: T) -> Option<Replica<O, T>> {
+        if heads.iter().any(|head| !self.hash_graph.contains(head)) {
+            return None;
+        }
+        let mut fork = Replica::with_policy(crdt, self.policy.clone());
+        for hash in self.hash_graph.causal_past_in_order(heads) {
+            let node = &self.hash_graph.nodes[hash];
+            fork.crdt.interpret_node(node);
+            fork.hash_graph.add_node(node.clone());
+            if let Some(receipt) = self.receipts.get(hash) {
+                fork.receipts.insert(hash.clone(), *receipt);
+            }
+        }
+        Some(fork)
+    }
+
+    /// Returns the nodes of this replica that are not in `other`, in causal order, e.g. the
+    /// nodes to send to a peer or to another branch.
+    pub fn missing_in(&self, other: &HashGraph<O>) -> Vec<Node<O>> {
+        self.hash_graph.hashes_in_causal_order().into_iter()
+            .filter(|hash| !other.contains(hash))
+            .map(|hash| self.hash_graph.nodes[hash].clone())
+            .collect()
+    }
+
+    /// Applies the nodes of `branch`, e.g. the HashGraph of a fork, that this replica does not
+    /// have, as remote nodes, so that concurrent edits are resolved by the CRDT, and reports
+    /// which nodes the merge added. Merging in both directions makes the replicas equal.
+    pub fn merge(&mut self, branch: &HashGraph<O>) -> MergeReport {
+        let mut report = MergeReport::default();
+        let buffered: Vec<HashType> = self.pending_nodes.iter().map(|node| node.get_hash()).collect();
+        for hash in branch.hashes_in_causal_order() {
+            if self.hash_graph.contains(hash) {
+                continue;
+            }
+            match self.handle_remote_node(branch.nodes[hash].clone()) {
+                Delivery::Applied => report.added.push(hash.clone()),
+                _ => report.not_applied.push(hash.clone()),
+            }
+        }
+        let added: HashSet<&HashType> = report.added.iter().collect();
+        let released = buffered.into_iter().filter(|hash| self.hash_graph.contains(hash) && !added.contains(hash)).collect();
+        report.released = released;
+        report
+    }
+
+    /// Returns when and how the node `hash` was applied, or `None` if the replica did not
+    /// apply it itself.
+    pub fn receipt(&self, hash: &HashType) -> Option<&Receipt> {
+        self.receipts.get(hash)
+    }
+
+    fn record(&mut self, hash: HashType, verdict: Verdict) {
+        self.receipts.insert(hash, Receipt { received_at: SystemTime::now(), verdict });
+    }
+
+    fn apply_remote_node(&mut self, node: Node<O>, verdict: Verdict) {
+        trace!("Interpreting node");
+        self.record(node.get_hash(), verdict);
+        let events = self.crdt.interpret_node(&node);
+        self.hash_graph.add_node(node);
+        self.notify(&events);
+    }
+
+    fn notify(&mut self, events: &[T::Event]) {
+        for event in events.iter() {
+            for subscriber in self.subscribers.iter_mut() {
+                subscriber(event);
+            }
+        }
+    }
+
+    fn reject(&mut self, node: &Node<O>, rejection: Rejection) -> Delivery {
+        for hook in self.rejection_hooks.iter_mut() {
+            hook(node, rejection);
+        }
+        Delivery::Rejected(rejection)
+    }
+
+    /// IMPORTANT: used only for testing
+    /// Builds a node for `op` whose predecessors are up to 9 random nodes of the HashGraph,
+    /// so it is structurally valid but not necessarily semantically valid.
+    pub fn random_struct_valid_node<R: Rng>(&self, rng: &mut R, op: O) -> Node<O> {
+        let num_preds = (rng.next_u32() % 10) as usize;
+        let num_preds = min(num_preds, self.hash_graph.nodes.len());
+        let mut preds = self.hash_graph.nodes.keys().cloned().collect::<Vec<HashType>>();
+        preds.shuffle(rng);
+        preds.truncate(num_preds);
+        Node::new(preds, op)
+    }
+
+    /// IMPORTANT: used only for testing
+    /// Subscribes to the change events of the replica and returns the events recorded so far.
+    #[cfg(test)]
+    pub(crate) fn record_events(&mut self) -> std::rc::Rc<std::cell::RefCell<Vec<T::Event>>>
+    where
+        T::Event: Clone + 'static,
+    {
+        let events = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+        let sink = events.clone();
+        self.subscribe(move |event: &T::Event| sink.borrow_mut().push(event.clone()));
+        events
+    }
+
+    /// IMPORTANT: used only for testing
+    /// Builds a node for `op` with up to 9 random hashes as predecessors, which almost never
+    /// make it structurally valid.
+    pub fn random_node<R: Rng>(&self, rng: &mut R, op: O) -> Node<O> {
+        let num_preds = (rng.next_u32() % 10) as usize;
+        let mut preds: Vec<HashType> = vec![];
+        for _ in 0..num_preds {
+            let mut hash = [0u8; 32];
+            rng.fill(&mut hash);
+            preds.push(hex::encode(hash));
+        }
+        Node::new(preds, op)
+    }
+}
+
+impl<O: Serialize + Clone + Display, T: BFTCRDT<O>> Replica<O, T> {
+    /// Returns a cursor at the start of the change log, which holds the nodes of the replica
+    /// in causal order. Concurrent nodes are ordered by hash, so replicas with the same nodes
+    /// have the same log.
+    pub fn log_cursor(&self) -> LogCursor {
+        LogCursor {
+            order: self.hash_graph.hashes_in_causal_order().into_iter().cloned().collect(),
+            next: 0,
+        }
+    }
+
+    /// Returns the next page of the change log: at most `limit` of the nodes after `cursor`
+    /// whose operation matches `kind`, e.g. `|op| matches!(op, BFTRGAOp::Insert(..))`, and
+    /// moves the cursor past them. `author_of` tells who created a node, e.g. from its
+    /// signature or the peer it was received from.
+    pub fn log<A>(
+        &self,
+        cursor: &mut LogCursor,
+        kind: impl Fn(&O) -> bool,
+        author_of: impl Fn(&Node<O>) -> A,
+        limit: usize,
+    ) -> Vec<LogEntry<A>> {
+        let mut page = vec![];
+        while page.len() < limit {
+            let Some(hash) = cursor.order.get(cursor.next) else { break };
+            cursor.next += 1;
+            let node = &self.hash_graph.nodes[hash];
+            if kind(&node.value) {
+                page.push(LogEntry {
+                    hash: hash.clone(),
+                    operation: node.value.to_string(),
+                    author: author_of(node),
+                    receipt: self.receipts.get(hash).copied(),
+                });
+            }
+        }
+        page
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::rc::Rc;
+    use crate::bft_crdts::bft_counter::{BFTCounterEvent, BFTPNCounter, BFTPNCounterOp};
+    use crate::bft_crdts::bft_rga::{BFTRGA, BFTRGAOp, LamportId};
+    use crate::bft_crdts::hash_graph::HashType;
+    use rand::SeedableRng;
+    use rand_pcg::Pcg32;
+    use super::*;
+
+    #[test]
+    fn test_replica_buffers_orphans() {
+        let mut local = Replica::new(BFTPNCounter::new());
+        let nodes: Vec<_> = [1, 2, 3].into_iter().map(|n| {
+            let op = local.crdt.increment("a", n);
+            local.handle_local_op(op)
+        }).collect();
+
+        let mut remote = Replica::new(BFTPNCounter::new());
+        assert_eq!(remote.handle_remote_node(nodes[2].clone()), Delivery::Buffered);
+        assert_eq!(remote.handle_remote_node(nodes[1].clone()), Delivery::Buffered);
+        assert_eq!(remote.handle_remote_node(nodes[0].clone()), Delivery::Applied);
+        assert!(remote.pending_nodes.is_empty());
+        assert_eq!(remote.crdt.get_value(), 6);
+        // a node is applied only once however often it is delivered
+        assert_eq!(remote.handle_remote_node(nodes[1].clone()), Delivery::Ignored);
+        assert_eq!(remote.crdt.get_value(), 6);
+
+        let rejections = Rc::new(RefCell::new(vec![]));
+        let sink = rejections.clone();
+        let policy = ReplicaPolicy { max_pending: Some(1), duplicates: DuplicatePolicy::Reject, ..Default::default() };
+        let mut remote = Replica::with_policy(BFTPNCounter::new(), policy);
+        remote.on_rejection(move |node, rejection| sink.borrow_mut().push((node.get_hash(), rejection)));
+        assert_eq!(remote.handle_remote_node(nodes[1].clone()), Delivery::Buffered);
+        assert_eq!(remote.handle_remote_node(nodes[2].clone()), Delivery::Rejected(Rejection::BufferFull));
+        assert_eq!(remote.handle_remote_node(nodes[0].clone()), Delivery::Applied);
+        assert_eq!(remote.handle_remote_node(nodes[0].clone()), Delivery::Rejected(Rejection::Duplicate));
+        assert_eq!(remote.crdt.get_value(), 3);
+        assert_eq!(*rejections.borrow(), vec![(nodes[2].get_hash(), Rejection::BufferFull), (nodes[0].get_hash(), Rejection::Duplicate)]);
+    }
+
+    #[test]
+    fn test_replica_rejects_invalid_nodes() {
+        let policy = ReplicaPolicy { orphans: OrphanPolicy::Drop, notify_local: true, ..Default::default() };
+        // every author is taken as authenticated here
+        let authenticate = |node: &Node<BFTPNCounterOp<&'static str>>| Some(*node.value.author());
+        let mut local = Replica::with_policy(BFTPNCounter::with_author_cap(10, authenticate), policy.clone());
+        let events = local.record_events();
+        let op = local.crdt.increment("a", 4);
+        let first = local.handle_local_op(op);
+        let op = local.crdt.increment("a", 5);
+        let second = local.handle_local_op(op);
+        assert_eq!(*events.borrow(), vec![BFTCounterEvent::Changed(4), BFTCounterEvent::Changed(9)]);
+
+        let mut remote = Replica::with_policy(BFTPNCounter::with_author_cap(10, authenticate), policy);
+        assert_eq!(remote.handle_remote_node(second), Delivery::Rejected(Rejection::Orphan));
+        assert_eq!(remote.handle_remote_node(first), Delivery::Applied);
+        let over_cap = Node::new(remote.hash_graph.get_heads().to_vec(), BFTPNCounterOp::Increment("a", 11));
+        assert_eq!(remote.handle_remote_node(over_cap), Delivery::Rejected(Rejection::Invalid));
+        assert_eq!(remote.crdt.get_value(), 4);
+
+        // random nodes are never applied unless they are structurally and semantically valid
+        let mut rng = Pcg32::seed_from_u64(1);
+        for _ in 0..10 {
+            let node = remote.random_node(&mut rng, BFTPNCounterOp::Increment("b", 20));
+            assert!(matches!(remote.handle_remote_node(node), Delivery::Rejected(_)));
+            let node = remote.random_struct_valid_node(&mut rng, BFTPNCounterOp::Increment("b", 20));
+            assert_eq!(remote.handle_remote_node(node), Delivery::Rejected(Rejection::Invalid));
+        }
+        assert_eq!(remote.crdt.get_value(), 4);
+    }
+
+    #[test]
+    fn test_replica_fork_and_merge() {
+        let mut main = Replica::new(BFTRGA::new(1));
+        for (i, c) in "ab".chars().enumerate() {
+            let op = main.crdt.insert_auto(i, c).unwrap();
+            main.handle_local_op(op);
+        }
+        let fork_point = main.hash_graph.get_heads().to_vec();
+        let op = main.crdt.insert_auto(2, 'c').unwrap();
+        let c = main.handle_local_op(op);
+
+        // the fork starts from the state before 'c' and is edited offline
+        let mut branch = main.fork(&fork_point, BFTRGA::new(2)).unwrap();
+        assert_eq!(branch.crdt.get_list(), vec!['a', 'b']);
+        assert_eq!(branch.hash_graph.get_heads().to_vec(), fork_point);
+        let op = branch.crdt.insert_auto(2, 'x').unwrap();
+        let x = branch.handle_local_op(op);
+        let op = main.crdt.delete(0).unwrap();
+        let delete = main.handle_local_op(op);
+
+        let hashes = |nodes: Vec<Node<BFTRGAOp<LamportId, char>>>| nodes.iter().map(|n| n.get_hash()).collect::<Vec<_>>();
+        assert_eq!(hashes(branch.missing_in(&main.hash_graph)), vec![x.get_hash()]);
+        assert_eq!(hashes(main.missing_in(&branch.hash_graph)), vec![c.get_hash(), delete.get_hash()]);
+
+        let into_main = main.merge(&branch.hash_graph);
+        assert_eq!(into_main.added, vec![x.get_hash()]);
+        assert!(into_main.not_applied.is_empty());
+        let into_branch = branch.merge(&main.hash_graph);
+        assert_eq!(into_branch.added, vec![c.get_hash(), delete.get_hash()]);
+        assert_eq!(main.crdt.get_list(), branch.crdt.get_list());
+        assert_eq!(main.crdt.get_list().len(), 3);
+        assert!(main.merge(&branch.hash_graph).added.is_empty());
+
+        // an invalid node of the branch is not merged, and neither is what follows it
+        let invalid = branch.handle_local_op(BFTRGAOp::Delete((LamportId { counter: 9, replica: 9 }, "unknown".to_string())));
+        let op = branch.crdt.insert_auto(0, 'z').unwrap();
+        let after_invalid = branch.handle_local_op(op);
+        let report = main.merge(&branch.hash_graph);
+        assert!(report.added.is_empty());
+        assert_eq!(report.not_applied, vec![invalid.get_hash(), after_invalid.get_hash()]);
+
+        // a buffered node whose predecessor the branch brings is released by the merge
+        let mut other = main.fork(main.hash_graph.get_heads(), BFTRGA::new(4)).unwrap();
+        let op = other.crdt.insert_auto(0, 'p').unwrap();
+        let p = other.handle_local_op(op);
+        let op = other.crdt.insert_auto(1, 'q').unwrap();
+        let q = other.handle_local_op(op);
+        assert_eq!(main.handle_remote_node(q.clone()), Delivery::Buffered);
+        let report = main.merge(&other.hash_graph);
+        assert_eq!(report.added, vec![p.get_hash()]);
+        assert_eq!(report.released, vec![q.get_hash()]);
+        assert_eq!(main.crdt.get_list(), other.crdt.get_list());
+
+        assert!(main.fork(&["unknown".to_string()], BFTRGA::new(3)).is_none());
+    }
+
+    #[test]
+    fn test_replica_operation_log() {
+        let mut alice = Replica::new(BFTRGA::new(1));
+        let mut bob = Replica::new(BFTRGA::new(2));
+        let mut authors: HashMap<HashType, &str> = HashMap::new();
+        let op = alice.crdt.insert_auto(0, 'a').unwrap();
+        let a = alice.handle_local_op(op);
+        authors.insert(a.get_hash(), "alice");
+        let mut remote = vec![];
+        for (i, c) in "xy".chars().enumerate() {
+            let op = bob.crdt.insert_auto(i, c).unwrap();
+            let node = bob.handle_local_op(op);
+            authors.insert(node.get_hash(), "bob");
+            remote.push(node);
+        }
+        // 'y' arrives before the node it follows
+        assert_eq!(alice.handle_remote_node(remote[1].clone()), Delivery::Buffered);
+        assert_eq!(alice.handle_remote_node(remote[0].clone()), Delivery::Applied);
+        let op = alice.crdt.delete(0).unwrap();
+        let delete = alice.handle_local_op(op);
+        authors.insert(delete.get_hash(), "alice");
+
+        let all = |_: &BFTRGAOp<LamportId, char>| true;
+        let author_of = |node: &Node<BFTRGAOp<LamportId, char>>| authors[&node.get_hash()];
+        let log = alice.log(&mut alice.log_cursor(), all, author_of, 10);
+        assert_eq!(log.len(), 4);
+        let entry = |node: &Node<BFTRGAOp<LamportId, char>>| log.iter().find(|e| e.hash == node.get_hash()).unwrap();
+        assert_eq!(entry(&a).receipt.unwrap().verdict, Verdict::Local);
+        assert_eq!(entry(&remote[0]).receipt.unwrap().verdict, Verdict::Valid);
+        assert_eq!(entry(&remote[1]).receipt.unwrap().verdict, Verdict::ValidAfterBuffering);
+        assert_eq!(entry(&remote[1]).author, "bob");
+        assert_eq!(entry(&delete).operation, delete.value.to_string());
+        // a node comes after its predecessors
+        let position = |node: &Node<BFTRGAOp<LamportId, char>>| log.iter().position(|e| e.hash == node.get_hash()).unwrap();
+        assert!(position(&remote[0]) < position(&remote[1]));
+        assert!(position(&a) < position(&delete));
+
+        // the log is the same on every replica with the same nodes, up to the receipts
+        for node in alice.missing_in(&bob.hash_graph) {
+            bob.handle_remote_node(node);
+        }
+        let hashes = |log: Vec<LogEntry<&str>>| log.into_iter().map(|e| e.hash).collect::<Vec<_>>();
+        assert_eq!(hashes(bob.log(&mut bob.log_cursor(), all, author_of, 10)), hashes(log.clone()));
+
+        let inserts = alice.log(&mut alice.log_cursor(), |op| matches!(op, BFTRGAOp::Insert(..)), author_of, 10);
+        assert_eq!(inserts.len(), 3);
+        // paging with a cursor yields the log piece by piece, without nodes added since
+        let mut cursor = alice.log_cursor();
+        assert_eq!(alice.log(&mut cursor, all, author_of, 1), log[..1].to_vec());
+        assert_eq!(alice.log(&mut cursor, all, author_of, 2), log[1..3].to_vec());
+        let op = alice.crdt.insert_auto(0, 'b').unwrap();
+        alice.handle_local_op(op);
+        assert_eq!(alice.log(&mut cursor, all, author_of, 10), log[3..].to_vec());
+        assert!(alice.log(&mut cursor, all, author_of, 10).is_empty());
+        assert!(alice.fork(&[remote[0].get_hash()], BFTRGA::new(3)).unwrap().receipt(&remote[0].get_hash()).is_some());
+    }
+}
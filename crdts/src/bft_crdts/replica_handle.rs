@@ -0,0 +1,170 @@
+use tokio::sync::{mpsc, oneshot};
+use crate::bft_crdts::bft_crdt::BFTCRDT;
+use crate::bft_crdts::hash_graph::Node;
+use crate::bft_crdts::replica::{Delivery, Replica};
+use crate::serialize::Serialize;
+
+//  A replica handle runs a `Replica` in a tokio task and lets any number of async tasks share
+//   it: every call is a command sent to the task, which handles one command at a time, so a
+//   local operation is generated from and applied to the same state. The task owns the replica
+//   and creates it itself, so that neither the CRDT nor the subscribers of the replica have to
+//   be `Send`. It is therefore a local task that runs within a `tokio::task::LocalSet`, where
+//   the tasks of any number of replicas take turns on one thread, and it stops when the last
+//   handle is dropped. The handles themselves can be used from any thread.
+//
+//   Every new local node is sent to the subscribers of local nodes, e.g. network layers that
+//   forward it to the other peers, through a bounded channel per subscriber. The task waits
+//   while a channel is full, so a slow subscriber holds back further commands, and callers
+//   wait once the bounded command channel is full as well.
+
+type Generate<O, T> = Box<dyn FnOnce(&mut T) -> Option<O> + Send>;
+type Query<O, T> = Box<dyn FnOnce(&Replica<O, T>) + Send>;
+
+enum Command<O: Serialize + Clone, T: BFTCRDT<O>> {
+    // generates the operation from the state of the CRDT
+    ApplyLocal(Generate<O, T>, oneshot::Sender<Option<Node<O>>>),
+    DeliverRemote(Node<O>, oneshot::Sender<Delivery>),
+    Query(Query<O, T>),
+    SubscribeLocal(mpsc::Sender<Node<O>>),
+}
+
+pub struct ReplicaHandle<O: Serialize + Clone, T: BFTCRDT<O>> {
+    commands: mpsc::Sender<Command<O, T>>,
+    capacity: usize,
+}
+
+impl<O, T> Clone for ReplicaHandle<O, T>
+where
+    O: Serialize + Clone,
+    T: BFTCRDT<O>,
+{
+    fn clone(&self) -> Self {
+        ReplicaHandle {
+            commands: self.commands.clone(),
+            capacity: self.capacity,
+        }
+    }
+}
+
+impl<O, T> ReplicaHandle<O, T>
+where
+    O: Serialize + Clone + Send + 'static,
+    T: BFTCRDT<O> + 'static,
+{
+    /// Starts a task that runs the replica created by `make_replica`. `capacity` bounds the
+    /// number of commands waiting for the task and of local nodes waiting for each subscriber.
+    /// Must be called from within a `tokio::task::LocalSet`, e.g. in `LocalSet::run_until`.
+    pub fn spawn(make_replica: impl FnOnce() -> Replica<O, T> + 'static, capacity: usize) -> Self {
+        let (commands, receiver) = mpsc::channel(capacity);
+        tokio::task::spawn_local(async move { Self::run(make_replica(), receiver).await });
+        ReplicaHandle { commands, capacity }
+    }
+
+    async fn run(mut replica: Replica<O, T>, mut commands: mpsc::Receiver<Command<O, T>>) {
+        let mut subscribers: Vec<mpsc::Sender<Node<O>>> = vec![];
+        while let Some(command) = commands.recv().await {
+            match command {
+                Command::ApplyLocal(generate, reply) => {
+                    let node = generate(&mut replica.crdt).map(|op| replica.handle_local_op(op));
+                    if let Some(node) = &node {
+                        // waits for slow subscribers and forgets those that are gone
+                        let mut live = vec![];
+                        for subscriber in subscribers {
+                            if subscriber.send(node.clone()).await.is_ok() {
+                                live.push(subscriber);
+                            }
+                        }
+                        subscribers = live;
+                    }
+                    let _ = reply.send(node);
+                }
+                Command::DeliverRemote(node, reply) => {
+                    let _ = reply.send(replica.handle_remote_node(node));
+                }
+                Command::Query(query) => query(&replica),
+                Command::SubscribeLocal(subscriber) => subscribers.push(subscriber),
+            }
+        }
+    }
+
+    /// Generates an operation from the state of the CRDT, e.g.
+    /// `handle.apply_local(|rga| rga.insert_auto(0, 'a'))`, and applies it as a local node.
+    /// Returns `None` if `generate` does, or if the replica has stopped.
+    pub async fn apply_local(&self, generate: impl FnOnce(&mut T) -> Option<O> + Send + 'static) -> Option<Node<O>> {
+        let (reply, response) = oneshot::channel();
+        self.commands.send(Command::ApplyLocal(Box::new(generate), reply)).await.ok()?;
+        response.await.ok()?
+    }
+
+    /// Hands a node received from another peer to the replica. Returns `None` if the replica
+    /// has stopped.
+    pub async fn deliver_remote(&self, node: Node<O>) -> Option<Delivery> {
+        let (reply, response) = oneshot::channel();
+        self.commands.send(Command::DeliverRemote(node, reply)).await.ok()?;
+        response.await.ok()
+    }
+
+    /// Runs `query` on the replica, e.g. `handle.query(|replica| replica.crdt.get_value())`.
+    /// Returns `None` if the replica has stopped.
+    pub async fn query<R: Send + 'static>(&self, query: impl FnOnce(&Replica<O, T>) -> R + Send + 'static) -> Option<R> {
+        let (reply, response) = oneshot::channel();
+        let query = move |replica: &Replica<O, T>| {
+            let _ = reply.send(query(replica));
+        };
+        self.commands.send(Command::Query(Box::new(query))).await.ok()?;
+        response.await.ok()
+    }
+
+    /// Returns a stream of the local nodes created from now on, in the order they are created.
+    /// Returns `None` if the replica has stopped.
+    pub async fn subscribe_local(&self) -> Option<mpsc::Receiver<Node<O>>> {
+        let (subscriber, receiver) = mpsc::channel(self.capacity);
+        self.commands.send(Command::SubscribeLocal(subscriber)).await.ok()?;
+        Some(receiver)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bft_crdts::bft_counter::BFTPNCounter;
+    use crate::bft_crdts::replica::{Delivery, Replica};
+    use super::*;
+
+    #[tokio::test]
+    async fn test_replica_handle() {
+        tokio::task::LocalSet::new().run_until(run_replicas()).await;
+    }
+
+    async fn run_replicas() {
+        let a = ReplicaHandle::spawn(|| Replica::new(BFTPNCounter::new()), 1);
+        let b = ReplicaHandle::spawn(|| Replica::new(BFTPNCounter::new()), 1);
+        let mut outgoing = a.subscribe_local().await.unwrap();
+        let first = a.apply_local(|counter| Some(counter.increment("a", 2))).await.unwrap();
+        assert!(a.apply_local(|_counter| None).await.is_none());
+
+        // the subscriber has not taken the first node yet, so the next operation waits for it
+        let a2 = a.clone();
+        let second = tokio::spawn(async move { a2.apply_local(|counter| Some(counter.increment("a", 3))).await });
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!second.is_finished());
+        assert_eq!(outgoing.recv().await.map(|n| n.get_hash()), Some(first.get_hash()));
+        let second = second.await.unwrap().unwrap();
+        assert_eq!(outgoing.recv().await.map(|n| n.get_hash()), Some(second.get_hash()));
+
+        assert_eq!(b.deliver_remote(second).await, Some(Delivery::Buffered));
+        assert_eq!(b.deliver_remote(first).await, Some(Delivery::Applied));
+        assert_eq!(b.query(|replica| replica.crdt.get_value()).await, Some(5));
+        assert_eq!(a.query(|replica| replica.hash_graph.get_heads().to_vec()).await, b.query(|replica| replica.hash_graph.get_heads().to_vec()).await);
+
+        // replicas with subscribers that are not `Send` share the thread, however many there are
+        let handles: Vec<_> = (0..2000).map(|_| ReplicaHandle::spawn(|| {
+            let mut replica = Replica::new(BFTPNCounter::new());
+            replica.record_events();
+            replica
+        }, 1)).collect();
+        for handle in handles.iter() {
+            handle.apply_local(|counter| Some(counter.increment("a", 1))).await.unwrap();
+        }
+        assert_eq!(handles[1999].query(|replica| replica.crdt.get_value()).await, Some(1));
+    }
+}
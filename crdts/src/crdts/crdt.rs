@@ -1,3 +1,48 @@
+use std::collections::HashSet;
+use std::hash::Hash;
+
 pub trait CRDT<O> {
     fn interpret_op(&mut self, op: &O);
-}
\ No newline at end of file
+}
+
+/// An operation that declares how it depends on other operations by their IDs, which lets
+/// its causal dependencies be checked without knowing the data type (see `BFTAdapter`).
+pub trait CausalOp {
+    type Id;
+
+    /// The same operation with IDs of type `J`, see `map_ids`.
+    type WithIds<J: Eq + Hash + Clone>;
+
+    /// Returns the IDs this operation introduces, e.g. the ID of an inserted element.
+    fn ids(&self) -> Vec<Self::Id>;
+
+    /// Returns the IDs of the operations that have to be applied before this one.
+    fn deps(&self) -> Vec<Self::Id>;
+
+    /// Whether `dep`, an operation introducing one of `deps`, is of a kind this operation
+    /// may depend on.
+    fn accepts_dep(&self, _dep: &Self) -> bool {
+        true
+    }
+
+    /// Returns the operation with each ID it introduces replaced by `id(..)` and each of its
+    /// dependencies replaced by `dep(..)`, which is called in the order of `deps`.
+    fn map_ids<J: Eq + Hash + Clone>(&self, id: impl FnMut(&Self::Id) -> J, dep: impl FnMut(&Self::Id) -> J) -> Self::WithIds<J>;
+}
+
+/// Read access shared by the set CRDTs, so that code can query a set without depending on
+/// whether it is add-wins, remove-wins or two-phase.
+pub trait SetQuery<E> {
+    fn contains(&self, e: &E) -> bool;
+
+    /// Returns the elements currently in the set.
+    fn elements(&self) -> HashSet<E>;
+
+    fn len(&self) -> usize {
+        self.elements().len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
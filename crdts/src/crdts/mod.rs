@@ -2,4 +2,4 @@ pub mod crdt;
 pub mod orset;
 pub mod rga;
 pub mod ordered_list;
-mod linked_list;
\ No newline at end of file
+pub(crate) mod order_statistic_tree;
\ No newline at end of file
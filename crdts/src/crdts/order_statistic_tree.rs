@@ -0,0 +1,428 @@
+// Order-statistic tree used as the backing store of `OrderedList`.
+//
+// The tree is a treap stored in an arena: every node keeps the number of list positions and
+// the number of visible positions in its subtree, so positional lookups, rank queries and
+// updates of a single item run in O(log n) expected time. Nodes also keep a parent link so
+// that a node handle (e.g. from an ID index) can be turned back into a position.
+use rand::RngCore;
+use rand::SeedableRng;
+use rand_pcg::Pcg32;
+
+pub(crate) type NodeId = usize;
+
+/// Items stored in the tree report how many list positions they occupy and how many of
+/// those positions are visible.
+pub(crate) trait Weighted {
+    fn len(&self) -> usize;
+    fn visible_len(&self) -> usize;
+}
+
+#[derive(Clone)]
+struct TreeNode<T> {
+    item: T,
+    priority: u32,
+    left: Option<NodeId>,
+    right: Option<NodeId>,
+    parent: Option<NodeId>,
+    len: usize,
+    visible_len: usize,
+}
+
+#[derive(Clone)]
+pub(crate) struct OrderStatisticTree<T: Weighted> {
+    nodes: Vec<Option<TreeNode<T>>>,
+    free: Vec<NodeId>,
+    root: Option<NodeId>,
+    rng: Pcg32,
+}
+
+impl<T: Weighted> OrderStatisticTree<T> {
+    pub fn new() -> Self {
+        OrderStatisticTree {
+            nodes: vec![],
+            free: vec![],
+            root: None,
+            rng: Pcg32::seed_from_u64(0),
+        }
+    }
+
+    pub fn visible_len(&self) -> usize {
+        self.root.map_or(0, |r| self.node(r).visible_len)
+    }
+
+    pub fn get(&self, id: NodeId) -> &T {
+        &self.node(id).item
+    }
+
+    /// Mutates the item in place and refreshes the counts on the path to the root.
+    pub fn update<R>(&mut self, id: NodeId, f: impl FnOnce(&mut T) -> R) -> R {
+        let res = f(&mut self.node_mut(id).item);
+        self.refresh_path(Some(id));
+        res
+    }
+
+    pub fn first(&self) -> Option<NodeId> {
+        self.root.map(|r| self.leftmost(r))
+    }
+
+    pub fn last(&self) -> Option<NodeId> {
+        self.root.map(|r| self.rightmost(r))
+    }
+
+    pub fn next(&self, id: NodeId) -> Option<NodeId> {
+        if let Some(r) = self.node(id).right {
+            return Some(self.leftmost(r));
+        }
+        let mut current = id;
+        while let Some(p) = self.node(current).parent {
+            if self.node(p).left == Some(current) {
+                return Some(p);
+            }
+            current = p;
+        }
+        None
+    }
+
+    pub fn prev(&self, id: NodeId) -> Option<NodeId> {
+        if let Some(l) = self.node(id).left {
+            return Some(self.rightmost(l));
+        }
+        let mut current = id;
+        while let Some(p) = self.node(current).parent {
+            if self.node(p).right == Some(current) {
+                return Some(p);
+            }
+            current = p;
+        }
+        None
+    }
+
+    /// Inserts `item` directly before `anchor`, or at the back if `anchor` is `None`.
+    pub fn insert_before(&mut self, anchor: Option<NodeId>, item: T) -> NodeId {
+        let id = self.alloc(item);
+        match (anchor, self.root) {
+            (_, None) => self.root = Some(id),
+            (None, Some(root)) => {
+                let last = self.rightmost(root);
+                self.attach(last, id, false);
+            }
+            (Some(a), Some(_)) => match self.node(a).left {
+                None => self.attach(a, id, true),
+                Some(l) => {
+                    let predecessor = self.rightmost(l);
+                    self.attach(predecessor, id, false);
+                }
+            },
+        }
+        self.sift_up(id);
+        id
+    }
+
+    pub fn remove(&mut self, id: NodeId) -> T {
+        // rotate the node down until it is a leaf, then cut it off
+        loop {
+            let node = self.node(id);
+            let child = match (node.left, node.right) {
+                (None, None) => break,
+                (Some(l), None) => l,
+                (None, Some(r)) => r,
+                (Some(l), Some(r)) => {
+                    if self.node(l).priority > self.node(r).priority { l } else { r }
+                }
+            };
+            self.rotate_up(child);
+        }
+        let parent = self.node(id).parent;
+        match parent {
+            None => self.root = None,
+            Some(p) => {
+                let p_node = self.node_mut(p);
+                if p_node.left == Some(id) {
+                    p_node.left = None;
+                } else {
+                    p_node.right = None;
+                }
+            }
+        }
+        self.refresh_path(parent);
+        self.free.push(id);
+        self.nodes[id].take().expect("dangling tree node").item
+    }
+
+    /// Finds the node holding the `idx`-th visible position, and the offset of that position
+    /// among the node's visible positions.
+    pub fn find_visible(&self, mut idx: usize) -> Option<(NodeId, usize)> {
+        let mut current = self.root;
+        while let Some(id) = current {
+            let node = self.node(id);
+            let left_visible = node.left.map_or(0, |l| self.node(l).visible_len);
+            if idx < left_visible {
+                current = node.left;
+                continue;
+            }
+            idx -= left_visible;
+            let own = node.item.visible_len();
+            if idx < own {
+                return Some((id, idx));
+            }
+            idx -= own;
+            current = node.right;
+        }
+        None
+    }
+
+    /// Finds the node holding the `idx`-th position (visible or not), and the offset of that
+    /// position inside the node.
+    pub fn find(&self, mut idx: usize) -> Option<(NodeId, usize)> {
+        let mut current = self.root;
+        while let Some(id) = current {
+            let node = self.node(id);
+            let left_len = node.left.map_or(0, |l| self.node(l).len);
+            if idx < left_len {
+                current = node.left;
+                continue;
+            }
+            idx -= left_len;
+            let own = node.item.len();
+            if idx < own {
+                return Some((id, idx));
+            }
+            idx -= own;
+            current = node.right;
+        }
+        None
+    }
+
+    /// Returns the number of positions and visible positions before the node.
+    pub fn rank(&self, id: NodeId) -> (usize, usize) {
+        let node = self.node(id);
+        let mut len = node.left.map_or(0, |l| self.node(l).len);
+        let mut visible = node.left.map_or(0, |l| self.node(l).visible_len);
+        let mut current = id;
+        while let Some(p) = self.node(current).parent {
+            let p_node = self.node(p);
+            if p_node.right == Some(current) {
+                len += p_node.item.len() + p_node.left.map_or(0, |l| self.node(l).len);
+                visible += p_node.item.visible_len() + p_node.left.map_or(0, |l| self.node(l).visible_len);
+            }
+            current = p;
+        }
+        (len, visible)
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            tree: self,
+            next: self.first(),
+        }
+    }
+
+    fn node(&self, id: NodeId) -> &TreeNode<T> {
+        self.nodes[id].as_ref().expect("dangling tree node")
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> &mut TreeNode<T> {
+        self.nodes[id].as_mut().expect("dangling tree node")
+    }
+
+    fn alloc(&mut self, item: T) -> NodeId {
+        let node = TreeNode {
+            len: item.len(),
+            visible_len: item.visible_len(),
+            item,
+            priority: self.rng.next_u32(),
+            left: None,
+            right: None,
+            parent: None,
+        };
+        match self.free.pop() {
+            Some(id) => {
+                self.nodes[id] = Some(node);
+                id
+            }
+            None => {
+                self.nodes.push(Some(node));
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    fn attach(&mut self, parent: NodeId, child: NodeId, as_left: bool) {
+        if as_left {
+            self.node_mut(parent).left = Some(child);
+        } else {
+            self.node_mut(parent).right = Some(child);
+        }
+        self.node_mut(child).parent = Some(parent);
+        self.refresh_path(Some(parent));
+    }
+
+    fn sift_up(&mut self, id: NodeId) {
+        while let Some(p) = self.node(id).parent {
+            if self.node(p).priority >= self.node(id).priority {
+                break;
+            }
+            self.rotate_up(id);
+        }
+    }
+
+    fn rotate_up(&mut self, x: NodeId) {
+        let p = self.node(x).parent.expect("cannot rotate the root");
+        let g = self.node(p).parent;
+        if self.node(p).left == Some(x) {
+            let b = self.node(x).right;
+            self.node_mut(p).left = b;
+            if let Some(b) = b {
+                self.node_mut(b).parent = Some(p);
+            }
+            self.node_mut(x).right = Some(p);
+        } else {
+            let b = self.node(x).left;
+            self.node_mut(p).right = b;
+            if let Some(b) = b {
+                self.node_mut(b).parent = Some(p);
+            }
+            self.node_mut(x).left = Some(p);
+        }
+        self.node_mut(p).parent = Some(x);
+        self.node_mut(x).parent = g;
+        match g {
+            None => self.root = Some(x),
+            Some(g) => {
+                let g_node = self.node_mut(g);
+                if g_node.left == Some(p) {
+                    g_node.left = Some(x);
+                } else {
+                    g_node.right = Some(x);
+                }
+            }
+        }
+        self.pull(p);
+        self.pull(x);
+    }
+
+    fn pull(&mut self, id: NodeId) {
+        let node = self.node(id);
+        let (mut len, mut visible_len) = (node.item.len(), node.item.visible_len());
+        for child in [node.left, node.right].into_iter().flatten() {
+            len += self.node(child).len;
+            visible_len += self.node(child).visible_len;
+        }
+        let node = self.node_mut(id);
+        node.len = len;
+        node.visible_len = visible_len;
+    }
+
+    fn refresh_path(&mut self, mut current: Option<NodeId>) {
+        while let Some(id) = current {
+            self.pull(id);
+            current = self.node(id).parent;
+        }
+    }
+
+    fn leftmost(&self, mut id: NodeId) -> NodeId {
+        while let Some(l) = self.node(id).left {
+            id = l;
+        }
+        id
+    }
+
+    fn rightmost(&self, mut id: NodeId) -> NodeId {
+        while let Some(r) = self.node(id).right {
+            id = r;
+        }
+        id
+    }
+}
+
+pub(crate) struct Iter<'a, T: Weighted> {
+    tree: &'a OrderStatisticTree<T>,
+    next: Option<NodeId>,
+}
+
+impl<'a, T: Weighted> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let id = self.next?;
+        self.next = self.tree.next(id);
+        Some(self.tree.get(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    // (value, visible)
+    impl Weighted for (u32, bool) {
+        fn len(&self) -> usize {
+            1
+        }
+
+        fn visible_len(&self) -> usize {
+            self.1 as usize
+        }
+    }
+
+    fn collect(tree: &OrderStatisticTree<(u32, bool)>) -> Vec<u32> {
+        tree.iter().map(|(v, _)| *v).collect()
+    }
+
+    #[test]
+    fn test_insert_after_and_before() {
+        let mut tree = OrderStatisticTree::new();
+        let c = tree.insert_before(None, (3, true));
+        tree.insert_before(Some(c), (1, true));
+        tree.insert_before(Some(c), (2, true));
+        tree.insert_before(None, (4, true));
+        let first = tree.first();
+        tree.insert_before(first, (0, true));
+        assert_eq!(collect(&tree), vec![0, 1, 2, 3, 4]);
+        assert_eq!(tree.visible_len(), 5);
+    }
+
+    #[test]
+    fn test_visible_lookup_and_rank() {
+        let mut tree = OrderStatisticTree::new();
+        let mut ids = vec![];
+        for i in 0..10 {
+            ids.push(tree.insert_before(None, (i, i % 2 == 0)));
+        }
+        assert_eq!(tree.visible_len(), 5);
+        assert_eq!(tree.get(tree.find_visible(3).unwrap().0).0, 6);
+        assert_eq!(tree.rank(ids[7]), (7, 4));
+        tree.update(ids[6], |e| e.1 = false);
+        assert_eq!(tree.get(tree.find_visible(3).unwrap().0).0, 8);
+        assert_eq!(tree.find_visible(4), None);
+        assert_eq!(tree.get(tree.find(6).unwrap().0).0, 6);
+    }
+
+    #[test]
+    fn test_matches_vec_model() {
+        let mut rng = Pcg32::seed_from_u64(42);
+        let mut tree = OrderStatisticTree::new();
+        let mut model: Vec<(u32, NodeId)> = vec![];
+        for i in 0..2000 {
+            if model.is_empty() || rng.gen_bool(0.7) {
+                let pos = rng.gen_range(0..=model.len());
+                let anchor = model.get(pos).map(|(_, id)| *id);
+                let id = tree.insert_before(anchor, (i, true));
+                model.insert(pos, (i, id));
+            } else {
+                let pos = rng.gen_range(0..model.len());
+                let (v, id) = model.remove(pos);
+                assert_eq!(tree.remove(id), (v, true));
+            }
+            if i % 100 == 0 {
+                assert_eq!(collect(&tree), model.iter().map(|(v, _)| *v).collect::<Vec<_>>());
+                for (pos, (_, id)) in model.iter().enumerate() {
+                    assert_eq!(tree.rank(*id).0, pos);
+                    assert_eq!(tree.prev(*id), pos.checked_sub(1).map(|p| model[p].1));
+                }
+                assert_eq!(tree.last(), model.last().map(|(_, id)| *id));
+            }
+        }
+    }
+}
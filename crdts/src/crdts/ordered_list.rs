@@ -1,134 +1,444 @@
+use std::collections::{BTreeMap, HashMap};
 use std::hash::Hash;
-use crate::crdts::linked_list::LinkedList;
+use crate::crdts::order_statistic_tree::{NodeId, OrderStatisticTree, Weighted};
 
 type Element<I, V> = (I, V, bool);
 
+/// Element IDs that can form runs of consecutive IDs. `OrderedList` stores a run of elements
+/// that were inserted one after another with consecutive IDs as a single span, and derives
+/// the IDs inside the span from the ID of its first element and the extra part of each ID.
+///
+/// Successors must compare greater than their predecessors, and the keys of a run must be
+/// contiguous under `Key`, so that the span holding an ID can be found from the closest span
+/// start at or below its key.
+pub trait SpanId: Sized {
+    type Key: Ord + Clone;
+
+    /// The part of the ID that does not follow from its place in the run, e.g. the node hash
+    /// in the element IDs of `BFTRGA`. Spans store it for every element.
+    type Extra: Clone + PartialEq;
+
+    fn span_key(&self) -> Self::Key;
+
+    fn extra(&self) -> Self::Extra;
+
+    /// Returns the ID with the given extra part `n` places after `self` in its run, if this
+    /// kind of ID forms runs.
+    fn nth_successor(&self, n: usize, extra: Self::Extra) -> Option<Self>;
+
+    /// Returns `n` if the key of `self` is `n` places after that of `start` in the same run.
+    fn offset_from(&self, start: &Self) -> Option<usize>;
+}
+
+macro_rules! impl_span_id_for_int {
+    ($($t:ty),*) => {
+        $(
+            impl SpanId for $t {
+                type Key = $t;
+                type Extra = ();
+
+                fn span_key(&self) -> $t {
+                    *self
+                }
+
+                fn extra(&self) {}
+
+                fn nth_successor(&self, n: usize, _extra: ()) -> Option<$t> {
+                    <$t>::try_from(n).ok().and_then(|n| self.checked_add(n))
+                }
+
+                fn offset_from(&self, start: &$t) -> Option<usize> {
+                    usize::try_from(*self as i128 - *start as i128).ok()
+                }
+            }
+        )*
+    };
+}
+
+impl_span_id_for_int!(u8, u16, u32, u64, usize, i8, i16, i32, i64);
+
+// IDs without a notion of successor only ever form runs of length one
+impl SpanId for String {
+    type Key = String;
+    type Extra = ();
+
+    fn span_key(&self) -> String {
+        self.clone()
+    }
+
+    fn extra(&self) {}
+
+    fn nth_successor(&self, n: usize, _extra: ()) -> Option<String> {
+        if n == 0 { Some(self.clone()) } else { None }
+    }
+
+    fn offset_from(&self, start: &String) -> Option<usize> {
+        if self == start { Some(0) } else { None }
+    }
+}
+
+impl<'a> SpanId for &'a str {
+    type Key = &'a str;
+    type Extra = ();
+
+    fn span_key(&self) -> &'a str {
+        self
+    }
+
+    fn extra(&self) {}
+
+    fn nth_successor(&self, n: usize, _extra: ()) -> Option<&'a str> {
+        if n == 0 { Some(self) } else { None }
+    }
+
+    fn offset_from(&self, start: &&'a str) -> Option<usize> {
+        if self == start { Some(0) } else { None }
+    }
+}
+
+// A pair forms a run when the first components do, whatever the second components are, e.g.
+// the (peer-chosen ID, node hash) element IDs of `BFTRGA`. The second components are stored
+// out of line, so runs are keyed by the first components only.
+impl<I: SpanId> SpanId for (I, String) {
+    type Key = I::Key;
+    type Extra = (I::Extra, String);
+
+    fn span_key(&self) -> I::Key {
+        self.0.span_key()
+    }
+
+    fn extra(&self) -> Self::Extra {
+        (self.0.extra(), self.1.clone())
+    }
+
+    fn nth_successor(&self, n: usize, (extra, second): Self::Extra) -> Option<Self> {
+        self.0.nth_successor(n, extra).map(|i| (i, second))
+    }
+
+    fn offset_from(&self, start: &Self) -> Option<usize> {
+        self.0.offset_from(&start.0)
+    }
+}
+
+// A run of elements with consecutive IDs that are either all visible or all deleted. A pinned
+// span holds a single element whose key another element already has, e.g. because a faulty
+// peer reused a `LamportId`; it is looked up by its whole ID and never joins a run.
+#[derive(Clone)]
+pub(crate) struct Span<I: SpanId, V> {
+    id: I,
+    extras: Vec<I::Extra>,
+    values: Vec<V>,
+    deleted: bool,
+    pinned: bool,
+}
+
+impl<I: SpanId, V> Span<I, V> {
+    fn new(id: I, value: V, deleted: bool, pinned: bool) -> Self {
+        Span { extras: vec![id.extra()], id, values: vec![value], deleted, pinned }
+    }
+
+    fn id_at(&self, offset: usize) -> I {
+        self.id.nth_successor(offset, self.extras[offset].clone()).expect("span IDs must form a run")
+    }
+}
+
+impl<I: SpanId, V> Weighted for Span<I, V> {
+    fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    fn visible_len(&self) -> usize {
+        if self.deleted { 0 } else { self.values.len() }
+    }
+}
+
+// Spans are kept in an order-statistic tree so that positional lookups are O(log n), and
+// `index` maps the key of the first element of each span to its tree node so that lookups by
+// ID are O(log n) as well. Element IDs are assumed to be unique, but keys need not be: the
+// pinned spans are indexed by ID in `pinned` instead.
 pub struct OrderedList<I, V> 
 where
-    I: PartialEq + Eq + Hash + Clone + PartialOrd,
+    I: PartialEq + Eq + Hash + Clone + PartialOrd + SpanId,
     V: PartialEq + Eq + Hash + Clone,
 {
-    pub(crate) elements: LinkedList<Element<I, V>>,
+    pub(crate) elements: OrderStatisticTree<Span<I, V>>,
+    index: BTreeMap<I::Key, NodeId>,
+    pinned: HashMap<I, NodeId>,
 }
 
 impl<I, V> OrderedList<I, V> 
 where
-    I: PartialEq + Eq + Hash + Clone + PartialOrd,
+    I: PartialEq + Eq + Hash + Clone + PartialOrd + SpanId,
     V: PartialEq + Eq + Hash + Clone,
 {
     pub fn new() -> Self {
         OrderedList {
-            elements: LinkedList::new(),
+            elements: OrderStatisticTree::new(),
+            index: BTreeMap::new(),
+            pinned: HashMap::new(),
         }
     }
 
     pub fn insert_by_id(&mut self, id: I, value: V, after: Option<I>) -> Option<()> {
-        let to_insert = (id, value, false);
-        let mut cursor = self.elements.cursor_mut();
-        cursor.move_next();
-        match after {
-            None => {
-                while let Some(element) = cursor.current() {
-                    if element.0 < to_insert.0 {
-                        let mut element_temp_list = LinkedList::new();
-                        element_temp_list.push_back(to_insert.clone());
-                        cursor.splice_before(element_temp_list);
-                        return Some(());
-                    }
-                    cursor.move_next();
-                }
-                self.elements.push_back(to_insert);
-                Some(())
-            }
+        // (node, offset) of the first element after the insertion anchor
+        let mut cursor = match after {
+            None => self.elements.first().map(|node| (node, 0)),
             Some(after_id) => {
-                let mut found = false;                
-                while let Some(element) = cursor.current() {
-                    if element.0 == after_id {
-                        found = true;
-                        cursor.move_next();
-                        break;
-                    }
-                    cursor.move_next(); 
+                let (node, offset) = self.find_id(&after_id)?;
+                if offset + 1 < self.elements.get(node).len() {
+                    Some((node, offset + 1))
+                } else {
+                    self.elements.next(node).map(|next| (next, 0))
                 }
-
-                if !found {
-                    return None;
-                }
-
-                while let Some(element) = cursor.current() {
-                    if element.0 < to_insert.0 {
-                        let mut element_temp_list = LinkedList::new();
-                        element_temp_list.push_front(to_insert.clone());
-                        cursor.splice_before(element_temp_list);
-                        return Some(());
-                    }
-                    cursor.move_next();
-                }
-                self.elements.push_back(to_insert);
-                Some(())
             }
+        };
+        // skip over the elements that have greater IDs than the inserted element; IDs grow
+        // within a span, so once one element is skipped the rest of its span is skipped too
+        while let Some((node, offset)) = cursor {
+            if self.elements.get(node).id_at(offset) < id {
+                break;
+            }
+            cursor = self.elements.next(node).map(|next| (next, 0));
         }
+        let before = cursor.map(|(node, offset)| self.split(node, offset));
+        let pinned = self.find_key(&id).is_some();
+        let node = self.elements.insert_before(before, Span::new(id, value, false, pinned));
+        self.index_span(node);
+        let node = self.merge_with_prev(node);
+        self.merge_with_next(node);
+        Some(())
     }
 
     pub fn delete_by_id(&mut self, id: I) -> Option<()> {
-        for element in self.elements.iter_mut() {
-            if element.0 == id {
-                element.2 = true;
-                return Some(());
-            }
-        }
-        None
+        let (node, offset) = self.find_id(&id)?;
+        self.delete_at(node, offset);
+        Some(())
     }
     
     pub fn get_by_id(&self, id: I) -> Option<Element<I, V>> {
-        for element in self.elements.iter() {
-            if element.0 == id {
-                return Some(element.clone());
-            }
+        self.find_id(&id).map(|(node, offset)| self.element_at(node, offset))
+    }
+
+    /// Returns the index of the element among the visible elements, or `None` if it is
+    /// unknown or deleted.
+    pub fn index_of(&self, id: &I) -> Option<usize> {
+        let (node, offset) = self.find_id(id)?;
+        if self.elements.get(node).deleted {
+            return None;
         }
-        None
+        Some(self.elements.rank(node).1 + offset)
+    }
+
+    /// Returns the number of visible elements before the element, whether or not the element
+    /// itself is deleted.
+    pub fn rank_of(&self, id: &I) -> Option<usize> {
+        let (node, offset) = self.find_id(id)?;
+        let visible_offset = if self.elements.get(node).deleted { 0 } else { offset };
+        Some(self.elements.rank(node).1 + visible_offset)
     }
 
     pub fn get_by_idx(&self, idx: usize) -> Option<Element<I, V>> {
-        let mut count = 0;
-        for element in self.elements.iter() {
-            if !element.2 {
-                if count == idx {
-                    return Some(element.clone());
-                }
-                count += 1;
-            }
-        }
-        None
+        self.elements.find_visible(idx).map(|(node, offset)| self.element_at(node, offset))
+    }
+
+    /// Like `get_by_idx`, but counts deleted elements as well.
+    pub fn get_by_raw_idx(&self, idx: usize) -> Option<Element<I, V>> {
+        self.elements.find(idx).map(|(node, offset)| self.element_at(node, offset))
     }
 
     pub fn delete_by_idx(&mut self, idx: usize) -> Option<()> {
-        let mut count = 0;
-        for element in self.elements.iter_mut() {
-            if !element.2 {
-                if count == idx {
-                    element.2 = true;
-                    return Some(());
+        let (node, offset) = self.elements.find_visible(idx)?;
+        self.delete_at(node, offset);
+        Some(())
+    }
+
+    /// Physically removes deleted elements for which `collectable` returns true, and returns
+    /// their IDs. A tombstone is only removed if the element following it has a smaller ID,
+    /// so that later inserts skip over the remaining elements exactly as they would have with
+    /// the tombstone in place. The caller must make sure that no later operation refers to a
+    /// collected ID.
+    pub fn purge_tombstones(&mut self, mut collectable: impl FnMut(&I) -> bool) -> Vec<I> {
+        let mut collected = vec![];
+        // walk from the back so that the successor of each tombstone is already final
+        let mut next_id: Option<I> = None;
+        let mut cursor = self.elements.last();
+        while let Some(node) = cursor {
+            cursor = self.elements.prev(node);
+            if !self.elements.get(node).deleted {
+                next_id = Some(self.elements.get(node).id.clone());
+                continue;
+            }
+            self.unindex_span(node);
+            let span = self.elements.remove(node);
+            // kept elements are reinserted as spans of consecutive IDs, last span first
+            let mut kept = vec![];
+            for offset in (0..span.values.len()).rev() {
+                let id = span.id_at(offset);
+                if collectable(&id) && next_id.as_ref().is_none_or(|next| *next < id) {
+                    collected.push(id);
+                    self.reinsert_tombstones(cursor, &mut kept, span.pinned);
+                } else {
+                    kept.push((id.clone(), span.values[offset].clone()));
+                    next_id = Some(id);
                 }
-                count += 1;
             }
+            self.reinsert_tombstones(cursor, &mut kept, span.pinned);
+        }
+        collected
+    }
+
+    // Inserts the reversed `elements`, if any, as a deleted span directly after `after` (or at
+    // the front).
+    fn reinsert_tombstones(&mut self, after: Option<NodeId>, elements: &mut Vec<(I, V)>, pinned: bool) {
+        let Some((id, value)) = elements.pop() else { return };
+        let mut span = Span::new(id, value, true, pinned);
+        while let Some((id, value)) = elements.pop() {
+            span.extras.push(id.extra());
+            span.values.push(value);
         }
-        None
+        let before = match after {
+            Some(node) => self.elements.next(node),
+            None => self.elements.first(),
+        };
+        let node = self.elements.insert_before(before, span);
+        self.index_span(node);
+    }
+
+    /// Number of visible elements.
+    pub fn len(&self) -> usize {
+        self.elements.visible_len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
     
     pub fn get_list(&self) -> Vec<V> {
-        self.elements.iter().filter(|e| !e.2).map(|e| e.1.clone()).collect()
+        self.elements.iter().filter(|span| !span.deleted).flat_map(|span| span.values.iter().cloned()).collect()
+    }
+
+    /// Returns all elements in list order, deleted ones included.
+    pub fn iter(&self) -> impl Iterator<Item = Element<I, V>> + '_ {
+        self.elements.iter().flat_map(|span| {
+            span.values.iter().enumerate().map(|(offset, value)| (span.id_at(offset), value.clone(), span.deleted))
+        })
+    }
+
+    // the span and offset of the element that is not pinned and has the key of `id`
+    fn find_key(&self, id: &I) -> Option<(NodeId, usize)> {
+        let (_, node) = self.index.range(..=id.span_key()).next_back()?;
+        let span = self.elements.get(*node);
+        match id.offset_from(&span.id) {
+            Some(offset) if offset < span.values.len() => Some((*node, offset)),
+            _ => None,
+        }
+    }
+
+    fn find_id(&self, id: &I) -> Option<(NodeId, usize)> {
+        match self.find_key(id) {
+            Some((node, offset)) if self.elements.get(node).extras[offset] == id.extra() => Some((node, offset)),
+            _ => self.pinned.get(id).map(|node| (*node, 0)),
+        }
+    }
+
+    fn index_span(&mut self, node: NodeId) {
+        let span = self.elements.get(node);
+        if span.pinned {
+            self.pinned.insert(span.id.clone(), node);
+        } else {
+            self.index.insert(span.id.span_key(), node);
+        }
+    }
+
+    fn unindex_span(&mut self, node: NodeId) {
+        let span = self.elements.get(node);
+        if span.pinned {
+            self.pinned.remove(&span.id);
+        } else {
+            self.index.remove(&span.id.span_key());
+        }
+    }
+
+    fn element_at(&self, node: NodeId, offset: usize) -> Element<I, V> {
+        let span = self.elements.get(node);
+        (span.id_at(offset), span.values[offset].clone(), span.deleted)
+    }
+
+    fn delete_at(&mut self, node: NodeId, offset: usize) {
+        if self.elements.get(node).deleted {
+            return;
+        }
+        let node = self.split(node, offset);
+        if self.elements.get(node).len() > 1 {
+            self.split(node, 1);
+        }
+        self.elements.update(node, |span| span.deleted = true);
+        let node = self.merge_with_prev(node);
+        self.merge_with_next(node);
+    }
+
+    /// Splits the span so that the element at `offset` starts a span, and returns that span.
+    fn split(&mut self, node: NodeId, offset: usize) -> NodeId {
+        if offset == 0 {
+            return node;
+        }
+        let id = self.elements.get(node).id_at(offset);
+        let (extras, values, deleted) = self.elements.update(node, |span| {
+            (span.extras.split_off(offset), span.values.split_off(offset), span.deleted)
+        });
+        let next = self.elements.next(node);
+        let tail_node = self.elements.insert_before(next, Span { id, extras, values, deleted, pinned: false });
+        self.index_span(tail_node);
+        tail_node
+    }
+
+    /// Merges the span into its predecessor if the two form one run, and returns the node now
+    /// holding the span's elements.
+    fn merge_with_prev(&mut self, node: NodeId) -> NodeId {
+        match self.elements.prev(node) {
+            Some(prev) if self.can_merge(prev, node) => {
+                self.join(prev, node);
+                prev
+            }
+            _ => node,
+        }
+    }
+
+    fn merge_with_next(&mut self, node: NodeId) {
+        if let Some(next) = self.elements.next(node) {
+            if self.can_merge(node, next) {
+                self.join(node, next);
+            }
+        }
+    }
+
+    // appends the elements of `second` to the span `first`
+    fn join(&mut self, first: NodeId, second: NodeId) {
+        self.unindex_span(second);
+        let span = self.elements.remove(second);
+        self.elements.update(first, |first_span| {
+            first_span.extras.extend(span.extras);
+            first_span.values.extend(span.values);
+        });
+    }
+
+    fn can_merge(&self, first: NodeId, second: NodeId) -> bool {
+        let (first, second) = (self.elements.get(first), self.elements.get(second));
+        first.deleted == second.deleted && !first.pinned && !second.pinned
+            && second.id.offset_from(&first.id) == Some(first.values.len())
     }
 }
 
 impl<I, V> Clone for OrderedList<I, V>
 where
-    I: PartialEq + Eq + Hash + Clone + PartialOrd,
+    I: PartialEq + Eq + Hash + Clone + PartialOrd + SpanId,
     V: PartialEq + Eq + Hash + Clone,
 {
     fn clone(&self) -> Self {
         OrderedList {
             elements: self.elements.clone(),
+            index: self.index.clone(),
+            pinned: self.pinned.clone(),
         }
     }
 }
@@ -215,4 +525,177 @@ mod tests {
 
         assert_eq!(list.get_list(), list2.get_list());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_lookup_in_long_list() {
+        let mut list = OrderedList::new();
+        list.insert_by_id(0, 0, None);
+        for i in 1..1000 {
+            list.insert_by_id(i, i, Some(i - 1));
+        }
+        for i in (0..1000).step_by(2) {
+            list.delete_by_id(i);
+        }
+        assert_eq!(list.len(), 500);
+        assert_eq!(list.get_by_idx(10), Some((21, 21, false)));
+        assert_eq!(list.get_by_raw_idx(10), Some((10, 10, true)));
+        assert_eq!(list.index_of(&21), Some(10));
+        assert_eq!(list.index_of(&20), None);
+        list.delete_by_idx(10);
+        assert_eq!(list.get_by_id(21), Some((21, 21, true)));
+        assert_eq!(list.rank_of(&21), Some(10));
+        assert_eq!(list.rank_of(&22), Some(10));
+        assert_eq!(list.rank_of(&1000), None);
+        assert_eq!(list.get_by_idx(10), Some((23, 23, false)));
+    }
+
+    fn span_count<I, V>(list: &OrderedList<I, V>) -> usize
+    where
+        I: PartialEq + Eq + Hash + Clone + PartialOrd + SpanId,
+        V: PartialEq + Eq + Hash + Clone,
+    {
+        list.elements.iter().count()
+    }
+
+    #[test]
+    fn test_consecutive_inserts_form_one_span() {
+        let mut list = OrderedList::new();
+        list.insert_by_id(0u64, 'a', None);
+        for i in 1..100 {
+            list.insert_by_id(i, 'a', Some(i - 1));
+        }
+        assert_eq!(span_count(&list), 1);
+        assert_eq!(list.len(), 100);
+    }
+
+    #[test]
+    fn test_insert_and_delete_split_spans() {
+        let mut list = OrderedList::new();
+        list.insert_by_id(0u64, "a", None);
+        list.insert_by_id(1, "b", Some(0));
+        list.insert_by_id(2, "c", Some(1));
+        list.insert_by_id(3, "d", Some(2));
+        list.insert_by_id(10, "x", Some(1));
+        assert_eq!(list.get_list(), vec!["a", "b", "x", "c", "d"]);
+        assert_eq!(span_count(&list), 3);
+
+        list.delete_by_id(2);
+        assert_eq!(list.get_list(), vec!["a", "b", "x", "d"]);
+        assert_eq!(span_count(&list), 4);
+        assert_eq!(list.get_by_id(2), Some((2, "c", true)));
+        assert_eq!(list.index_of(&3), Some(3));
+
+        // deleting the rest of the run merges the tombstones back into one span
+        list.delete_by_id(3);
+        assert_eq!(span_count(&list), 3);
+    }
+
+    #[test]
+    fn test_backspacing_a_run_merges_tombstones() {
+        let mut list = OrderedList::new();
+        list.insert_by_id(0u64, 'a', None);
+        for i in 1..10 {
+            list.insert_by_id(i, 'a', Some(i - 1));
+        }
+        for i in (3..10).rev() {
+            list.delete_by_idx(i);
+        }
+        assert_eq!(list.len(), 3);
+        assert_eq!(span_count(&list), 2);
+    }
+
+    #[test]
+    fn test_matches_unencoded_list() {
+        use rand::{Rng, SeedableRng};
+        use rand_pcg::Pcg32;
+
+        // reference implementation storing one element per entry
+        fn reference_insert(list: &mut Vec<(u64, u64, bool)>, id: u64, after: Option<u64>) {
+            let mut pos = match after {
+                None => 0,
+                Some(a) => list.iter().position(|e| e.0 == a).unwrap() + 1,
+            };
+            while pos < list.len() && list[pos].0 >= id {
+                pos += 1;
+            }
+            list.insert(pos, (id, id, false));
+        }
+
+        let mut rng = Pcg32::seed_from_u64(7);
+        let mut list = OrderedList::new();
+        let mut reference: Vec<(u64, u64, bool)> = vec![];
+        let mut next_id = 0;
+        for _ in 0..3000 {
+            let visible: Vec<u64> = reference.iter().filter(|e| !e.2).map(|e| e.0).collect();
+            if visible.is_empty() || rng.gen_bool(0.7) {
+                // mostly sequential typing with occasional jumps and out-of-order IDs
+                let after = if visible.is_empty() || rng.gen_bool(0.05) { None } else { Some(visible[rng.gen_range(0..visible.len())]) };
+                let id = if rng.gen_bool(0.1) { next_id + 5 } else { next_id };
+                next_id = id + 1;
+                if reference.iter().any(|e| e.0 == id) {
+                    continue;
+                }
+                list.insert_by_id(id, id, after);
+                reference_insert(&mut reference, id, after);
+            } else {
+                let id = visible[rng.gen_range(0..visible.len())];
+                list.delete_by_id(id);
+                reference.iter_mut().find(|e| e.0 == id).unwrap().2 = true;
+            }
+            assert_eq!(list.get_list(), reference.iter().filter(|e| !e.2).map(|e| e.1).collect::<Vec<_>>());
+        }
+        for (raw_idx, element) in reference.iter().enumerate() {
+            assert_eq!(list.get_by_raw_idx(raw_idx), Some(*element));
+        }
+        assert!(span_count(&list) < reference.len());
+    }
+
+    #[test]
+    fn test_runs_of_hash_identified_elements() {
+        let id = |i: u64| (i, format!("hash{}", i));
+        let mut list = OrderedList::new();
+        list.insert_by_id(id(0), 'a', None);
+        for i in 1..50 {
+            list.insert_by_id(id(i), 'a', Some(id(i - 1)));
+        }
+        assert_eq!(span_count(&list), 1);
+        assert_eq!(list.get_by_id(id(20)), Some((id(20), 'a', false)));
+        assert_eq!(list.get_by_id((20, "other".to_string())), None);
+
+        // an element reusing a key gets a span of its own and is found by its whole ID
+        let reused = (20, "other".to_string());
+        list.insert_by_id(reused.clone(), 'b', Some(id(20)));
+        assert_eq!(span_count(&list), 2);
+        assert_eq!(list.index_of(&reused), Some(50));
+        assert_eq!(list.index_of(&id(20)), Some(20));
+        list.delete_by_id(reused.clone());
+        list.delete_by_id(id(20));
+        assert_eq!(list.len(), 49);
+        assert_eq!(list.purge_tombstones(|_| true), vec![reused.clone()]);
+        assert_eq!(list.get_by_id(reused), None);
+        assert_eq!(list.get_by_id(id(20)), Some((id(20), 'a', true)));
+    }
+
+    #[test]
+    fn test_purge_tombstones_keeps_insert_positions() {
+        let mut list = OrderedList::new();
+        list.insert_by_id(0u64, "a", None);
+        for i in 1..6 {
+            list.insert_by_id(i, "x", Some(i - 1));
+        }
+        list.insert_by_id(10, "b", Some(5));
+        for i in 1..6 {
+            list.delete_by_id(i);
+        }
+        // "b" follows the tombstones with a greater ID, so none of them can go yet
+        assert_eq!(list.purge_tombstones(|_| true), Vec::<u64>::new());
+
+        list.delete_by_id(10);
+        assert_eq!(list.purge_tombstones(|id| *id != 3), vec![10, 5, 4]);
+        assert_eq!(span_count(&list), 2);
+        assert_eq!(list.get_by_id(3), Some((3, "x", true)));
+        assert_eq!(list.get_by_id(4), None);
+        list.insert_by_id(7, "c", Some(0));
+        assert_eq!(list.get_list(), vec!["a", "c"]);
+    }
+}
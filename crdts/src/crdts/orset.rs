@@ -1,14 +1,74 @@
 use std::collections::HashMap;
 use std::collections::HashSet;
-use crate::crdts::crdt::CRDT;
+use crate::crdts::crdt::{CausalOp, CRDT, SetQuery};
+use crate::serialize::Serialize;
 use std::hash::Hash;
 use std::cmp::Eq;
 
+#[derive(Clone, Debug)]
 pub enum ORSetOp<E, I> {
     Add(E, I),
     Remove(E, Vec<I>),
 }
 
+impl<E, I> CausalOp for ORSetOp<E, I>
+where
+    E: Eq + Hash + Clone,
+    I: PartialEq + Eq + Hash + Clone
+{
+    type Id = I;
+    type WithIds<J: Eq + Hash + Clone> = ORSetOp<E, J>;
+
+    fn ids(&self) -> Vec<I> {
+        match self {
+            ORSetOp::Add(_, i) => vec![i.clone()],
+            ORSetOp::Remove(_, _) => vec![],
+        }
+    }
+
+    fn deps(&self) -> Vec<I> {
+        match self {
+            ORSetOp::Add(_, _) => vec![],
+            ORSetOp::Remove(_, ids) => ids.clone(),
+        }
+    }
+
+    // a Remove may only cancel Adds of the same element
+    fn accepts_dep(&self, dep: &Self) -> bool {
+        match (self, dep) {
+            (ORSetOp::Remove(e, _), ORSetOp::Add(e2, _)) => e == e2,
+            _ => false,
+        }
+    }
+
+    fn map_ids<J: Eq + Hash + Clone>(&self, mut id: impl FnMut(&I) -> J, dep: impl FnMut(&I) -> J) -> ORSetOp<E, J> {
+        match self {
+            ORSetOp::Add(e, i) => ORSetOp::Add(e.clone(), id(i)),
+            ORSetOp::Remove(e, ids) => ORSetOp::Remove(e.clone(), ids.iter().map(dep).collect()),
+        }
+    }
+}
+
+impl<E, I> Serialize for ORSetOp<E, I>
+where
+    E: Serialize,
+    I: Serialize,
+{
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            ORSetOp::Add(e, i) => [vec![0u8], i.to_bytes(), e.to_bytes()].concat(),
+            ORSetOp::Remove(e, ids) => {
+                let mut bytes = vec![1u8];
+                for i in ids {
+                    bytes.extend_from_slice(&i.to_bytes());
+                }
+                bytes.extend_from_slice(&e.to_bytes());
+                bytes
+            }
+        }
+    }
+}
+
 pub struct ORSet<E, I> 
 where
     E: Eq + Hash + Clone,
@@ -46,6 +106,20 @@ where
 
 }
 
+impl<E, I> SetQuery<E> for ORSet<E, I>
+where
+    E: Eq + Hash + Clone,
+    I: PartialEq + Eq + Hash + Clone
+{
+    fn contains(&self, e: &E) -> bool {
+        self.elements.get(e).is_some_and(|ids| !ids.is_empty())
+    }
+
+    fn elements(&self) -> HashSet<E> {
+        self.elements.iter().filter(|(_, ids)| !ids.is_empty()).map(|(e, _)| e.clone()).collect()
+    }
+}
+
 impl<E, I> CRDT<ORSetOp<E, I>> for ORSet<E, I>
 where
     E: Eq + Hash + Clone,
@@ -80,6 +154,8 @@ mod tests {
         assert!(!orset.is_in("b"));
         assert_eq!(orset.get_ids("a"), HashSet::from(["2"]));
         assert_eq!(orset.get_ids("b"), HashSet::new());
+        assert_eq!(orset.elements(), HashSet::from(["a"]));
+        assert_eq!(orset.len(), 1);
     }
 
     #[test]
@@ -1,8 +1,9 @@
-use crate::crdts::crdt::CRDT;
-use crate::crdts::ordered_list::OrderedList;
+use crate::crdts::crdt::{CausalOp, CRDT};
+use crate::crdts::ordered_list::{OrderedList, SpanId};
+use crate::serialize::Serialize;
 use std::hash::Hash;
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub enum RGAOp<I, V>
 where
     I: PartialEq + Eq + Hash + Clone,
@@ -12,9 +13,53 @@ where
     Delete(I),
 }
 
+impl<I, V> CausalOp for RGAOp<I, V>
+where
+    I: PartialEq + Eq + Hash + Clone,
+    V: PartialEq + Eq + Hash + Clone,
+{
+    type Id = I;
+    type WithIds<J: Eq + Hash + Clone> = RGAOp<J, V>;
+
+    fn ids(&self) -> Vec<I> {
+        match self {
+            RGAOp::Insert(id, _, _) => vec![id.clone()],
+            RGAOp::Delete(_) => vec![],
+        }
+    }
+
+    fn deps(&self) -> Vec<I> {
+        match self {
+            RGAOp::Insert(_, _, after) => after.iter().cloned().collect(),
+            RGAOp::Delete(id) => vec![id.clone()],
+        }
+    }
+
+    fn map_ids<J: Eq + Hash + Clone>(&self, mut id: impl FnMut(&I) -> J, mut dep: impl FnMut(&I) -> J) -> RGAOp<J, V> {
+        match self {
+            RGAOp::Insert(i, value, after) => RGAOp::Insert(id(i), value.clone(), after.as_ref().map(dep)),
+            RGAOp::Delete(i) => RGAOp::Delete(dep(i)),
+        }
+    }
+}
+
+impl<I, V> Serialize for RGAOp<I, V>
+where
+    I: PartialEq + Eq + Hash + Clone + Serialize,
+    V: PartialEq + Eq + Hash + Clone + Serialize,
+{
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            RGAOp::Insert(id, value, Some(after)) => [vec![0u8], id.to_bytes(), after.to_bytes(), value.to_bytes()].concat(),
+            RGAOp::Insert(id, value, None) => [vec![1u8], id.to_bytes(), value.to_bytes()].concat(),
+            RGAOp::Delete(id) => [vec![2u8], id.to_bytes()].concat(),
+        }
+    }
+}
+
 pub struct RGA<I, V>
 where
-    I: PartialEq + Eq + Hash + Clone + PartialOrd,
+    I: PartialEq + Eq + Hash + Clone + PartialOrd + SpanId,
     V: PartialEq + Eq + Hash + Clone,
 {
     elements: OrderedList<I, V>,
@@ -22,7 +67,7 @@ where
 
 impl<I, V> RGA<I, V>
 where
-    I: PartialEq + Eq + Hash + Clone + PartialOrd,
+    I: PartialEq + Eq + Hash + Clone + PartialOrd + SpanId,
     V: PartialEq + Eq + Hash + Clone,
 {
     pub fn new() -> Self {
@@ -61,20 +106,20 @@ where
         }
     }
     
+    /// Removes tombstones for which `is_stable` returns true and returns their IDs. The
+    /// caller must guarantee that no operation delivered later refers to these IDs.
+    pub fn collect_tombstones(&mut self, is_stable: impl FnMut(&I) -> bool) -> Vec<I> {
+        self.elements.purge_tombstones(is_stable)
+    }
+    
     pub fn raw_delete(&mut self, idx: usize) -> Option<RGAOp<I, V>> {
-        let iter = self.elements.elements.iter().enumerate();
-        for (i, (id, _, _)) in iter {
-            if i == idx {
-                return Some(RGAOp::Delete(id.clone()));
-            }
-        }
-        None
+        self.elements.get_by_raw_idx(idx).map(|(id, _, _)| RGAOp::Delete(id))
     }
 }
 
 impl<I, V> CRDT<RGAOp<I, V>> for RGA<I, V>
 where
-    I: PartialEq + Eq + Hash + Clone + PartialOrd,
+    I: PartialEq + Eq + Hash + Clone + PartialOrd + SpanId,
     V: PartialEq + Eq + Hash + Clone,
 {
     fn interpret_op(&mut self, op: &RGAOp<I, V>) {
@@ -123,4 +168,25 @@ mod tests {
         let insert_op = rga.insert(1, "a", 0);
         assert!(insert_op.is_none());
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_collect_tombstones() {
+        let mut rga = RGA::new();
+        for (i, v) in ["a", "b", "c"].iter().enumerate() {
+            let insert_op = rga.insert(i, *v, i).unwrap();
+            rga.interpret_op(&insert_op);
+        }
+        let delete_op = rga.delete(1).unwrap();
+        rga.interpret_op(&delete_op);
+        // "c" was inserted after "b" with a greater ID, so "b" has to stay to keep later
+        // inserts at the right position
+        assert_eq!(rga.collect_tombstones(|_| true), Vec::<usize>::new());
+        let delete_op = rga.delete(1).unwrap();
+        rga.interpret_op(&delete_op);
+        assert_eq!(rga.collect_tombstones(|_| true), vec![2, 1]);
+        assert_eq!(rga.get_list(), vec!["a"]);
+        let insert_op = rga.insert(1, "d", 3).unwrap();
+        rga.interpret_op(&insert_op);
+        assert_eq!(rga.get_list(), vec!["a", "d"]);
+    }
+}
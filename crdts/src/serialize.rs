@@ -93,4 +93,16 @@ impl<T: Serialize> Serialize for &T {
     fn to_bytes(&self) -> Vec<u8> {
         (*self).to_bytes()
     }
-}
\ No newline at end of file
+}
+
+impl<T: Serialize> Serialize for Box<T> {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.as_ref().to_bytes()
+    }
+}
+
+impl Serialize for () {
+    fn to_bytes(&self) -> Vec<u8> {
+        vec![]
+    }
+}
@@ -67,7 +67,7 @@ pub struct RgaNodeMessage {
     /// predecessor hashes
     #[prost(string, repeated, tag = "1")]
     pub predecessors: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
-    #[prost(oneof = "rga_node_message::Operation", tags = "2, 3")]
+    #[prost(oneof = "rga_node_message::Operation", tags = "2, 3, 4")]
     pub operation: ::core::option::Option<rga_node_message::Operation>,
 }
 /// Nested message and enum types in `RGANodeMessage`.
@@ -94,6 +94,19 @@ pub mod rga_node_message {
     }
     #[allow(clippy::derive_partial_eq_without_eq)]
     #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct MoveMessage {
+        /// (String, String) elem_id of the moved element
+        #[prost(message, optional, tag = "1")]
+        pub elem_id: ::core::option::Option<ElemId>,
+        /// String id of the new position
+        #[prost(string, tag = "2")]
+        pub id: ::prost::alloc::string::String,
+        /// (String, String) elem_id
+        #[prost(message, optional, tag = "3")]
+        pub after: ::core::option::Option<ElemId>,
+    }
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
     pub struct ElemId {
         /// First part of the tuple
         #[prost(string, tag = "1")]
@@ -111,6 +124,9 @@ pub mod rga_node_message {
         /// Delete operation
         #[prost(message, tag = "3")]
         Delete(DeleteMessage),
+        /// Move operation
+        #[prost(message, tag = "4")]
+        Move(MoveMessage),
     }
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -127,6 +143,65 @@ pub struct RgaResponse {
     #[prost(string, tag = "1")]
     pub result: ::prost::alloc::string::String,
 }
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PnCounterNodeMessage {
+    /// predecessor hashes
+    #[prost(string, repeated, tag = "1")]
+    pub predecessors: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(oneof = "pn_counter_node_message::Operation", tags = "2, 3")]
+    pub operation: ::core::option::Option<pn_counter_node_message::Operation>,
+}
+/// Nested message and enum types in `PNCounterNodeMessage`.
+pub mod pn_counter_node_message {
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct IncrementMessage {
+        /// author of the increment
+        #[prost(string, tag = "1")]
+        pub author: ::prost::alloc::string::String,
+        /// amount to add
+        #[prost(int64, tag = "2")]
+        pub amount: i64,
+    }
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct DecrementMessage {
+        /// author of the decrement
+        #[prost(string, tag = "1")]
+        pub author: ::prost::alloc::string::String,
+        /// amount to subtract
+        #[prost(int64, tag = "2")]
+        pub amount: i64,
+    }
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Operation {
+        /// Increment operation
+        #[prost(message, tag = "2")]
+        Increment(IncrementMessage),
+        /// Decrement operation
+        #[prost(message, tag = "3")]
+        Decrement(DecrementMessage),
+    }
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PnCounterRequest {
+    /// History
+    #[prost(message, repeated, tag = "1")]
+    pub nodes: ::prost::alloc::vec::Vec<PnCounterNodeMessage>,
+    /// per-author cap, if any
+    #[prost(int64, optional, tag = "2")]
+    pub author_cap: ::core::option::Option<i64>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PnCounterResponse {
+    /// value of the counter
+    #[prost(int64, tag = "1")]
+    pub value: i64,
+}
 /// Generated client implementations.
 pub mod bftcrdt_tester_service_client {
     #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
@@ -260,6 +335,36 @@ pub mod bftcrdt_tester_service_client {
                 );
             self.inner.unary(req, path, codec).await
         }
+        pub async fn test_pn_counter_once(
+            &mut self,
+            request: impl tonic::IntoRequest<super::PnCounterRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::PnCounterResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/bftcrdtrpc.BFTCRDTTesterService/testPNCounterOnce",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "bftcrdtrpc.BFTCRDTTesterService",
+                        "testPNCounterOnce",
+                    ),
+                );
+            self.inner.unary(req, path, codec).await
+        }
     }
 }
 /// Generated server implementations.
@@ -277,6 +382,13 @@ pub mod bftcrdt_tester_service_server {
             &self,
             request: tonic::Request<super::RgaRequest>,
         ) -> std::result::Result<tonic::Response<super::RgaResponse>, tonic::Status>;
+        async fn test_pn_counter_once(
+            &self,
+            request: tonic::Request<super::PnCounterRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::PnCounterResponse>,
+            tonic::Status,
+        >;
     }
     #[derive(Debug)]
     pub struct BftcrdtTesterServiceServer<T: BftcrdtTesterService> {
@@ -450,6 +562,52 @@ pub mod bftcrdt_tester_service_server {
                     };
                     Box::pin(fut)
                 }
+                "/bftcrdtrpc.BFTCRDTTesterService/testPNCounterOnce" => {
+                    #[allow(non_camel_case_types)]
+                    struct testPNCounterOnceSvc<T: BftcrdtTesterService>(pub Arc<T>);
+                    impl<
+                        T: BftcrdtTesterService,
+                    > tonic::server::UnaryService<super::PnCounterRequest>
+                    for testPNCounterOnceSvc<T> {
+                        type Response = super::PnCounterResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::PnCounterRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                (*inner).test_pn_counter_once(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = testPNCounterOnceSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => {
                     Box::pin(async move {
                         Ok(
@@ -1,6 +1,6 @@
-use crdts::bft_crdts::bft_crdt::{BFTCRDTGenerator};
 use crdts::bft_crdts::bft_orset::{BFTORSet, BFTORSetOp};
 use crdts::bft_crdts::hash_graph::{Node};
+use crdts::bft_crdts::replica::{OrphanPolicy, Replica, ReplicaPolicy};
 use tracing::{error, info};
 use protocol::bftcrdtrpc::bftcrdt_tester_service_client::BftcrdtTesterServiceClient;
 use protocol::bftcrdtrpc::or_set_node_message::{AddMessage, Operation, RemMessage};
@@ -10,32 +10,56 @@ use rand;
 use rand::Rng;
 use rand::SeedableRng;
 use rand::rngs::StdRng;
+use rand_pcg::Pcg32;
 
 const MAX_NATURAL_NUMBER: i32 = 200;
 
 pub struct ORSetGenerator {
-    generator: BFTCRDTGenerator<BFTORSetOp<i32>, BFTORSet<i32>>,
+    replica: Replica<BFTORSetOp<i32>, BFTORSet<i32>>,
     rng: StdRng,
+    // draws the predecessors of the generated nodes
+    node_rng: Pcg32,
 }
 
 impl ORSetGenerator {
     pub fn new(seed: u64) -> Self {
         let orset = BFTORSet::new();
-        let generator = BFTCRDTGenerator::new(orset, seed);
         ORSetGenerator {
-            generator,
+            replica: Replica::with_policy(orset, Self::replica_policy()),
             rng: StdRng::seed_from_u64(seed),
+            node_rng: Pcg32::seed_from_u64(seed),
         }
     }
+
+    // the server drops nodes that are not structurally valid, and so does the generator
+    fn replica_policy() -> ReplicaPolicy {
+        ReplicaPolicy { orphans: OrphanPolicy::Drop, ..Default::default() }
+    }
+
+    // handles a node the way the server will, so that later operations are generated from the same state
+    fn handle_remote(&mut self, node: Node<BFTORSetOp<i32>>) -> Node<BFTORSetOp<i32>> {
+        self.replica.handle_remote_node(node.clone());
+        node
+    }
+
+    fn handle_random_struct_valid(&mut self, op: BFTORSetOp<i32>) -> Node<BFTORSetOp<i32>> {
+        let node = self.replica.random_struct_valid_node(&mut self.node_rng, op);
+        self.handle_remote(node)
+    }
+
+    fn handle_random(&mut self, op: BFTORSetOp<i32>) -> Node<BFTORSetOp<i32>> {
+        let node = self.replica.random_node(&mut self.node_rng, op);
+        self.handle_remote(node)
+    }
     
     pub fn generate_valid_add(&mut self) -> Node<BFTORSetOp<i32>> {
         let element = self.rng.gen_range(0..MAX_NATURAL_NUMBER);
-        let add_op =  self.generator.crdt.add(element);
-        self.generator.generate_and_interpret_valid_node(add_op)
+        let add_op =  self.replica.crdt.add(element);
+        self.replica.handle_local_op(add_op)
     }
     
     pub fn generate_valid_rem(&mut self) -> Node<BFTORSetOp<i32>> {
-        let all_values = self.generator.crdt.get_set();
+        let all_values = self.replica.crdt.get_set();
         // Check if the set is empty
         if all_values.is_empty() {
             // If empty, generate an add operation instead
@@ -44,19 +68,19 @@ impl ORSetGenerator {
         // randomly choose an element to remove
         let random_idx = self.rng.gen_range(0..all_values.len());
         let element = all_values.iter().nth(random_idx).unwrap().clone();
-        let rem_op = self.generator.crdt.remove_elem(element);
-        self.generator.generate_and_interpret_valid_node(rem_op)
+        let rem_op = self.replica.crdt.remove_elem(element);
+        self.replica.handle_local_op(rem_op)
     }
     
     pub fn generate_random_struct_valid_add_node(&mut self) -> Node<BFTORSetOp<i32>> {
         // generate random number from 0 to MAX_NATURAL_NUMBER
         let element = self.rng.gen_range(0..MAX_NATURAL_NUMBER);
-        let add_op = self.generator.crdt.add(element);
-        self.generator.generate_and_interpret_random_struct_valid_node(add_op)
+        let add_op = self.replica.crdt.add(element);
+        self.handle_random_struct_valid(add_op)
     }
     
     pub fn generate_random_struct_valid_rem_node(&mut self) -> Node<BFTORSetOp<i32>> {
-        let all_values = self.generator.crdt.get_set();
+        let all_values = self.replica.crdt.get_set();
         // Check if the set is empty
         if all_values.is_empty() {
             // If empty, generate an add operation instead
@@ -65,20 +89,20 @@ impl ORSetGenerator {
         // randomly choose an element to remove
         let random_idx = self.rng.gen_range(0..all_values.len());
         let element = all_values.iter().nth(random_idx).unwrap().clone();
-        let rem_op = self.generator.crdt.remove_elem(element);
-        self.generator.generate_and_interpret_random_struct_valid_node(rem_op)
+        let rem_op = self.replica.crdt.remove_elem(element);
+        self.handle_random_struct_valid(rem_op)
     }
     
     pub fn generate_random_add(&mut self) -> Node<BFTORSetOp<i32>> {
         let element = self.rng.gen_range(0..MAX_NATURAL_NUMBER);
-        let add_op = self.generator.crdt.add(element);
-        self.generator.generate_and_interpret_random_node(add_op)
+        let add_op = self.replica.crdt.add(element);
+        self.handle_random(add_op)
     }
     
     pub fn generate_random_rem(&mut self) -> Node<BFTORSetOp<i32>> {
         let element = self.rng.gen_range(0..MAX_NATURAL_NUMBER);
-        let rem_op = self.generator.crdt.remove_elem(element);
-        self.generator.generate_and_interpret_random_node(rem_op)
+        let rem_op = self.replica.crdt.remove_elem(element);
+        self.handle_random(rem_op)
     }
 }
 
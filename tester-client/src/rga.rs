@@ -1,109 +1,132 @@
-use crdts::bft_crdts::bft_crdt::BFTCRDTGenerator;
 use crdts::bft_crdts::bft_rga::{BFTRGA, BFTRGAOp};
 use crdts::bft_crdts::hash_graph::Node;
+use crdts::bft_crdts::replica::{OrphanPolicy, Replica, ReplicaPolicy};
 use tracing::{error, info};
 use protocol::bftcrdtrpc::bftcrdt_tester_service_client::BftcrdtTesterServiceClient;
-use protocol::bftcrdtrpc::rga_node_message::{InsertMessage, Operation, DeleteMessage, ElemId};
+use protocol::bftcrdtrpc::rga_node_message::{InsertMessage, Operation, DeleteMessage, MoveMessage, ElemId};
 use protocol::bftcrdtrpc::RgaRequest;
 use crate::cli::Args;
-use rand::{Rng};
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
 
 const MAX_VALUE: i32 = 100;
 
 pub struct RGAGenerator {
-    generator: BFTCRDTGenerator<BFTRGAOp<String, i32>, BFTRGA<String, i32>>,
+    replica: Replica<BFTRGAOp<String, i32>, BFTRGA<String, i32>>,
+    rng: Pcg32,
 }
 
 impl RGAGenerator {
     pub fn new(seed: u64) -> Self {
-        let rga = BFTRGA::new();
-        let generator = BFTCRDTGenerator::new(rga, seed);
+        let rga = BFTRGA::default();
         RGAGenerator {
-            generator,
+            replica: Replica::with_policy(rga, Self::replica_policy()),
+            rng: Pcg32::seed_from_u64(seed),
         }
     }
+
+    // the server drops nodes that are not structurally valid, and so does the generator
+    fn replica_policy() -> ReplicaPolicy {
+        ReplicaPolicy { orphans: OrphanPolicy::Drop, ..Default::default() }
+    }
+
+    // handles a node the way the server will, so that later operations are generated from the same state
+    fn handle_remote(&mut self, node: Node<BFTRGAOp<String, i32>>) -> Node<BFTRGAOp<String, i32>> {
+        self.replica.handle_remote_node(node.clone());
+        node
+    }
+
+    fn handle_random_struct_valid(&mut self, op: BFTRGAOp<String, i32>) -> Node<BFTRGAOp<String, i32>> {
+        let node = self.replica.random_struct_valid_node(&mut self.rng, op);
+        self.handle_remote(node)
+    }
+
+    fn handle_random(&mut self, op: BFTRGAOp<String, i32>) -> Node<BFTRGAOp<String, i32>> {
+        let node = self.replica.random_node(&mut self.rng, op);
+        self.handle_remote(node)
+    }
     
     pub fn generate_valid_insert(&mut self) -> Node<BFTRGAOp<String, i32>> {
-        let value = self.generator.rng.gen_range(0..MAX_VALUE);
-        let list_size = self.generator.crdt.get_list().len();
-        let insert_idx = if list_size == 0 { 0 } else { self.generator.rng.gen_range(0..=list_size) };
+        let value = self.rng.gen_range(0..MAX_VALUE);
+        let list_size = self.replica.crdt.get_list().len();
+        let insert_idx = if list_size == 0 { 0 } else { self.rng.gen_range(0..=list_size) };
         // in reality, we would use this id to control the exact position of the insert, but for testing we can just generate a random id
-        let id = format!("id_{}", self.generator.rng.gen_range(0..100000));
+        let id = format!("id_{}", self.rng.gen_range(0..100000));
         
-        if let Some(insert_op) = self.generator.crdt.insert(insert_idx, value, id) {
-            self.generator.generate_and_interpret_valid_node(insert_op)
+        if let Some(insert_op) = self.replica.crdt.insert(insert_idx, value, id) {
+            self.replica.handle_local_op(insert_op)
         } else {
             panic!("Index out of range")
         }
     }
     
     pub fn generate_valid_delete(&mut self) -> Node<BFTRGAOp<String, i32>> {
-        let list_size = self.generator.crdt.get_list().len();
+        let list_size = self.replica.crdt.get_list().len();
         if list_size == 0 {
             // If list is empty, generate an insert instead
             return self.generate_valid_insert();
         }
         
-        let delete_idx = self.generator.rng.gen_range(0..list_size);
-        if let Some(delete_op) = self.generator.crdt.delete(delete_idx) {
-            self.generator.generate_and_interpret_valid_node(delete_op)
+        let delete_idx = self.rng.gen_range(0..list_size);
+        if let Some(delete_op) = self.replica.crdt.delete(delete_idx) {
+            self.replica.handle_local_op(delete_op)
         } else {
             panic!("Index out of range")
         }
     }
     
     pub fn generate_random_struct_valid_insert(&mut self) -> Node<BFTRGAOp<String, i32>> {
-        let value = self.generator.rng.gen_range(0..MAX_VALUE);
-        let list_size = self.generator.crdt.get_list().len();
-        let insert_idx = if list_size == 0 { 0 } else { self.generator.rng.gen_range(0..=list_size) };
-        let id = format!("id_{}", self.generator.rng.gen_range(0..100000));
+        let value = self.rng.gen_range(0..MAX_VALUE);
+        let list_size = self.replica.crdt.get_list().len();
+        let insert_idx = if list_size == 0 { 0 } else { self.rng.gen_range(0..=list_size) };
+        let id = format!("id_{}", self.rng.gen_range(0..100000));
         
-        if let Some(insert_op) = self.generator.crdt.insert(insert_idx, value, id) {
-            self.generator.generate_and_interpret_random_struct_valid_node(insert_op)
+        if let Some(insert_op) = self.replica.crdt.insert(insert_idx, value, id) {
+            self.handle_random_struct_valid(insert_op)
         } else {
             panic!("Index out of range")
         }
     }
     
     pub fn generate_random_struct_valid_delete(&mut self) -> Node<BFTRGAOp<String, i32>> {
-        let list_size = self.generator.crdt.get_list().len();
+        let list_size = self.replica.crdt.get_list().len();
         if list_size == 0 {
             // If list is empty, generate an insert instead
             return self.generate_random_struct_valid_insert();
         }
         
-        let delete_idx = self.generator.rng.gen_range(0..list_size);
-        if let Some(delete_op) = self.generator.crdt.delete(delete_idx) {
-            self.generator.generate_and_interpret_random_struct_valid_node(delete_op)
+        let delete_idx = self.rng.gen_range(0..list_size);
+        if let Some(delete_op) = self.replica.crdt.delete(delete_idx) {
+            self.handle_random_struct_valid(delete_op)
         } else {
             panic!("Index out of range")
         }
     }
     
     pub fn generate_random_insert(&mut self) -> Node<BFTRGAOp<String, i32>> {
-        let value = self.generator.rng.gen_range(0..MAX_VALUE);
-        let list_size = self.generator.crdt.get_list().len();
-        let insert_idx = if list_size == 0 { 0 } else { self.generator.rng.gen_range(0..=list_size) };
+        let value = self.rng.gen_range(0..MAX_VALUE);
+        let list_size = self.replica.crdt.get_list().len();
+        let insert_idx = if list_size == 0 { 0 } else { self.rng.gen_range(0..=list_size) };
         // in reality, we would use this id to control the exact position of the insert, but for testing we can just generate a random id
-        let id = format!("id_{}", self.generator.rng.gen_range(0..100000));
+        let id = format!("id_{}", self.rng.gen_range(0..100000));
 
-        if let Some(insert_op) = self.generator.crdt.insert(insert_idx, value, id) {
-            self.generator.generate_and_interpret_random_node(insert_op)
+        if let Some(insert_op) = self.replica.crdt.insert(insert_idx, value, id) {
+            self.handle_random(insert_op)
         } else {
             panic!("Index out of range")
         }
     }
     
     pub fn generate_random_delete(&mut self) -> Node<BFTRGAOp<String, i32>> {
-        let list_size = self.generator.crdt.get_list().len();
+        let list_size = self.replica.crdt.get_list().len();
         if list_size == 0 {
             // If list is empty, generate an insert instead
             return self.generate_valid_insert();
         }
 
-        let delete_idx = self.generator.rng.gen_range(0..list_size);
-        if let Some(delete_op) = self.generator.crdt.delete(delete_idx) {
-            self.generator.generate_and_interpret_random_node(delete_op)
+        let delete_idx = self.rng.gen_range(0..list_size);
+        if let Some(delete_op) = self.replica.crdt.delete(delete_idx) {
+            self.handle_random(delete_op)
         } else {
             panic!("Index out of range")
         }
@@ -165,6 +188,13 @@ impl RGAExperiment {
                     elem_id: Some(ElemId { first, second }),
                 })
             }
+            BFTRGAOp::Move((first, second), id, after) => {
+                Operation::Move(MoveMessage {
+                    elem_id: Some(ElemId { first, second }),
+                    id,
+                    after: after.map(|(first, second)| ElemId { first, second }),
+                })
+            }
         };
         
         protocol::bftcrdtrpc::RgaNodeMessage {
@@ -178,7 +208,7 @@ impl RGAExperiment {
             0 => {
                 let mut inputs = vec![];
                 for _ in 0..self.args.depth {
-                    if self.rga_generator.generator.rng.gen::<f32>() < 0.2 {
+                    if self.rga_generator.rng.gen::<f32>() < 0.2 {
                         // 20% chance to generate delete
                         let delete = self.rga_generator.generate_valid_delete();
                         inputs.push(delete);
@@ -193,9 +223,9 @@ impl RGAExperiment {
             1 => {
                 let mut inputs = vec![];
                 for _ in 0..self.args.depth {
-                    if self.rga_generator.generator.rng.gen::<f32>() < 0.5 {
+                    if self.rga_generator.rng.gen::<f32>() < 0.5 {
                         // 50% chance to generate random structurally valid node
-                        if self.rga_generator.generator.rng.gen::<f32>() < 0.2 {
+                        if self.rga_generator.rng.gen::<f32>() < 0.2 {
                             // 20% chance to generate delete
                             let delete = self.rga_generator.generate_random_struct_valid_delete();
                             inputs.push(delete);
@@ -206,7 +236,7 @@ impl RGAExperiment {
                         }
                     } else {
                         // 50% chance to generate valid node
-                        if self.rga_generator.generator.rng.gen::<f32>() < 0.2 {
+                        if self.rga_generator.rng.gen::<f32>() < 0.2 {
                             // 20% chance to generate delete
                             let delete = self.rga_generator.generate_valid_delete();
                             inputs.push(delete);
@@ -222,10 +252,10 @@ impl RGAExperiment {
             2 => {
                 let mut inputs = vec![];
                 for _ in 0..self.args.depth {
-                    let rand = self.rga_generator.generator.rng.gen::<f32>();
+                    let rand = self.rga_generator.rng.gen::<f32>();
                     if rand < 0.2 {
                         // 20% chance to generate random node
-                        if self.rga_generator.generator.rng.gen::<f32>() < 0.2 {
+                        if self.rga_generator.rng.gen::<f32>() < 0.2 {
                             // 20% chance to generate delete
                             let delete = self.rga_generator.generate_random_delete();
                             inputs.push(delete);
@@ -236,7 +266,7 @@ impl RGAExperiment {
                         }
                     } else if rand < 0.6 {
                         // 40% chance to generate random structurally valid node
-                        if self.rga_generator.generator.rng.gen::<f32>() < 0.2 {
+                        if self.rga_generator.rng.gen::<f32>() < 0.2 {
                             // 20% chance to generate delete
                             let delete = self.rga_generator.generate_random_struct_valid_delete();
                             inputs.push(delete);
@@ -247,7 +277,7 @@ impl RGAExperiment {
                         }
                     } else {
                         // 40% chance to generate valid node
-                        if self.rga_generator.generator.rng.gen::<f32>() < 0.2 {
+                        if self.rga_generator.rng.gen::<f32>() < 0.2 {
                             // 20% chance to generate delete
                             let delete = self.rga_generator.generate_valid_delete();
                             inputs.push(delete);
@@ -263,7 +293,7 @@ impl RGAExperiment {
             3 => {
                 let mut inputs = vec![];
                 for _ in 0..self.args.depth {
-                    if self.rga_generator.generator.rng.gen::<f32>() < 0.2 {
+                    if self.rga_generator.rng.gen::<f32>() < 0.2 {
                         // 20% chance to generate delete
                         let delete = self.rga_generator.generate_random_delete();
                         inputs.push(delete);
@@ -1,17 +1,25 @@
 use std::collections::HashMap;
 use protocol::bftcrdtrpc::bftcrdt_tester_service_server::{BftcrdtTesterService, BftcrdtTesterServiceServer};
-use protocol::bftcrdtrpc::{or_set_response, OrSetRequest, OrSetResponse, RgaRequest, RgaResponse};
+use protocol::bftcrdtrpc::{or_set_response, OrSetRequest, OrSetResponse, PnCounterRequest, PnCounterResponse, RgaRequest, RgaResponse};
 use tonic::{transport::Server, Request, Response, Status};
 use tracing::info;
-use crdts::bft_crdts::bft_crdt::BFTCRDTTester;
+use crdts::bft_crdts::bft_counter::{BFTPNCounter, BFTPNCounterOp};
 use crdts::bft_crdts::bft_orset::{BFTORSet, BFTORSetOp};
 use crdts::bft_crdts::bft_rga::{BFTRGAOp, BFTRGA};
 use crdts::bft_crdts::hash_graph::{Node};
+use crdts::bft_crdts::replica::{OrphanPolicy, Replica, ReplicaPolicy};
 use protocol::bftcrdtrpc::or_set_node_message::Operation as OrSetOperation;
 use protocol::bftcrdtrpc::rga_node_message::Operation as RGAOperation;
+use protocol::bftcrdtrpc::pn_counter_node_message::Operation as PNCounterOperation;
 
 mod logger;
 
+// The nodes of a request are handled in the order they are sent, and nodes whose predecessors
+// have not been handled before are dropped
+fn tester_policy() -> ReplicaPolicy {
+    ReplicaPolicy { orphans: OrphanPolicy::Drop, ..Default::default() }
+}
+
 // Our server implementation
 #[derive(Debug, Default)]
 pub struct BftCrdtTesterServer {}
@@ -23,7 +31,7 @@ impl BftcrdtTesterService for BftCrdtTesterServer {
         &self,
         request: Request<OrSetRequest>,
     ) -> Result<Response<OrSetResponse>, Status> {
-        let mut tester: BFTCRDTTester<BFTORSetOp<i32>, BFTORSet<i32>> = BFTCRDTTester::new(BFTORSet::new());
+        let mut replica: Replica<BFTORSetOp<i32>, BFTORSet<i32>> = Replica::with_policy(BFTORSet::new(), tester_policy());
 
         for node in request.into_inner().nodes {
             let op :BFTORSetOp<i32> = match node.operation {
@@ -37,15 +45,12 @@ impl BftcrdtTesterService for BftCrdtTesterServer {
                 }
                 None => return Err(Status::invalid_argument("Operation not provided")),
             };
-            let hash_node = Node {
-                predecessors: node.predecessors,
-                value: op,
-            };
+            let hash_node = Node::new(node.predecessors, op);
             
-            tester.handle_node(hash_node);
+            replica.handle_remote_node(hash_node);
         }
         let mut result_map: HashMap<i32, or_set_response::ElemIds> = Default::default();
-        for (k, v) in tester.crdt.elements.iter() {
+        for (k, v) in replica.crdt.elements.iter() {
             let mut elem_ids: Vec<String> = v.iter().map(|id| id.to_string()).collect();
             // Sort the elem_ids
             elem_ids.sort();
@@ -65,7 +70,7 @@ impl BftcrdtTesterService for BftCrdtTesterServer {
 
     async fn test_rga_once(&self, request: Request<RgaRequest>) -> Result<Response<RgaResponse>, Status> {
 
-        let mut tester: BFTCRDTTester<BFTRGAOp<String, i32>, BFTRGA<String, i32>> = BFTCRDTTester::new(BFTRGA::new());
+        let mut replica: Replica<BFTRGAOp<String, i32>, BFTRGA<String, i32>> = Replica::with_policy(BFTRGA::default(), tester_policy());
 
         for node in request.into_inner().nodes {
             let op :BFTRGAOp<String, i32> = match node.operation {
@@ -79,18 +84,20 @@ impl BftcrdtTesterService for BftCrdtTesterServer {
                         let elem_id = d.elem_id.map(|id| (id.first, id.second));
                         BFTRGAOp::Delete(elem_id.unwrap())
                     }
+                    RGAOperation::Move(m) => {
+                        let elem_id = m.elem_id.map(|id| (id.first, id.second));
+                        let after = m.after.map(|id| (id.first, id.second));
+                        BFTRGAOp::Move(elem_id.unwrap(), m.id, after)
+                    }
                 }
                 None => return Err(Status::invalid_argument("Operation not provided")),
             };
-            let hash_node = Node {
-                predecessors: node.predecessors,
-                value: op,
-            };
+            let hash_node = Node::new(node.predecessors, op);
 
-            tester.handle_node(hash_node);
+            replica.handle_remote_node(hash_node);
         }
         
-        let int_list = tester.crdt.get_list();
+        let int_list = replica.crdt.get_list();
         let result: String = int_list.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(",");
         
         let reply = RgaResponse {
@@ -98,6 +105,38 @@ impl BftcrdtTesterService for BftCrdtTesterServer {
         };
         Ok(Response::new(reply))
     }
+
+    async fn test_pn_counter_once(&self, request: Request<PnCounterRequest>) -> Result<Response<PnCounterResponse>, Status> {
+        let request = request.into_inner();
+        let counter = match request.author_cap {
+            // the tester names the author of each node in the clear and vouches for it
+            Some(cap) => BFTPNCounter::with_author_cap(cap, |node: &Node<BFTPNCounterOp<String>>| Some(node.value.author().clone())),
+            None => BFTPNCounter::new(),
+        };
+        let mut replica: Replica<BFTPNCounterOp<String>, BFTPNCounter<String>> = Replica::with_policy(counter, tester_policy());
+
+        for node in request.nodes {
+            let op :BFTPNCounterOp<String> = match node.operation {
+                Some(inner_op) => match inner_op {
+                    PNCounterOperation::Increment(i) => {
+                        BFTPNCounterOp::Increment(i.author, i.amount)
+                    }
+                    PNCounterOperation::Decrement(d) => {
+                        BFTPNCounterOp::Decrement(d.author, d.amount)
+                    }
+                }
+                None => return Err(Status::invalid_argument("Operation not provided")),
+            };
+            let hash_node = Node::new(node.predecessors, op);
+
+            replica.handle_remote_node(hash_node);
+        }
+
+        let reply = PnCounterResponse {
+            value: replica.crdt.get_value(),
+        };
+        Ok(Response::new(reply))
+    }
 }
 
 #[tokio::main]